@@ -0,0 +1,90 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargePortOverride {
+    /// Let the EC choose the charge port
+    Off,
+    /// Don't charge from any port
+    DontCharge,
+    Port(u8),
+}
+
+impl From<ChargePortOverride> for i16 {
+    fn from(value: ChargePortOverride) -> Self {
+        match value {
+            ChargePortOverride::Off => -1,
+            ChargePortOverride::DontCharge => -2,
+            ChargePortOverride::Port(port) => port as i16,
+        }
+    }
+}
+
+/// The charging modes of USB-A ports
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum UsbChargeMode {
+    /// Disable both charging and data
+    Disabled,
+    /// Standard downstream port with 500 mA
+    Sdp2,
+    /// Charging downstream port with BC 1.2
+    Cdp,
+    /// Dedicated charging port with BC 1.2, where D+ and D- are shorted
+    DcpShort,
+    /// The board's default charging mode
+    Enabled,
+    /// The mode set in the board config
+    Default,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsUsbChargeSetMode {
+    usb_port_id: u8,
+    /// The lower 7 bits are the mode and the top bit inhibits charging
+    mode: u8,
+}
+
+/// Gets the number of ports which the device can charge from, including dedicated charging ports
+pub fn charge_port_count<File: AsRawFd>(file: &mut File) -> EcCmdResult<u8> {
+    ec_command_bytemuck(CrosEcCmd::ChargePortCount, 0, &(), file.as_raw_fd())
+}
+
+/// Forces the device to charge from a specific port, or not charge at all
+pub fn pd_charge_port_override<File: AsRawFd>(
+    file: &mut File,
+    charge_port_override: ChargePortOverride,
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::PdChargePortOverride,
+        0,
+        &i16::from(charge_port_override),
+        file.as_raw_fd(),
+    )
+}
+
+/// Sets the charging mode of a USB-A port.
+/// If `inhibit_charge` is true, the EC doesn't charge the port until the mode is set again.
+pub fn usb_charge_set_mode<File: AsRawFd>(
+    file: &mut File,
+    usb_port_id: u8,
+    mode: UsbChargeMode,
+    inhibit_charge: bool,
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::UsbChargeSetMode,
+        0,
+        &EcParamsUsbChargeSetMode {
+            usb_port_id,
+            mode: mode as u8 | (inhibit_charge as u8) << 7,
+        },
+        file.as_raw_fd(),
+    )
+}
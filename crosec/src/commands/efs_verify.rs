@@ -0,0 +1,11 @@
+use std::os::fd::AsRawFd;
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::{flash::FlashRegion, CrosEcCmd};
+
+/// Makes the EC verify the hash of an RW image with early firmware selection (EFS).
+/// This fails if the image is invalid, or if the EC doesn't support EFS.
+pub fn efs_verify<File: AsRawFd>(file: &mut File, region: FlashRegion) -> EcCmdResult<()> {
+    ec_command_bytemuck(CrosEcCmd::EfsVerify, 0, &(region as u8), file.as_raw_fd())
+}
@@ -0,0 +1,440 @@
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use bytemuck::{bytes_of, pod_read_unaligned, Pod, Zeroable};
+use strum_macros::{EnumIter, IntoStaticStr};
+
+use crate::{
+    ec_command::{ec_command_bytemuck, ec_command_with_dynamic_output_size},
+    EcCmdResult, EcError, EcResponseStatus,
+};
+
+use super::{
+    get_cmd_versions::{ec_cmd_get_cmd_versions, V1},
+    get_protocol_info::EcResponseGetProtocolInfo,
+    CrosEcCmd,
+};
+
+/// Erased flash reads as 0s instead of 1s
+pub const EC_FLASH_INFO_ERASE_TO_0: u32 = 1 << 0;
+/// The flash must be selected with `EC_CMD_FLASH_SELECT` before it is accessed
+pub const EC_FLASH_INFO_SELECT_REQUIRED: u32 = 1 << 1;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseFlashInfoV1 {
+    flash_size: u32,
+    write_block_size: u32,
+    erase_block_size: u32,
+    protect_block_size: u32,
+    write_ideal_size: u32,
+    flags: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlashInfoV1 {
+    pub flash_size: u32,
+    pub write_block_size: u32,
+    pub erase_block_size: u32,
+    pub protect_block_size: u32,
+    /// The ideal write size in bytes. Writes are fastest in multiples of this. Only available with version 1.
+    pub write_ideal_size: Option<u32>,
+    /// See `EC_FLASH_INFO_*`. Only available with version 1.
+    pub flags: Option<u32>,
+}
+
+/// Gets the size of the flash and its blocks with version 0 or 1
+pub fn flash_info<File: AsRawFd>(file: &mut File, version: u8) -> EcCmdResult<FlashInfoV1> {
+    let response_size = match version {
+        0 => size_of::<u32>() * 4,
+        _ => size_of::<EcResponseFlashInfoV1>(),
+    };
+    let mut response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::FlashInfo,
+        version,
+        &[],
+        response_size,
+        file.as_raw_fd(),
+    )?;
+    response.resize(size_of::<EcResponseFlashInfoV1>(), 0);
+    let info: EcResponseFlashInfoV1 = pod_read_unaligned(&response);
+    Ok(FlashInfoV1 {
+        flash_size: info.flash_size,
+        write_block_size: info.write_block_size,
+        erase_block_size: info.erase_block_size,
+        protect_block_size: info.protect_block_size,
+        write_ideal_size: (version >= 1).then_some(info.write_ideal_size),
+        flags: (version >= 1).then_some(info.flags),
+    })
+}
+
+/// Gets the flash info with version 1 if the EC supports it, and version 0 if it doesn't
+pub fn flash_info_latest<File: AsRawFd>(file: &mut File) -> EcCmdResult<FlashInfoV1> {
+    let versions = ec_cmd_get_cmd_versions(file, CrosEcCmd::FlashInfo)?;
+    flash_info(file, if versions & V1 != 0 { 1 } else { 0 })
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseFlashInfoV2 {
+    flash_size: u32,
+    flags: u32,
+    write_ideal_size: u32,
+    num_banks_total: u16,
+    num_banks_desc: u16,
+}
+
+/// A group of banks which are the same
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcFlashBank {
+    /// The number of banks of this type
+    pub count: u16,
+    /// The size of each bank is `1 << size_exp` bytes
+    pub size_exp: u8,
+    /// The write block size is `1 << write_size_exp` bytes
+    pub write_size_exp: u8,
+    /// The erase block size is `1 << erase_size_exp` bytes
+    pub erase_size_exp: u8,
+    /// The protect block size is `1 << protect_size_exp` bytes
+    pub protect_size_exp: u8,
+    reserved: [u8; 2],
+}
+
+impl EcFlashBank {
+    pub fn size(&self) -> u32 {
+        1 << self.size_exp
+    }
+
+    pub fn write_size(&self) -> u32 {
+        1 << self.write_size_exp
+    }
+
+    pub fn erase_size(&self) -> u32 {
+        1 << self.erase_size_exp
+    }
+
+    pub fn protect_size(&self) -> u32 {
+        1 << self.protect_size_exp
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FlashInfoV2 {
+    pub flash_size: u32,
+    /// See `EC_FLASH_INFO_*`
+    pub flags: u32,
+    pub write_ideal_size: u32,
+    pub num_banks_total: u16,
+    pub banks: Vec<EcFlashBank>,
+}
+
+/// Gets the size of the flash, with a descriptor of each type of bank.
+/// Banks with different sizes can only be described with version 2.
+pub fn flash_info_v2<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+) -> EcCmdResult<FlashInfoV2> {
+    let max_banks_desc = (protocol_info.max_ec_output_size() - size_of::<EcResponseFlashInfoV2>())
+        / size_of::<EcFlashBank>();
+    let mut params = (max_banks_desc.min(u16::MAX as usize) as u16)
+        .to_le_bytes()
+        .to_vec();
+    params.extend_from_slice(&[0; 2]);
+    let response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::FlashInfo,
+        2,
+        &params,
+        size_of::<EcResponseFlashInfoV2>() + max_banks_desc * size_of::<EcFlashBank>(),
+        file.as_raw_fd(),
+    )?;
+    let (info, banks) = response.split_at(size_of::<EcResponseFlashInfoV2>());
+    let info: EcResponseFlashInfoV2 = pod_read_unaligned(info);
+    Ok(FlashInfoV2 {
+        flash_size: info.flash_size,
+        flags: info.flags,
+        write_ideal_size: info.write_ideal_size,
+        num_banks_total: info.num_banks_total,
+        banks: banks
+            .chunks_exact(size_of::<EcFlashBank>())
+            .take(info.num_banks_desc as usize)
+            .map(pod_read_unaligned)
+            .collect(),
+    })
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsFlashRange {
+    offset: u32,
+    size: u32,
+}
+
+/// Reads `size` bytes of flash, in as many commands as needed
+pub fn flash_read<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+    offset: u32,
+    size: u32,
+) -> EcCmdResult<Vec<u8>> {
+    let max_chunk_size = protocol_info.max_ec_output_size() as u32;
+    let mut data = Vec::with_capacity(size as usize);
+    while (data.len() as u32) < size {
+        let chunk_size = (size - data.len() as u32).min(max_chunk_size);
+        data.extend(ec_command_with_dynamic_output_size(
+            CrosEcCmd::FlashRead,
+            0,
+            bytes_of(&EcParamsFlashRange {
+                offset: offset + data.len() as u32,
+                size: chunk_size,
+            }),
+            chunk_size as usize,
+            file.as_raw_fd(),
+        )?);
+    }
+    Ok(data)
+}
+
+/// The size of the chunks which [`flash_write`] writes, like the C ectool:
+/// the ideal write size, capped at what fits in a request and rounded down to a multiple of the write block size,
+/// so every chunk starts at an offset the EC accepts
+fn flash_write_chunk_size(
+    protocol_info: &EcResponseGetProtocolInfo,
+    flash_info: &FlashInfoV1,
+) -> usize {
+    let max_chunk_size = protocol_info.max_ec_input_size() - size_of::<EcParamsFlashRange>();
+    let chunk_size = match flash_info.write_ideal_size {
+        Some(write_ideal_size) if write_ideal_size != 0 => {
+            (write_ideal_size as usize).min(max_chunk_size)
+        }
+        _ => max_chunk_size,
+    };
+    let write_block_size = flash_info.write_block_size.max(1) as usize;
+    (chunk_size - chunk_size % write_block_size).max(write_block_size)
+}
+
+/// Writes to flash, which must be erased first, in chunks that fit in a request.
+/// `offset` must be a multiple of the write block size.
+pub fn flash_write<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+    flash_info: &FlashInfoV1,
+    offset: u32,
+    data: &[u8],
+) -> EcCmdResult<()> {
+    let chunk_size = flash_write_chunk_size(protocol_info, flash_info);
+    for (chunk_index, chunk) in data.chunks(chunk_size).enumerate() {
+        let mut params = bytes_of(&EcParamsFlashRange {
+            offset: offset + (chunk_index * chunk_size) as u32,
+            size: chunk.len() as u32,
+        })
+        .to_vec();
+        params.extend_from_slice(chunk);
+        ec_command_with_dynamic_output_size(
+            CrosEcCmd::FlashWrite,
+            1,
+            &params,
+            0,
+            file.as_raw_fd(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Erases flash. `offset` and `size` must be multiples of the erase block size.
+/// The EC doesn't respond until the erase is done, which can take longer than the kernel's timeout for big erases.
+/// For those, use [`flash_erase_async`].
+pub fn flash_erase<File: AsRawFd>(file: &mut File, offset: u32, size: u32) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::FlashErase,
+        0,
+        &EcParamsFlashRange { offset, size },
+        file.as_raw_fd(),
+    )
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum FlashEraseCommand {
+    SectorAsync = 1,
+    GetResult,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsFlashEraseV1 {
+    cmd: u8,
+    reserved: u8,
+    flag: u16,
+    params: EcParamsFlashRange,
+}
+
+fn flash_erase_v1<File: AsRawFd>(
+    file: &mut File,
+    command: FlashEraseCommand,
+    offset: u32,
+    size: u32,
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::FlashErase,
+        1,
+        &EcParamsFlashEraseV1 {
+            cmd: command as u8,
+            reserved: 0,
+            flag: 0,
+            params: EcParamsFlashRange { offset, size },
+        },
+        file.as_raw_fd(),
+    )
+}
+
+/// Starts erasing flash in the background. Use [`flash_erase_async_done`] to check when it's done.
+pub fn flash_erase_async_start<File: AsRawFd>(
+    file: &mut File,
+    offset: u32,
+    size: u32,
+) -> EcCmdResult<()> {
+    flash_erase_v1(file, FlashEraseCommand::SectorAsync, offset, size)
+}
+
+/// Returns `true` once the erase started with [`flash_erase_async_start`] is done, or an error if it failed
+pub fn flash_erase_async_done<File: AsRawFd>(file: &mut File) -> EcCmdResult<bool> {
+    match flash_erase_v1(file, FlashEraseCommand::GetResult, 0, 0) {
+        Ok(()) => Ok(true),
+        Err(EcError::Response(EcResponseStatus::Busy | EcResponseStatus::InProgress)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Erases flash in the background, polling every `poll_interval` until it's done.
+/// `on_poll` is called with the time since the erase started every time the erase is still in progress.
+pub fn flash_erase_async<File: AsRawFd>(
+    file: &mut File,
+    offset: u32,
+    size: u32,
+    poll_interval: Duration,
+    mut on_poll: impl FnMut(Duration),
+) -> EcCmdResult<()> {
+    let start = Instant::now();
+    flash_erase_async_start(file, offset, size)?;
+    while !flash_erase_async_done(file)? {
+        on_poll(start.elapsed());
+        sleep(poll_interval);
+    }
+    Ok(())
+}
+
+#[repr(u32)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum FlashProtectFlag {
+    /// RO flash code is protected when the EC boots
+    RoAtBoot = 1 << 0,
+    /// RO flash code is protected now. If this is set, `RoAtBoot` is also set.
+    RoNow = 1 << 1,
+    /// The entire flash is protected until the EC reboots
+    AllNow = 1 << 2,
+    /// The write protect GPIO is asserted
+    GpioAsserted = 1 << 3,
+    /// The flash is stuck protected, usually because the write protect GPIO was asserted
+    ErrorStuck = 1 << 4,
+    /// The flash protection doesn't match the at-boot flags
+    ErrorInconsistent = 1 << 5,
+    /// The entire flash is protected when the EC boots
+    AllAtBoot = 1 << 6,
+    /// RW flash code is protected when the EC boots
+    RwAtBoot = 1 << 7,
+    /// RW flash code is protected now
+    RwNow = 1 << 8,
+    /// The rollback region is protected when the EC boots
+    RollbackAtBoot = 1 << 9,
+    /// The rollback region is protected now
+    RollbackNow = 1 << 10,
+    /// The EC couldn't get the protection state
+    ErrorUnknown = 1 << 11,
+}
+
+flag_set!(FlashProtectFlags, FlashProtectFlag, u32);
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsFlashProtect {
+    mask: u32,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseFlashProtect {
+    flags: u32,
+    valid_flags: u32,
+    writable_flags: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlashProtect {
+    pub flags: FlashProtectFlags,
+    /// The flags which the EC supports
+    pub valid_flags: FlashProtectFlags,
+    /// The flags which can be changed right now
+    pub writable_flags: FlashProtectFlags,
+}
+
+/// Sets the flags in `mask` to their value in `flags`, and gets the flash protection state.
+/// Use an empty mask to only get the state.
+pub fn flash_protect<File: AsRawFd>(
+    file: &mut File,
+    mask: FlashProtectFlags,
+    flags: FlashProtectFlags,
+) -> EcCmdResult<FlashProtect> {
+    let response: EcResponseFlashProtect = ec_command_bytemuck(
+        CrosEcCmd::FlashProtect,
+        1,
+        &EcParamsFlashProtect {
+            mask: mask.0,
+            flags: flags.0,
+        },
+        file.as_raw_fd(),
+    )?;
+    Ok(FlashProtect {
+        flags: FlashProtectFlags(response.flags),
+        valid_flags: FlashProtectFlags(response.valid_flags),
+        writable_flags: FlashProtectFlags(response.writable_flags),
+    })
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum FlashRegion {
+    /// The RO image, which is also the region which is protected by write protect
+    Ro,
+    /// The image which is currently running
+    Active,
+    /// The region which is protected by write protect. This may be bigger than the RO image.
+    WpRo,
+    /// The image which can be updated, which is the one that isn't running
+    Update,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcResponseFlashRegionInfo {
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// Gets the offset and size of a region of flash
+pub fn flash_region_info<File: AsRawFd>(
+    file: &mut File,
+    region: FlashRegion,
+) -> EcCmdResult<EcResponseFlashRegionInfo> {
+    ec_command_bytemuck(
+        CrosEcCmd::FlashRegionInfo,
+        1,
+        &(region as u32),
+        file.as_raw_fd(),
+    )
+}
@@ -2,7 +2,9 @@ use std::{mem::offset_of, os::fd::AsRawFd};
 
 use bytemuck::{bytes_of, Pod, Zeroable};
 
-use crate::{ec_command::ec_command_with_dynamic_output_size, EcCmdResult};
+use crate::{
+    ec_command::ec_command_with_dynamic_output_size, EcCmdResult, EcError, EcResponseStatus,
+};
 
 use super::{
     fp_download::FpTemplate, fp_info::EcResponseFpInfo,
@@ -20,17 +22,17 @@ struct EcParamsFpTemplateWithoutData {
 /// Flag in the 'size' field indicating that the full template has been sent
 const FP_TEMPLATE_COMMIT: u32 = 0x80000000;
 
+/// Fails with [`EcResponseStatus::InvalidParam`] without sending anything
+/// if the template isn't the size of the FPMCU's templates
 pub fn fp_upload_template<File: AsRawFd>(
     file: &mut File,
     protocol_info: &EcResponseGetProtocolInfo,
     fp_info: &EcResponseFpInfo,
     template: &FpTemplate,
 ) -> EcCmdResult<()> {
-    assert_eq!(
-        template.buffer().len(),
-        fp_info.template_size as usize,
-        "The given template must match the fp sensor's template size"
-    );
+    if template.buffer().len() != fp_info.template_size as usize {
+        return Err(EcError::Response(EcResponseStatus::InvalidParam));
+    }
     // TODO(b/78544921): removing 32 bits is a workaround for the MCU bug
     // Idk what this bug is, but the ChromiumOS ectool removes 4 bytes, so we should too
     let max_chunk_size =
@@ -27,6 +27,7 @@ struct EcResponseGetCmdVersion {
 pub const V0: u32 = 0b001;
 pub const V1: u32 = 0b010;
 pub const V2: u32 = 0b100;
+pub const V3: u32 = 0b1000;
 
 pub fn ec_cmd_get_cmd_versions<File: AsRawFd>(file: &mut File, cmd: CrosEcCmd) -> EcCmdResult<u32> {
     let fd = file.as_raw_fd();
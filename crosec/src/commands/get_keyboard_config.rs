@@ -2,7 +2,11 @@ use super::CrosEcCmd;
 use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
 use bytemuck::{Pod, Zeroable};
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::fmt::{Display, Formatter};
 use std::os::fd::AsRawFd;
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, IntoStaticStr};
 
 const MAX_TOP_ROW_KEYS: usize = 15;
 
@@ -17,8 +21,17 @@ pub const KEYBD_CAP_SCRNLOCK_KEY: u8 = 4;
 /// Whether the keyboard has an assistant key.
 pub const KEYBD_CAP_ASSISTANT_KEY: u8 = 8;
 
+#[derive(EnumIter, IntoStaticStr, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeybdCapability {
+    FunctionKeys = KEYBD_CAP_FUNCTION_KEYS,
+    NumericKeypad = KEYBD_CAP_NUMERIC_KEYPAD,
+    ScrnlockKey = KEYBD_CAP_SCRNLOCK_KEY,
+    AssistantKey = KEYBD_CAP_ASSISTANT_KEY,
+}
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug, FromPrimitive)]
+#[derive(Clone, Copy, Debug, FromPrimitive, EnumIter, PartialEq, Eq)]
 pub enum ActionKey {
     TkAbsent = 0,
     TkBack = 1,
@@ -46,6 +59,166 @@ pub enum ActionKey {
     TkDonotdisturb = 23,
 }
 
+/// A Linux input event code, as in `KEY_*` from `input-event-codes.h`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinuxKey {
+    pub code: u16,
+    /// The name without the `KEY_` prefix, in lowercase. This is the name used by udev's hwdb.
+    pub name: &'static str,
+}
+
+/// A HID usage page and usage ID
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HidUsage {
+    pub page: u16,
+    pub id: u16,
+}
+
+impl Display for HidUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#06x}:{:#06x}", self.page, self.id)
+    }
+}
+
+const HID_USAGE_PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const HID_USAGE_PAGE_TELEPHONY: u16 = 0x0B;
+const HID_USAGE_PAGE_CONSUMER: u16 = 0x0C;
+
+impl ActionKey {
+    /// The key code that the kernel reports for this key on ChromeOS
+    pub fn linux_key(&self) -> Option<LinuxKey> {
+        let (code, name) = match self {
+            Self::TkAbsent => return None,
+            Self::TkBack => (158, "back"),
+            Self::TkForward => (159, "forward"),
+            Self::TkRefresh => (173, "refresh"),
+            Self::TkFullscreen => (0x174, "zoom"),
+            Self::TkOverview => (120, "scale"),
+            Self::TkBrightnessDown => (224, "brightnessdown"),
+            Self::TkBrightnessUp => (225, "brightnessup"),
+            Self::TkVolMute => (113, "mute"),
+            Self::TkVolDown => (114, "volumedown"),
+            Self::TkVolUp => (115, "volumeup"),
+            Self::TkSnapshot => (99, "sysrq"),
+            Self::TkPrivacyScrnToggle => (0x279, "privacy_screen_toggle"),
+            Self::TkKbdBklightDown => (229, "kbdillumdown"),
+            Self::TkKbdBklightUp => (230, "kbdillumup"),
+            Self::TkPlayPause => (164, "playpause"),
+            Self::TkNextTrack => (163, "nextsong"),
+            Self::TkPrevTrack => (165, "previoussong"),
+            Self::TkKbdBklightToggle => (228, "kbdillumtoggle"),
+            Self::TkMicmute => (248, "micmute"),
+            Self::TkMenu => (139, "menu"),
+            Self::TkDictate => (0x24a, "dictate"),
+            Self::TkAccessibility => (0x24e, "accessibility"),
+            Self::TkDonotdisturb => (0x24f, "do_not_disturb"),
+        };
+        Some(LinuxKey { code, name })
+    }
+
+    /// The HID usage for this key, if there is a standard one
+    pub fn hid_usage(&self) -> Option<HidUsage> {
+        let (page, id) = match self {
+            Self::TkBack => (HID_USAGE_PAGE_CONSUMER, 0x224),
+            Self::TkForward => (HID_USAGE_PAGE_CONSUMER, 0x225),
+            Self::TkRefresh => (HID_USAGE_PAGE_CONSUMER, 0x227),
+            Self::TkFullscreen => (HID_USAGE_PAGE_CONSUMER, 0x232),
+            Self::TkOverview => (HID_USAGE_PAGE_CONSUMER, 0x29f),
+            Self::TkBrightnessDown => (HID_USAGE_PAGE_CONSUMER, 0x70),
+            Self::TkBrightnessUp => (HID_USAGE_PAGE_CONSUMER, 0x6f),
+            Self::TkVolMute => (HID_USAGE_PAGE_CONSUMER, 0xe2),
+            Self::TkVolDown => (HID_USAGE_PAGE_CONSUMER, 0xea),
+            Self::TkVolUp => (HID_USAGE_PAGE_CONSUMER, 0xe9),
+            Self::TkSnapshot => (HID_USAGE_PAGE_CONSUMER, 0x65),
+            Self::TkKbdBklightDown => (HID_USAGE_PAGE_CONSUMER, 0x7a),
+            Self::TkKbdBklightUp => (HID_USAGE_PAGE_CONSUMER, 0x79),
+            Self::TkPlayPause => (HID_USAGE_PAGE_CONSUMER, 0xcd),
+            Self::TkNextTrack => (HID_USAGE_PAGE_CONSUMER, 0xb5),
+            Self::TkPrevTrack => (HID_USAGE_PAGE_CONSUMER, 0xb6),
+            Self::TkKbdBklightToggle => (HID_USAGE_PAGE_CONSUMER, 0x7c),
+            Self::TkMicmute => (HID_USAGE_PAGE_TELEPHONY, 0x2f),
+            Self::TkMenu => (HID_USAGE_PAGE_CONSUMER, 0x40),
+            Self::TkDictate => (HID_USAGE_PAGE_CONSUMER, 0xd8),
+            Self::TkDonotdisturb => (HID_USAGE_PAGE_GENERIC_DESKTOP, 0x9b),
+            Self::TkAbsent | Self::TkPrivacyScrnToggle | Self::TkAccessibility => return None,
+        };
+        Some(HidUsage { page, id })
+    }
+
+    /// The scan code that `atkbd` sees for this key, which is what udev's hwdb matches on for `evdev:atkbd:*` devices.
+    /// The EC sends an `0xe0` prefixed set 1 scan code, which `atkbd` reports as `0x80 | code`.
+    pub fn atkbd_scancode(&self) -> Option<u8> {
+        let set_1_code: u8 = match self {
+            Self::TkBack => 0x6a,
+            Self::TkForward => 0x69,
+            Self::TkRefresh => 0x67,
+            Self::TkFullscreen => 0x11,
+            Self::TkOverview => 0x12,
+            Self::TkSnapshot => 0x13,
+            Self::TkBrightnessDown => 0x14,
+            Self::TkBrightnessUp => 0x15,
+            Self::TkPrivacyScrnToggle => 0x16,
+            Self::TkKbdBklightDown => 0x17,
+            Self::TkKbdBklightUp => 0x18,
+            Self::TkNextTrack => 0x19,
+            Self::TkPrevTrack => 0x10,
+            Self::TkPlayPause => 0x1a,
+            Self::TkMicmute => 0x1b,
+            Self::TkKbdBklightToggle => 0x1e,
+            Self::TkVolMute => 0x20,
+            Self::TkVolDown => 0x2e,
+            Self::TkVolUp => 0x30,
+            Self::TkMenu => 0x5d,
+            Self::TkAbsent | Self::TkDictate | Self::TkAccessibility | Self::TkDonotdisturb => {
+                return None
+            }
+        };
+        Some(0x80 | set_1_code)
+    }
+}
+
+impl Display for ActionKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::TkAbsent => "Absent",
+            Self::TkBack => "Back",
+            Self::TkForward => "Forward",
+            Self::TkRefresh => "Refresh",
+            Self::TkFullscreen => "Fullscreen",
+            Self::TkOverview => "Overview",
+            Self::TkBrightnessDown => "Brightness Down",
+            Self::TkBrightnessUp => "Brightness Up",
+            Self::TkVolMute => "Volume Mute",
+            Self::TkVolDown => "Volume Down",
+            Self::TkVolUp => "Volume Up",
+            Self::TkSnapshot => "Snapshot",
+            Self::TkPrivacyScrnToggle => "Privacy Screen Toggle",
+            Self::TkKbdBklightDown => "Keyboard Backlight Down",
+            Self::TkKbdBklightUp => "Keyboard Backlight Up",
+            Self::TkPlayPause => "Play/Pause",
+            Self::TkNextTrack => "Next Track",
+            Self::TkPrevTrack => "Previous Track",
+            Self::TkKbdBklightToggle => "Keyboard Backlight Toggle",
+            Self::TkMicmute => "Mic Mute",
+            Self::TkMenu => "Menu",
+            Self::TkDictate => "Dictate",
+            Self::TkAccessibility => "Accessibility",
+            Self::TkDonotdisturb => "Do Not Disturb",
+        })
+    }
+}
+
+/// What the top row keys should be mapped to in a generated keymap
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TopRowMapping {
+    /// Each key sends the action printed on it, like on ChromeOS
+    #[default]
+    ActionKeys,
+    /// The keys send F1, F2, etc. from left to right
+    FunctionKeys,
+}
+
 #[repr(C)]
 #[derive(Pod, Zeroable, Clone, Copy, Debug)]
 pub struct EcResponseKeybdConfig {
@@ -61,6 +234,47 @@ pub struct EcResponseKeybdConfig {
     pub capabilities: u8,
 }
 
+impl EcResponseKeybdConfig {
+    /// The action keys from left to right, with the raw value of keys which aren't known
+    pub fn top_row_keys(&self) -> Vec<Result<ActionKey, u8>> {
+        self.action_keys[..(self.num_top_row_keys as usize).min(MAX_TOP_ROW_KEYS)]
+            .iter()
+            .map(|key| ActionKey::from_u8(*key).ok_or(*key))
+            .collect()
+    }
+
+    pub fn has_capability(&self, capability: KeybdCapability) -> bool {
+        self.capabilities & capability as u8 != 0
+    }
+
+    pub fn capabilities(&self) -> Vec<KeybdCapability> {
+        KeybdCapability::iter()
+            .filter(|capability| self.has_capability(*capability))
+            .collect()
+    }
+
+    /// Generates a [udev hwdb](https://www.freedesktop.org/software/systemd/man/latest/hwdb.html) entry for the top row keys.
+    /// `evdev_match` is the match line, for example `evdev:atkbd:dmi:bvn*:bvr*:bd*:svnGoogle:pnEve:*`.
+    /// This only applies to keyboards that go through `atkbd`, which is the case for x86 Chromebooks.
+    pub fn hwdb(&self, evdev_match: &str, mapping: TopRowMapping) -> String {
+        let mut hwdb = format!("{evdev_match}\n");
+        for (index, key) in self.top_row_keys().into_iter().enumerate() {
+            let Some(scancode) = key.ok().and_then(|key| key.atkbd_scancode()) else {
+                continue;
+            };
+            let key_name = match mapping {
+                TopRowMapping::ActionKeys => match key.ok().and_then(|key| key.linux_key()) {
+                    Some(linux_key) => linux_key.name.to_owned(),
+                    None => continue,
+                },
+                TopRowMapping::FunctionKeys => format!("f{}", index + 1),
+            };
+            hwdb += &format!(" KEYBOARD_KEY_{scancode:02x}={key_name}\n");
+        }
+        hwdb
+    }
+}
+
 pub fn ec_cmd_get_keyboard_config<File: AsRawFd>(
     file: &mut File,
 ) -> EcCmdResult<EcResponseKeybdConfig> {
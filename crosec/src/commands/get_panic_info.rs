@@ -0,0 +1,21 @@
+use std::os::fd::AsRawFd;
+
+use crate::{ec_command::ec_command_with_dynamic_output_size, EcCmdResult};
+
+use super::CrosEcCmd;
+
+/// Bigger than the panic data of any architecture, and smaller than the max response size of any EC
+const PANIC_DATA_MAX_SIZE: usize = 0xA0;
+
+/// Gets the raw panic data which the EC saved when it last panicked.
+/// The response is padded with zeros, and is all zeros if there is no panic data.
+/// See [`crate::panic_info::PanicInfo::parse`] to decode it.
+pub fn ec_cmd_get_panic_info<File: AsRawFd>(file: &mut File) -> EcCmdResult<Vec<u8>> {
+    ec_command_with_dynamic_output_size(
+        CrosEcCmd::GetPanicInfo,
+        0,
+        &[],
+        PANIC_DATA_MAX_SIZE,
+        file.as_raw_fd(),
+    )
+}
@@ -1,6 +1,8 @@
 use std::os::fd::AsRawFd;
+use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
+use strum_macros::{EnumIter, FromRepr, IntoStaticStr};
 
 use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
 
@@ -24,7 +26,7 @@ pub struct EcResponseUptimeInfo {
     pub ap_resets_since_ec_boot: u32,
 
     /// The set of flags which describe the EC's most recent reset.
-    /// See EC_RESET_FLAG_* for details.
+    /// See [`EcResponseUptimeInfo::reset_flags`] for the decoded flags.
     pub ec_reset_flags: u32,
 
     /// Empty log entries have both the cause and timestamp set to zero.
@@ -34,7 +36,7 @@ pub struct EcResponseUptimeInfo {
 #[repr(C)]
 #[derive(Pod, Zeroable, Clone, Copy, Debug)]
 pub struct ApResetLogEntry {
-    /// See enum chipset_{reset,shutdown}_reason for details.
+    /// See enum chipset_{reset,shutdown}_reason for details, and [`ApResetLogEntry::cause`] for the decoded cause.
     pub reset_cause: u16,
 
     /// Reserved for protocol growth.
@@ -46,6 +48,166 @@ pub struct ApResetLogEntry {
     pub reset_time_ms: u32,
 }
 
+/// Why the EC reset. More than one flag can be set.
+#[repr(u32)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcResetFlag {
+    /// Some other reason
+    Other = 1 << 0,
+    /// The reset pin was asserted
+    ResetPin = 1 << 1,
+    Brownout = 1 << 2,
+    /// The power was turned on
+    PowerOn = 1 << 3,
+    Watchdog = 1 << 4,
+    /// A soft reboot, such as from the `reboot` console command
+    Soft = 1 << 5,
+    /// Woke up from hibernating
+    Hibernate = 1 << 6,
+    RtcAlarm = 1 << 7,
+    WakePin = 1 << 8,
+    LowBattery = 1 << 9,
+    /// Jumped directly to this image, so the EC didn't actually reset
+    Sysjump = 1 << 10,
+    /// A hard reboot, such as from `reboot hard`
+    Hard = 1 << 11,
+    /// The AP was left off, so it doesn't boot after the reset
+    ApOff = 1 << 12,
+    /// Some of the flags were preserved from the previous boot
+    Preserved = 1 << 13,
+    UsbResume = 1 << 14,
+    /// A USB-C debug cable was connected
+    Rdd = 1 << 15,
+    /// Fixed reset functionality
+    Rbox = 1 << 16,
+    /// A security threat was detected
+    Security = 1 << 17,
+    /// The AP experienced a watchdog reset
+    ApWatchdog = 1 << 18,
+    /// Stay in RO instead of jumping to RW
+    StayInRo = 1 << 19,
+    /// Jumped to RW with early firmware selection
+    Efs = 1 << 20,
+    /// Leave the AP off in the idle state
+    ApIdle = 1 << 21,
+    /// The EC booted because power was first applied
+    InitialPower = 1 << 22,
+}
+
+flag_set!(EcResetFlags, EcResetFlag, u32);
+
+/// Why the EC reset the AP
+#[repr(u16)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipsetResetReason {
+    Unknown,
+    /// A reason specific to the board
+    BoardCustom,
+    /// The AP hung, so the EC rebooted it
+    HangReboot,
+    /// Requested by the `apreset` console command
+    ConsoleCmd,
+    /// Requested by a host command
+    HostCmd,
+    /// The keyboard sysreset combo was pressed
+    KbSysreset,
+    /// The keyboard warm reboot combo was pressed
+    KbWarmReboot,
+    /// Requested by the debugger
+    DbgWarmReboot,
+    /// Requested by the AP
+    ApReq,
+    /// The EC reset the AP while initializing
+    Init,
+    /// The AP's watchdog expired
+    ApWatchdog,
+}
+
+/// Why the EC shut down the AP
+#[repr(u16)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipsetShutdownReason {
+    /// A power rail failed
+    Powerfail = 1 << 15,
+    /// The EC shut down the AP while initializing
+    Init,
+    /// A reason specific to the board
+    BoardCustom,
+    /// The battery can't power the AP
+    BatteryInhibit,
+    /// The AP didn't respond to a power signal in time
+    Wait,
+    BatteryCrit,
+    /// Requested by the `apshutdown` console command
+    ConsoleCmd,
+    /// The AP entered G3
+    G3,
+    Thermal,
+    /// The power button was held
+    Button,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApResetCause {
+    Reset(ChipsetResetReason),
+    Shutdown(ChipsetShutdownReason),
+    Unknown(u16),
+}
+
+impl From<u16> for ApResetCause {
+    fn from(reset_cause: u16) -> Self {
+        if let Some(reason) = ChipsetResetReason::from_repr(reset_cause) {
+            Self::Reset(reason)
+        } else if let Some(reason) = ChipsetShutdownReason::from_repr(reset_cause) {
+            Self::Shutdown(reason)
+        } else {
+            Self::Unknown(reset_cause)
+        }
+    }
+}
+
+impl ApResetLogEntry {
+    pub fn is_empty(&self) -> bool {
+        self.reset_cause == 0 && self.reset_time_ms == 0
+    }
+
+    pub fn cause(&self) -> ApResetCause {
+        self.reset_cause.into()
+    }
+
+    /// How long before `time_since_ec_boot_ms` the reset happened.
+    /// See [`EcResponseUptimeInfo::time_since_ec_boot_ms`].
+    pub fn age(&self, time_since_ec_boot_ms: u32) -> Duration {
+        Duration::from_millis(
+            time_since_ec_boot_ms
+                .saturating_sub(self.reset_time_ms)
+                .into(),
+        )
+    }
+}
+
+impl EcResponseUptimeInfo {
+    pub fn uptime(&self) -> Duration {
+        Duration::from_millis(self.time_since_ec_boot_ms.into())
+    }
+
+    pub fn reset_flags(&self) -> EcResetFlags {
+        EcResetFlags(self.ec_reset_flags)
+    }
+
+    /// The AP resets which were logged, oldest first, without the empty entries
+    pub fn ap_resets(&self) -> impl Iterator<Item = &ApResetLogEntry> + '_ {
+        let mut entries: Vec<_> = self
+            .recent_ap_reset
+            .iter()
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        entries.sort_by_key(|entry| entry.reset_time_ms);
+        entries.into_iter()
+    }
+}
+
+/// Only version 0 of this command exists. Its response already has the reset flags and reset log.
 pub fn ec_cmd_get_uptime_info<File: AsRawFd>(file: &mut File) -> EcCmdResult<EcResponseUptimeInfo> {
     ec_command_bytemuck(CrosEcCmd::GetUptimeInfo, 0, &(), file.as_raw_fd())
 }
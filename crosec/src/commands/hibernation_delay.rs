@@ -0,0 +1,43 @@
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseHibernationDelay {
+    time_g3: u32,
+    time_remaining: u32,
+    hibernate_delay: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HibernationDelay {
+    /// How long the AP has been in G3, or zero if it isn't in G3
+    pub time_g3: Duration,
+    /// How long until the EC hibernates, or zero if the AP isn't in G3
+    pub time_remaining: Duration,
+    /// How long the EC waits in G3 before hibernating
+    pub hibernate_delay: Duration,
+}
+
+/// Gets how long the EC waits after the AP shuts down into G3 before hibernating.
+/// If `delay` is specified, the delay is set first. It is rounded down to whole seconds, and can't be zero.
+pub fn hibernation_delay<File: AsRawFd>(
+    file: &mut File,
+    delay: Option<Duration>,
+) -> EcCmdResult<HibernationDelay> {
+    // The EC only sets the delay if it isn't 0
+    let seconds = delay.map_or(0, |delay| delay.as_secs().min(u32::MAX as u64) as u32);
+    let response: EcResponseHibernationDelay =
+        ec_command_bytemuck(CrosEcCmd::HibernationDelay, 0, &seconds, file.as_raw_fd())?;
+    Ok(HibernationDelay {
+        time_g3: Duration::from_secs(response.time_g3.into()),
+        time_remaining: Duration::from_secs(response.time_remaining.into()),
+        hibernate_delay: Duration::from_secs(response.hibernate_delay.into()),
+    })
+}
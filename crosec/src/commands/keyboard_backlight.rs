@@ -0,0 +1,53 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcResponsePwmGetKeyboardBacklight {
+    pub percent: u8,
+    pub enabled: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsPwmSetKeyboardBacklight {
+    percent: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct KeyboardBacklight {
+    pub percent: u8,
+    pub enabled: bool,
+}
+
+impl From<EcResponsePwmGetKeyboardBacklight> for KeyboardBacklight {
+    fn from(value: EcResponsePwmGetKeyboardBacklight) -> Self {
+        Self {
+            percent: value.percent,
+            enabled: value.enabled != 0,
+        }
+    }
+}
+
+pub fn pwm_get_keyboard_backlight<File: AsRawFd>(
+    file: &mut File,
+) -> EcCmdResult<KeyboardBacklight> {
+    let response: EcResponsePwmGetKeyboardBacklight =
+        ec_command_bytemuck(CrosEcCmd::PwmGetKeyboardBacklight, 0, &(), file.as_raw_fd())?;
+    Ok(response.into())
+}
+
+/// `percent` should be from 0 to 100
+pub fn pwm_set_keyboard_backlight<File: AsRawFd>(file: &mut File, percent: u8) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::PwmSetKeyboardBacklight,
+        0,
+        &EcParamsPwmSetKeyboardBacklight { percent },
+        file.as_raw_fd(),
+    )
+}
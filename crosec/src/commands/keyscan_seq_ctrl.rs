@@ -0,0 +1,170 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{bytes_of, cast_slice, Pod, Zeroable};
+
+use crate::{
+    ec_command::{ec_command_bytemuck, ec_command_with_dynamic_output_size},
+    EcCmdResult, KEYBOARD_COLS_MAX,
+};
+
+use super::{get_protocol_info::EcResponseGetProtocolInfo, CrosEcCmd};
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum KeyscanSeqCmd {
+    Status = 0,
+    Clear = 1,
+    Add = 2,
+    Start = 3,
+    Collect = 4,
+}
+
+/// The item has been scanned by the EC
+pub const EC_KEYSCAN_SEQ_FLAG_DONE: u8 = 1 << 0;
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsKeyscanSeqCtrlAdd {
+    cmd: u8,
+    /// Absolute time for this scan, measured from the start of the sequence
+    time_us: u32,
+    scan: [u8; KEYBOARD_COLS_MAX],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsKeyscanSeqCtrlCollect {
+    cmd: u8,
+    start_item: u8,
+    num_items: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct KeyscanSeqStatus {
+    pub active: u8,
+    pub num_items: u8,
+    pub cur_item: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcCollectItem {
+    /// See [`EC_KEYSCAN_SEQ_FLAG_DONE`]
+    pub flags: u8,
+}
+
+impl EcCollectItem {
+    pub fn done(&self) -> bool {
+        self.flags & EC_KEYSCAN_SEQ_FLAG_DONE != 0
+    }
+}
+
+/// Builds the scan data for one step of a sequence, where `keys` are the `(row, col)` positions of the pressed keys.
+/// Every key that isn't in `keys` is released.
+/// Returns the first key which isn't in the matrix if there is one, since each column only has 8 rows.
+pub fn key_matrix_scan<I: IntoIterator<Item = (u8, u8)>>(
+    keys: I,
+) -> Result<[u8; KEYBOARD_COLS_MAX], (u8, u8)> {
+    let mut scan = [0; KEYBOARD_COLS_MAX];
+    for (row, col) in keys {
+        let bit = 1u8.checked_shl(row.into()).ok_or((row, col))?;
+        *scan.get_mut(col as usize).ok_or((row, col))? |= bit;
+    }
+    Ok(scan)
+}
+
+pub fn keyscan_seq_status<File: AsRawFd>(file: &mut File) -> EcCmdResult<KeyscanSeqStatus> {
+    ec_command_bytemuck(
+        CrosEcCmd::KeyscanSeqCtrl,
+        0,
+        &(KeyscanSeqCmd::Status as u8),
+        file.as_raw_fd(),
+    )
+}
+
+pub fn keyscan_seq_clear<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::KeyscanSeqCtrl,
+        0,
+        &(KeyscanSeqCmd::Clear as u8),
+        file.as_raw_fd(),
+    )
+}
+
+/// Adds a scan to the end of the sequence. This fails with `Busy` if the sequence is currently running.
+pub fn keyscan_seq_add<File: AsRawFd>(
+    file: &mut File,
+    time_us: u32,
+    scan: [u8; KEYBOARD_COLS_MAX],
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::KeyscanSeqCtrl,
+        0,
+        &EcParamsKeyscanSeqCtrlAdd {
+            cmd: KeyscanSeqCmd::Add as u8,
+            time_us,
+            scan,
+        },
+        file.as_raw_fd(),
+    )
+}
+
+pub fn keyscan_seq_start<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::KeyscanSeqCtrl,
+        0,
+        &(KeyscanSeqCmd::Start as u8),
+        file.as_raw_fd(),
+    )
+}
+
+/// Gets the state of every item in the sequence, in chunks if needed
+pub fn keyscan_seq_collect<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+) -> EcCmdResult<Vec<EcCollectItem>> {
+    let status = keyscan_seq_status(file)?;
+    let max_chunk_size = (protocol_info.max_ec_output_size() - 1).min(u8::MAX as usize);
+    let mut items = Vec::with_capacity(status.num_items as usize);
+    while items.len() < status.num_items as usize {
+        let num_items = (status.num_items as usize - items.len()).min(max_chunk_size);
+        let response = ec_command_with_dynamic_output_size(
+            CrosEcCmd::KeyscanSeqCtrl,
+            0,
+            bytes_of(&EcParamsKeyscanSeqCtrlCollect {
+                cmd: KeyscanSeqCmd::Collect as u8,
+                start_item: items.len() as u8,
+                num_items: num_items as u8,
+            }),
+            1 + num_items,
+            file.as_raw_fd(),
+        )?;
+        let num_items_collected = (response[0] as usize).min(num_items);
+        if num_items_collected == 0 {
+            break;
+        }
+        items.extend_from_slice(cast_slice(&response[1..1 + num_items_collected]));
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan() {
+        let mut expected = [0; KEYBOARD_COLS_MAX];
+        expected[0] = 0b1000_0001;
+        expected[12] = 0b0000_0100;
+        assert_eq!(key_matrix_scan([(0, 0), (7, 0), (2, 12)]), Ok(expected));
+        assert_eq!(key_matrix_scan([]), Ok([0; KEYBOARD_COLS_MAX]));
+    }
+
+    #[test]
+    fn key_outside_matrix() {
+        assert_eq!(key_matrix_scan([(0, 0), (8, 1)]), Err((8, 1)));
+        assert_eq!(key_matrix_scan([(0, 13), (9, 0)]), Err((0, 13)));
+    }
+}
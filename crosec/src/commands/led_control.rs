@@ -0,0 +1,122 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, EnumString, FromRepr, IntoStaticStr};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+const EC_LED_FLAGS_QUERY: u8 = 1 << 0;
+const EC_LED_FLAGS_AUTO: u8 = 1 << 1;
+
+#[repr(u8)]
+#[derive(EnumIter, EnumString, IntoStaticStr, FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LedId {
+    /// LED to indicate battery state of charge
+    Battery,
+    /// LED to indicate system power state (on or in suspend).
+    /// May be on power button or on C-panel.
+    Power,
+    /// LED on power adapter or its plug
+    Adapter,
+    /// LED to indicate left side
+    Left,
+    /// LED to indicate right side
+    Right,
+    /// LED to indicate recovery mode with HW_REINIT
+    RecoveryHwReinit,
+    /// LED to indicate sysrq debug mode
+    SysrqDebug,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, EnumString, IntoStaticStr, FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LedColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    White,
+    Amber,
+}
+
+pub const EC_LED_COLOR_COUNT: usize = 6;
+
+/// A brightness for each color. When querying, this is the maximum brightness of each color, where 0 means the color isn't supported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LedBrightness(pub [u8; EC_LED_COLOR_COUNT]);
+
+impl LedBrightness {
+    pub fn get(&self, color: LedColor) -> u8 {
+        self.0[color as usize]
+    }
+
+    pub fn set(&mut self, color: LedColor, brightness: u8) {
+        self.0[color as usize] = brightness;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LedColor, u8)> + '_ {
+        LedColor::iter().map(|color| (color, self.get(color)))
+    }
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsLedControl {
+    led_id: u8,
+    flags: u8,
+    brightness: [u8; EC_LED_COLOR_COUNT],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseLedControl {
+    brightness_range: [u8; EC_LED_COLOR_COUNT],
+}
+
+fn led_control<File: AsRawFd>(
+    file: &mut File,
+    led: LedId,
+    flags: u8,
+    brightness: LedBrightness,
+) -> EcCmdResult<LedBrightness> {
+    let response: EcResponseLedControl = ec_command_bytemuck(
+        CrosEcCmd::LedControl,
+        1,
+        &EcParamsLedControl {
+            led_id: led as u8,
+            flags,
+            brightness: brightness.0,
+        },
+        file.as_raw_fd(),
+    )?;
+    Ok(LedBrightness(response.brightness_range))
+}
+
+/// Gets the brightness range of each color. A color with a range of 0 isn't supported by the LED.
+pub fn led_query<File: AsRawFd>(file: &mut File, led: LedId) -> EcCmdResult<LedBrightness> {
+    led_control(file, led, EC_LED_FLAGS_QUERY, Default::default())
+}
+
+/// Gives control of the LED back to the EC
+pub fn led_set_auto<File: AsRawFd>(file: &mut File, led: LedId) -> EcCmdResult<()> {
+    led_control(file, led, EC_LED_FLAGS_AUTO, Default::default())?;
+    Ok(())
+}
+
+/// Manually sets the brightness of each color. The EC won't change the LED until [`led_set_auto`] is called.
+/// Setting every color to 0 turns the LED off.
+pub fn led_set_brightness<File: AsRawFd>(
+    file: &mut File,
+    led: LedId,
+    brightness: LedBrightness,
+) -> EcCmdResult<()> {
+    led_control(file, led, 0, brightness)?;
+    Ok(())
+}
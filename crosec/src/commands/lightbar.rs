@@ -0,0 +1,514 @@
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+
+use bytemuck::{bytes_of, pod_read_unaligned, AnyBitPattern, NoUninit, Pod, Zeroable};
+use strum_macros::{EnumIter, FromRepr, IntoStaticStr};
+
+use crate::{
+    ec_command::ec_command_with_dynamic_output_size, EcCmdResult, EcError, EcResponseStatus,
+};
+
+use super::CrosEcCmd;
+
+pub const LB_BATTERY_LEVELS: usize = 4;
+/// The max size of a lightbar program, in bytes
+pub const EC_LB_PROG_LEN: usize = 192;
+/// The number of LEDs on the lightbar. Using this as an LED index addresses all of the LEDs at once.
+pub const NUM_LEDS: u8 = 4;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum LightbarCommand {
+    Dump = 0,
+    Off = 1,
+    On = 2,
+    Init = 3,
+    SetBrightness = 4,
+    Seq = 5,
+    Reg = 6,
+    SetRgb = 7,
+    GetSeq = 8,
+    Demo = 9,
+    GetParamsV0 = 10,
+    SetParamsV0 = 11,
+    Version = 12,
+    GetBrightness = 13,
+    GetRgb = 14,
+    GetDemo = 15,
+    GetParamsV1 = 16,
+    SetParamsV1 = 17,
+    SetProgram = 18,
+    ManualSuspendCtrl = 19,
+    Suspend = 20,
+    Resume = 21,
+    GetParamsV2Timing = 22,
+    SetParamsV2Timing = 23,
+    GetParamsV2Tap = 24,
+    SetParamsV2Tap = 25,
+    GetParamsV2Oscillation = 26,
+    SetParamsV2Oscillation = 27,
+    GetParamsV2Brightness = 28,
+    SetParamsV2Brightness = 29,
+    GetParamsV2Thresholds = 30,
+    SetParamsV2Thresholds = 31,
+    GetParamsV2Colors = 32,
+    SetParamsV2Colors = 33,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LightbarSequence {
+    Error,
+    S5,
+    S3,
+    S0,
+    S5S3,
+    S3S0,
+    S0S3,
+    S3S5,
+    Stop,
+    Run,
+    Konami,
+    Test,
+    Tap,
+    Program,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarDumpValue {
+    pub reg: u8,
+    pub ic0: u8,
+    pub ic1: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarVersion {
+    pub num: u32,
+    pub flags: u32,
+}
+
+/// Arrays with 2 elements are indexed by whether AC is connected (0 = no AC, 1 = AC)
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV0 {
+    // Timing
+    pub google_ramp_up: i32,
+    pub google_ramp_down: i32,
+    pub s3s0_ramp_up: i32,
+    pub s0_tick_delay: [i32; 2],
+    pub s0a_tick_delay: [i32; 2],
+    pub s0s3_ramp_down: i32,
+    pub s3_sleep_for: i32,
+    pub s3_ramp_up: i32,
+    pub s3_ramp_down: i32,
+
+    // Oscillation
+    pub new_s0: u8,
+    pub osc_min: [u8; 2],
+    pub osc_max: [u8; 2],
+    pub w_ofs: [u8; 2],
+
+    // Brightness limits based on the backlight and AC
+    pub bright_bl_off_fixed: [u8; 2],
+    pub bright_bl_on_min: [u8; 2],
+    pub bright_bl_on_max: [u8; 2],
+
+    /// Battery level thresholds
+    pub battery_threshold: [u8; LB_BATTERY_LEVELS - 1],
+
+    /// Map `[AC][battery_level]` to color index when the AP is running
+    pub s0_idx: [[u8; LB_BATTERY_LEVELS]; 2],
+    /// Map `[AC][battery_level]` to color index when the AP is sleeping
+    pub s3_idx: [[u8; LB_BATTERY_LEVELS]; 2],
+
+    /// Color palette. 0-3 are Google colors.
+    pub color: [Rgb; 8],
+}
+
+/// Arrays with 2 elements are indexed by whether AC is connected (0 = no AC, 1 = AC)
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV1 {
+    // Timing
+    pub google_ramp_up: i32,
+    pub google_ramp_down: i32,
+    pub s3s0_ramp_up: i32,
+    pub s0_tick_delay: [i32; 2],
+    pub s0a_tick_delay: [i32; 2],
+    pub s0s3_ramp_down: i32,
+    pub s3_sleep_for: i32,
+    pub s3_ramp_up: i32,
+    pub s3_ramp_down: i32,
+    pub s5_ramp_up: i32,
+    pub s5_ramp_down: i32,
+    pub tap_tick_delay: i32,
+    pub tap_gate_delay: i32,
+    pub tap_display_time: i32,
+
+    // Tap-for-battery params
+    pub tap_pct_red: u8,
+    pub tap_pct_green: u8,
+    pub tap_seg_min_on: u8,
+    pub tap_seg_max_on: u8,
+    pub tap_seg_osc: u8,
+    pub tap_idx: [u8; 3],
+
+    // Oscillation
+    pub osc_min: [u8; 2],
+    pub osc_max: [u8; 2],
+    pub w_ofs: [u8; 2],
+
+    // Brightness limits based on the backlight and AC
+    pub bright_bl_off_fixed: [u8; 2],
+    pub bright_bl_on_min: [u8; 2],
+    pub bright_bl_on_max: [u8; 2],
+
+    /// Battery level thresholds
+    pub battery_threshold: [u8; LB_BATTERY_LEVELS - 1],
+
+    /// Map `[AC][battery_level]` to color index when the AP is running
+    pub s0_idx: [[u8; LB_BATTERY_LEVELS]; 2],
+    /// Map `[AC][battery_level]` to color index when the AP is sleeping
+    pub s3_idx: [[u8; LB_BATTERY_LEVELS]; 2],
+
+    /// Single color pulse on inhibited power-up
+    pub s5_idx: u8,
+
+    /// Color palette. 0-3 are Google colors.
+    pub color: [Rgb; 8],
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2Timing {
+    pub google_ramp_up: i32,
+    pub google_ramp_down: i32,
+    pub s3s0_ramp_up: i32,
+    pub s0_tick_delay: [i32; 2],
+    pub s0a_tick_delay: [i32; 2],
+    pub s0s3_ramp_down: i32,
+    pub s3_sleep_for: i32,
+    pub s3_ramp_up: i32,
+    pub s3_ramp_down: i32,
+    pub s5_ramp_up: i32,
+    pub s5_ramp_down: i32,
+    pub tap_tick_delay: i32,
+    pub tap_gate_delay: i32,
+    pub tap_display_time: i32,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2Tap {
+    pub tap_pct_red: u8,
+    pub tap_pct_green: u8,
+    pub tap_seg_min_on: u8,
+    pub tap_seg_max_on: u8,
+    pub tap_seg_osc: u8,
+    pub tap_idx: [u8; 3],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2Oscillation {
+    pub osc_min: [u8; 2],
+    pub osc_max: [u8; 2],
+    pub w_ofs: [u8; 2],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2Brightness {
+    pub bright_bl_off_fixed: [u8; 2],
+    pub bright_bl_on_min: [u8; 2],
+    pub bright_bl_on_max: [u8; 2],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2Thresholds {
+    pub battery_threshold: [u8; LB_BATTERY_LEVELS - 1],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2Colors {
+    pub s0_idx: [[u8; LB_BATTERY_LEVELS]; 2],
+    pub s3_idx: [[u8; LB_BATTERY_LEVELS]; 2],
+    pub s5_idx: u8,
+    pub color: [Rgb; 8],
+}
+
+/// The v1 params were too big for i2c, so v2 splits them up into groups which are each gotten and set separately
+pub trait LightbarParamsV2Group: Pod {
+    #[doc(hidden)]
+    const GET: u8;
+    #[doc(hidden)]
+    const SET: u8;
+}
+
+macro_rules! impl_lightbar_params_v2_group {
+    ($group:ty, $get:ident, $set:ident) => {
+        impl LightbarParamsV2Group for $group {
+            const GET: u8 = LightbarCommand::$get as u8;
+            const SET: u8 = LightbarCommand::$set as u8;
+        }
+    };
+}
+
+impl_lightbar_params_v2_group!(LightbarParamsV2Timing, GetParamsV2Timing, SetParamsV2Timing);
+impl_lightbar_params_v2_group!(LightbarParamsV2Tap, GetParamsV2Tap, SetParamsV2Tap);
+impl_lightbar_params_v2_group!(
+    LightbarParamsV2Oscillation,
+    GetParamsV2Oscillation,
+    SetParamsV2Oscillation
+);
+impl_lightbar_params_v2_group!(
+    LightbarParamsV2Brightness,
+    GetParamsV2Brightness,
+    SetParamsV2Brightness
+);
+impl_lightbar_params_v2_group!(
+    LightbarParamsV2Thresholds,
+    GetParamsV2Thresholds,
+    SetParamsV2Thresholds
+);
+impl_lightbar_params_v2_group!(LightbarParamsV2Colors, GetParamsV2Colors, SetParamsV2Colors);
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct LightbarParamsV2 {
+    pub timing: LightbarParamsV2Timing,
+    pub tap: LightbarParamsV2Tap,
+    pub oscillation: LightbarParamsV2Oscillation,
+    pub brightness: LightbarParamsV2Brightness,
+    pub thresholds: LightbarParamsV2Thresholds,
+    pub colors: LightbarParamsV2Colors,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsLightbarReg {
+    ctrl: u8,
+    reg: u8,
+    value: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsLightbarSetRgb {
+    led: u8,
+    color: Rgb,
+}
+
+/// Sends a lightbar sub-command, which is the `cmd` byte followed by the sub-command's params
+fn lightbar_command<File: AsRawFd, Params: NoUninit, Response: AnyBitPattern>(
+    file: &mut File,
+    cmd: u8,
+    params: &Params,
+) -> EcCmdResult<Response> {
+    let response = lightbar_command_bytes(file, cmd, bytes_of(params), size_of::<Response>())?;
+    Ok(pod_read_unaligned(&response))
+}
+
+fn lightbar_command_bytes<File: AsRawFd>(
+    file: &mut File,
+    cmd: u8,
+    params: &[u8],
+    output_size: usize,
+) -> EcCmdResult<Vec<u8>> {
+    let mut input = vec![cmd];
+    input.extend_from_slice(params);
+    ec_command_with_dynamic_output_size(
+        CrosEcCmd::LightbarCmd,
+        0,
+        &input,
+        output_size,
+        file.as_raw_fd(),
+    )
+}
+
+pub fn lightbar_on<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::On as u8, &())
+}
+
+pub fn lightbar_off<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::Off as u8, &())
+}
+
+pub fn lightbar_init<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::Init as u8, &())
+}
+
+/// Only works if manual suspend control was enabled with [`lightbar_manual_suspend_ctrl`]
+pub fn lightbar_suspend<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::Suspend as u8, &())
+}
+
+/// Only works if manual suspend control was enabled with [`lightbar_manual_suspend_ctrl`]
+pub fn lightbar_resume<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::Resume as u8, &())
+}
+
+pub fn lightbar_dump<File: AsRawFd>(file: &mut File) -> EcCmdResult<[LightbarDumpValue; 23]> {
+    lightbar_command(file, LightbarCommand::Dump as u8, &())
+}
+
+pub fn lightbar_set_brightness<File: AsRawFd>(file: &mut File, brightness: u8) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::SetBrightness as u8, &brightness)
+}
+
+pub fn lightbar_get_brightness<File: AsRawFd>(file: &mut File) -> EcCmdResult<u8> {
+    lightbar_command(file, LightbarCommand::GetBrightness as u8, &())
+}
+
+pub fn lightbar_seq<File: AsRawFd>(file: &mut File, seq: LightbarSequence) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::Seq as u8, &(seq as u8))
+}
+
+/// If the EC returns an unknown sequence, the raw number is returned as the error
+pub fn lightbar_get_seq<File: AsRawFd>(
+    file: &mut File,
+) -> EcCmdResult<Result<LightbarSequence, u8>> {
+    let seq: u8 = lightbar_command(file, LightbarCommand::GetSeq as u8, &())?;
+    Ok(LightbarSequence::from_repr(seq).ok_or(seq))
+}
+
+/// Writes directly to a register of one of the LED controller ICs
+pub fn lightbar_reg<File: AsRawFd>(
+    file: &mut File,
+    ctrl: u8,
+    reg: u8,
+    value: u8,
+) -> EcCmdResult<()> {
+    lightbar_command(
+        file,
+        LightbarCommand::Reg as u8,
+        &EcParamsLightbarReg { ctrl, reg, value },
+    )
+}
+
+/// `led` is from 0 to [`NUM_LEDS`] - 1, or [`NUM_LEDS`] to set all of them
+pub fn lightbar_set_rgb<File: AsRawFd>(file: &mut File, led: u8, color: Rgb) -> EcCmdResult<()> {
+    lightbar_command(
+        file,
+        LightbarCommand::SetRgb as u8,
+        &EcParamsLightbarSetRgb { led, color },
+    )
+}
+
+pub fn lightbar_get_rgb<File: AsRawFd>(file: &mut File, led: u8) -> EcCmdResult<Rgb> {
+    lightbar_command(file, LightbarCommand::GetRgb as u8, &led)
+}
+
+pub fn lightbar_demo<File: AsRawFd>(file: &mut File, enable: bool) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::Demo as u8, &(enable as u8))
+}
+
+pub fn lightbar_get_demo<File: AsRawFd>(file: &mut File) -> EcCmdResult<bool> {
+    let demo: u8 = lightbar_command(file, LightbarCommand::GetDemo as u8, &())?;
+    Ok(demo != 0)
+}
+
+/// Use [`lightbar_version`] to find out which params version is supported
+pub fn lightbar_get_params_v0<File: AsRawFd>(file: &mut File) -> EcCmdResult<LightbarParamsV0> {
+    lightbar_command(file, LightbarCommand::GetParamsV0 as u8, &())
+}
+
+pub fn lightbar_set_params_v0<File: AsRawFd>(
+    file: &mut File,
+    params: &LightbarParamsV0,
+) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::SetParamsV0 as u8, params)
+}
+
+pub fn lightbar_get_params_v1<File: AsRawFd>(file: &mut File) -> EcCmdResult<LightbarParamsV1> {
+    lightbar_command(file, LightbarCommand::GetParamsV1 as u8, &())
+}
+
+pub fn lightbar_set_params_v1<File: AsRawFd>(
+    file: &mut File,
+    params: &LightbarParamsV1,
+) -> EcCmdResult<()> {
+    lightbar_command(file, LightbarCommand::SetParamsV1 as u8, params)
+}
+
+pub fn lightbar_get_params_v2_group<File: AsRawFd, Group: LightbarParamsV2Group>(
+    file: &mut File,
+) -> EcCmdResult<Group> {
+    lightbar_command(file, Group::GET, &())
+}
+
+pub fn lightbar_set_params_v2_group<File: AsRawFd, Group: LightbarParamsV2Group>(
+    file: &mut File,
+    group: &Group,
+) -> EcCmdResult<()> {
+    lightbar_command(file, Group::SET, group)
+}
+
+/// Gets every group of the v2 params
+pub fn lightbar_get_params_v2<File: AsRawFd>(file: &mut File) -> EcCmdResult<LightbarParamsV2> {
+    Ok(LightbarParamsV2 {
+        timing: lightbar_get_params_v2_group(file)?,
+        tap: lightbar_get_params_v2_group(file)?,
+        oscillation: lightbar_get_params_v2_group(file)?,
+        brightness: lightbar_get_params_v2_group(file)?,
+        thresholds: lightbar_get_params_v2_group(file)?,
+        colors: lightbar_get_params_v2_group(file)?,
+    })
+}
+
+/// Sets every group of the v2 params
+pub fn lightbar_set_params_v2<File: AsRawFd>(
+    file: &mut File,
+    params: &LightbarParamsV2,
+) -> EcCmdResult<()> {
+    lightbar_set_params_v2_group(file, &params.timing)?;
+    lightbar_set_params_v2_group(file, &params.tap)?;
+    lightbar_set_params_v2_group(file, &params.oscillation)?;
+    lightbar_set_params_v2_group(file, &params.brightness)?;
+    lightbar_set_params_v2_group(file, &params.thresholds)?;
+    lightbar_set_params_v2_group(file, &params.colors)?;
+    Ok(())
+}
+
+/// `num` is the version of the params that the EC supports
+pub fn lightbar_version<File: AsRawFd>(file: &mut File) -> EcCmdResult<LightbarVersion> {
+    lightbar_command(file, LightbarCommand::Version as u8, &())
+}
+
+/// When enabled, the lightbar won't change for suspend and resume until [`lightbar_suspend`] and [`lightbar_resume`] are called
+pub fn lightbar_manual_suspend_ctrl<File: AsRawFd>(
+    file: &mut File,
+    enable: bool,
+) -> EcCmdResult<()> {
+    lightbar_command(
+        file,
+        LightbarCommand::ManualSuspendCtrl as u8,
+        &(enable as u8),
+    )
+}
+
+/// Uploads a program to be run with [`LightbarSequence::Program`].
+/// Fails with [`EcResponseStatus::InvalidParam`] without sending anything if the program is longer than [`EC_LB_PROG_LEN`].
+pub fn lightbar_set_program<File: AsRawFd>(file: &mut File, program: &[u8]) -> EcCmdResult<()> {
+    if program.len() > EC_LB_PROG_LEN {
+        return Err(EcError::Response(EcResponseStatus::InvalidParam));
+    }
+    let mut params = [0; 1 + EC_LB_PROG_LEN];
+    params[0] = program.len() as u8;
+    params[1..1 + program.len()].copy_from_slice(program);
+    lightbar_command_bytes(file, LightbarCommand::SetProgram as u8, &params, 0)?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+/// Keyboard scanning is enabled
+pub const EC_MKBP_FLAGS_ENABLE: u8 = 1;
+
+pub const EC_MKBP_VALID_SCAN_PERIOD: u32 = 1 << 0;
+pub const EC_MKBP_VALID_POLL_TIMEOUT: u32 = 1 << 1;
+pub const EC_MKBP_VALID_MIN_POST_SCAN_DELAY: u32 = 1 << 3;
+pub const EC_MKBP_VALID_OUTPUT_SETTLE: u32 = 1 << 4;
+pub const EC_MKBP_VALID_DEBOUNCE_DOWN: u32 = 1 << 5;
+pub const EC_MKBP_VALID_DEBOUNCE_UP: u32 = 1 << 6;
+pub const EC_MKBP_VALID_FIFO_MAX_DEPTH: u32 = 1 << 7;
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcMkbpConfig {
+    /// Which fields are valid. See `EC_MKBP_VALID_*`.
+    pub valid_mask: u32,
+    /// See [`EC_MKBP_FLAGS_ENABLE`]
+    pub flags: u8,
+    /// Which flags are valid
+    pub valid_flags: u8,
+    /// Period between the start of scans
+    pub scan_period_us: u16,
+    /// Revert to interrupt mode after no activity for this long
+    pub poll_timeout_us: u32,
+    /// Minimum post-scan relax time. Once a scan is finished, the EC checks the time until the next one is due.
+    /// If that time is shorter than this, this is used instead.
+    pub min_post_scan_delay_us: u16,
+    /// Delay between setting up the output and waiting for it to settle
+    pub output_settle_us: u16,
+    /// Time for debounce on key down
+    pub debounce_down_us: u16,
+    /// Time for debounce on key up
+    pub debounce_up_us: u16,
+    /// Maximum depth to allow for the fifo (0 = no keyscan output)
+    pub fifo_max_depth: u8,
+}
+
+/// Only the fields which are `Some` will be changed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SetMkbpConfig {
+    pub enabled: Option<bool>,
+    pub scan_period_us: Option<u16>,
+    pub poll_timeout_us: Option<u32>,
+    pub min_post_scan_delay_us: Option<u16>,
+    pub output_settle_us: Option<u16>,
+    pub debounce_down_us: Option<u16>,
+    pub debounce_up_us: Option<u16>,
+    pub fifo_max_depth: Option<u8>,
+}
+
+impl SetMkbpConfig {
+    fn to_params(self) -> EcMkbpConfig {
+        let mut config = EcMkbpConfig::zeroed();
+        if let Some(enabled) = self.enabled {
+            config.valid_flags |= EC_MKBP_FLAGS_ENABLE;
+            if enabled {
+                config.flags |= EC_MKBP_FLAGS_ENABLE;
+            }
+        }
+        if let Some(scan_period_us) = self.scan_period_us {
+            config.valid_mask |= EC_MKBP_VALID_SCAN_PERIOD;
+            config.scan_period_us = scan_period_us;
+        }
+        if let Some(poll_timeout_us) = self.poll_timeout_us {
+            config.valid_mask |= EC_MKBP_VALID_POLL_TIMEOUT;
+            config.poll_timeout_us = poll_timeout_us;
+        }
+        if let Some(min_post_scan_delay_us) = self.min_post_scan_delay_us {
+            config.valid_mask |= EC_MKBP_VALID_MIN_POST_SCAN_DELAY;
+            config.min_post_scan_delay_us = min_post_scan_delay_us;
+        }
+        if let Some(output_settle_us) = self.output_settle_us {
+            config.valid_mask |= EC_MKBP_VALID_OUTPUT_SETTLE;
+            config.output_settle_us = output_settle_us;
+        }
+        if let Some(debounce_down_us) = self.debounce_down_us {
+            config.valid_mask |= EC_MKBP_VALID_DEBOUNCE_DOWN;
+            config.debounce_down_us = debounce_down_us;
+        }
+        if let Some(debounce_up_us) = self.debounce_up_us {
+            config.valid_mask |= EC_MKBP_VALID_DEBOUNCE_UP;
+            config.debounce_up_us = debounce_up_us;
+        }
+        if let Some(fifo_max_depth) = self.fifo_max_depth {
+            config.valid_mask |= EC_MKBP_VALID_FIFO_MAX_DEPTH;
+            config.fifo_max_depth = fifo_max_depth;
+        }
+        config
+    }
+}
+
+pub fn mkbp_get_config<File: AsRawFd>(file: &mut File) -> EcCmdResult<EcMkbpConfig> {
+    ec_command_bytemuck(CrosEcCmd::MkbpGetConfig, 0, &(), file.as_raw_fd())
+}
+
+pub fn mkbp_set_config<File: AsRawFd>(file: &mut File, config: SetMkbpConfig) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::MkbpSetConfig,
+        0,
+        &config.to_params(),
+        file.as_raw_fd(),
+    )
+}
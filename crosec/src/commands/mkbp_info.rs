@@ -0,0 +1,58 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{ec_command::ec_command_bytemuck, wait_event::event::EcMkbpEventType, EcCmdResult};
+
+use super::CrosEcCmd;
+
+/// Get the current value of an event type instead of the supported values
+const EC_MKBP_INFO_CURRENT: u8 = 2;
+
+pub const EC_MKBP_LID_OPEN: u32 = 1 << 0;
+pub const EC_MKBP_TABLET_MODE: u32 = 1 << 1;
+pub const EC_MKBP_BASE_ATTACHED: u32 = 1 << 2;
+pub const EC_MKBP_FRONT_PROXIMITY: u32 = 1 << 3;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsMkbpInfo {
+    info_type: u8,
+    event_type: u8,
+}
+
+/// The state of the switches which are reported with MKBP `Switches` events
+#[repr(transparent)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MkbpSwitches(pub u32);
+
+impl MkbpSwitches {
+    pub fn lid_open(&self) -> bool {
+        self.0 & EC_MKBP_LID_OPEN != 0
+    }
+
+    pub fn tablet_mode(&self) -> bool {
+        self.0 & EC_MKBP_TABLET_MODE != 0
+    }
+
+    pub fn base_attached(&self) -> bool {
+        self.0 & EC_MKBP_BASE_ATTACHED != 0
+    }
+
+    pub fn front_proximity(&self) -> bool {
+        self.0 & EC_MKBP_FRONT_PROXIMITY != 0
+    }
+}
+
+/// Gets the current state of the switches, which is what the next `Switches` event would contain
+pub fn mkbp_get_switches<File: AsRawFd>(file: &mut File) -> EcCmdResult<MkbpSwitches> {
+    ec_command_bytemuck(
+        CrosEcCmd::MkbpInfo,
+        1,
+        &EcParamsMkbpInfo {
+            info_type: EC_MKBP_INFO_CURRENT,
+            event_type: EcMkbpEventType::Switches as u8,
+        },
+        file.as_raw_fd(),
+    )
+}
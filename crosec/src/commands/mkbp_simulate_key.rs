@@ -0,0 +1,35 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsMkbpSimulateKey {
+    col: u8,
+    row: u8,
+    pressed: u8,
+}
+
+/// Simulates a key press or release at the given position in the keyboard matrix.
+/// The key stays pressed until it is released with another call to this function.
+pub fn mkbp_simulate_key<File: AsRawFd>(
+    file: &mut File,
+    row: u8,
+    col: u8,
+    pressed: bool,
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::MkbpSimulateKey,
+        0,
+        &EcParamsMkbpSimulateKey {
+            col,
+            row,
+            pressed: pressed as u8,
+        },
+        file.as_raw_fd(),
+    )
+}
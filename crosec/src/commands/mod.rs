@@ -12,12 +12,52 @@ pub enum CrosEcCmd {
     GetCmdVersions = 0x0008,
     GetProtocolInfo = 0x000B,
     GetFeatures = 0x000D,
+    FlashInfo = 0x0010,
+    FlashRead = 0x0011,
+    FlashWrite = 0x0012,
+    FlashErase = 0x0013,
+    FlashProtect = 0x0015,
+    FlashRegionInfo = 0x0016,
     SetFanTargetRpm = 0x0021,
+    PwmGetKeyboardBacklight = 0x0022,
+    PwmSetKeyboardBacklight = 0x0023,
+    PwmSetDuty = 0x0025,
+    PwmGetDuty = 0x0026,
+    LightbarCmd = 0x0028,
+    LedControl = 0x0029,
+    VbootHash = 0x002A,
+    MotionSenseCmd = 0x002B,
+    UsbChargeSetMode = 0x0030,
+    SetTabletMode = 0x0031,
+    MkbpSimulateKey = 0x0062,
+    MkbpSetConfig = 0x0064,
+    MkbpGetConfig = 0x0065,
+    KeyscanSeqCtrl = 0x0066,
+    MkbpInfo = 0x0067,
     ChargeControl = 0x0096,
     ConsoleSnapshot = 0x0097,
     ConsoleRead = 0x0098,
+    HibernationDelay = 0x00A8,
+    RebootEc = 0x00D2,
+    GetPanicInfo = 0x00D3,
+    UsbPdControl = 0x0101,
+    UsbPdPorts = 0x0102,
+    UsbPdPowerInfo = 0x0103,
+    ChargePortCount = 0x0105,
+    UsbPdDevInfo = 0x0112,
+    PdChargePortOverride = 0x0114,
+    PdGetLogEntry = 0x0115,
+    PdChipInfo = 0x0119,
+    RwsigCheckStatus = 0x011C,
+    RwsigAction = 0x011D,
+    EfsVerify = 0x011E,
     GetUptimeInfo = 0x0121,
+    RebootApOnG3 = 0x0127,
     GetKeybdConfig = 0x012A,
+    TypecDiscovery = 0x0131,
+    TypecControl = 0x0132,
+    TypecStatus = 0x0133,
+    TypecVdmResponse = 0x013C,
     FpMode = 0x0402,
     FpInfo = 0x0403,
     FpFrame = 0x0404,
@@ -33,6 +73,9 @@ pub enum CrosEcCmd {
 pub mod board_version;
 pub mod charge_control;
 pub mod charge_current_limit;
+pub mod charge_port;
+pub mod efs_verify;
+pub mod flash;
 pub mod fp_download;
 pub mod fp_get_encryption_status;
 pub mod fp_info;
@@ -45,9 +88,29 @@ pub mod get_chip_info;
 pub mod get_cmd_versions;
 pub mod get_features;
 pub mod get_keyboard_config;
+pub mod get_panic_info;
 pub mod get_protocol_info;
 pub mod get_uptime_info;
 pub mod hello;
+pub mod hibernation_delay;
+pub mod keyboard_backlight;
+pub mod keyscan_seq_ctrl;
+pub mod led_control;
+pub mod lightbar;
+pub mod mkbp_config;
+pub mod mkbp_info;
+pub mod mkbp_simulate_key;
+pub mod motion_sense;
+pub mod pd_chip_info;
+pub mod pd_log;
+pub mod pwm_duty;
 pub mod read_mem;
+pub mod reboot_ap_on_g3;
+pub mod reboot_ec;
+pub mod rwsig;
 pub mod set_fan_target_rpm;
+pub mod set_tablet_mode;
+pub mod typec_control;
+pub mod usb_pd;
+pub mod vboot_hash;
 pub mod version;
@@ -0,0 +1,615 @@
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+
+use bytemuck::{bytes_of, pod_read_unaligned, AnyBitPattern, NoUninit, Pod, Zeroable};
+use strum_macros::{EnumIter, FromRepr, IntoStaticStr};
+
+use crate::{ec_command::ec_command_with_dynamic_output_size, EcCmdResult};
+
+use super::{get_protocol_info::EcResponseGetProtocolInfo, CrosEcCmd};
+
+/// Pass this instead of a value to only read the current value
+pub const EC_MOTION_SENSE_NO_VALUE: i32 = -1;
+pub const EC_MOTION_SENSE_INVALID_CALIB_TEMP: i16 = 0x8000_u16 as i16;
+/// The lid angle returned when the EC can't calculate it, such as when the lid is perpendicular to the ground
+pub const LID_ANGLE_UNRELIABLE: u16 = 500;
+/// A scale of 1
+pub const MOTION_SENSE_DEFAULT_SCALE: u16 = 1 << 15;
+
+/// The motion sense module is active
+pub const MOTIONSENSE_MODULE_FLAG_ACTIVE: u8 = 1 << 0;
+/// Used in dump. The sensor is present.
+pub const MOTIONSENSE_SENSOR_FLAG_PRESENT: u8 = 1 << 0;
+
+/// Used in the FIFO. The sample was requested by a FIFO flush.
+pub const MOTIONSENSE_SENSOR_FLAG_FLUSH: u8 = 1 << 0;
+/// Used in the FIFO. The sample is a timestamp instead of sensor data.
+pub const MOTIONSENSE_SENSOR_FLAG_TIMESTAMP: u8 = 1 << 1;
+pub const MOTIONSENSE_SENSOR_FLAG_WAKEUP: u8 = 1 << 2;
+pub const MOTIONSENSE_SENSOR_FLAG_TABLET_MODE: u8 = 1 << 3;
+/// Used in the FIFO. The ODR of the sensor changed.
+pub const MOTIONSENSE_SENSOR_FLAG_ODR: u8 = 1 << 4;
+pub const MOTIONSENSE_SENSOR_FLAG_BYPASS_FIFO: u8 = 1 << 7;
+
+const MOTION_SENSE_SET_OFFSET: u16 = 1 << 0;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum MotionSenseCommand {
+    Dump = 0,
+    Info = 1,
+    EcRate = 2,
+    SensorOdr = 3,
+    SensorRange = 4,
+    KbWakeAngle = 5,
+    Data = 6,
+    FifoInfo = 7,
+    FifoFlush = 8,
+    FifoRead = 9,
+    PerformCalib = 10,
+    SensorOffset = 11,
+    LidAngle = 14,
+    FifoIntEnable = 15,
+    Spoof = 16,
+    TabletModeLidAngle = 17,
+    SensorScale = 18,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionSensorType {
+    Accel,
+    Gyro,
+    Mag,
+    Prox,
+    Light,
+    Activity,
+    Baro,
+    Sync,
+    LightRgb,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionSensorLocation {
+    Base,
+    Lid,
+    Camera,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionSensorChip {
+    Kxcj9,
+    Lsm6ds0,
+    Bmi160,
+    Si1141,
+    Si1142,
+    Si1143,
+    Kx022,
+    L3gd20h,
+    Bma255,
+    Bmp280,
+    Opt3001,
+    Bh1730,
+    Gpio,
+    Lis2dh,
+    Lsm6dsm,
+    Lis2de,
+    Lis2mdl,
+    Lsm6ds3,
+    Lsm6dso,
+    Lng2dm,
+    Tcs3400,
+    Lis2dw12,
+    Lis2dwl,
+    Lis2ds,
+    Bmi260,
+    Icm426xx,
+    Icm42607,
+    Bma422,
+    Bmi323,
+    Bmi220,
+    Cm32183,
+    Veml3328,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MotionSenseSpoofMode {
+    /// Stop spoofing the sensor
+    Disable,
+    /// Spoof the given values
+    Custom,
+    /// Spoof the sensor's current values
+    LockCurrent,
+    /// Check whether spoofing is enabled
+    Query,
+}
+
+/// One sample from a sensor. In the FIFO, this can also be a timestamp (see [`MOTIONSENSE_SENSOR_FLAG_TIMESTAMP`]).
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcResponseMotionSensorData {
+    /// See `MOTIONSENSE_SENSOR_FLAG_*`
+    pub flags: u8,
+    pub sensor_num: u8,
+    pub data: [i16; 3],
+}
+
+impl EcResponseMotionSensorData {
+    /// The data for sensors which report unsigned values, such as light sensors
+    pub fn udata(&self) -> [u16; 3] {
+        let data = self.data;
+        data.map(|value| value as u16)
+    }
+
+    /// The EC timestamp in microseconds, if this entry is a timestamp
+    pub fn timestamp(&self) -> Option<u32> {
+        if self.flags & MOTIONSENSE_SENSOR_FLAG_TIMESTAMP != 0 {
+            let data = self.data;
+            Some(pod_read_unaligned(&bytes_of(&data)[2..6]))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C, packed)]
+pub struct EcResponseMotionSenseFifoInfo {
+    /// The size of the FIFO, in entries
+    pub size: u16,
+    /// The number of entries in the FIFO
+    pub count: u16,
+    /// The EC timestamp in microseconds of when the FIFO info was sampled
+    pub timestamp: u32,
+    /// The total number of entries which were lost because the FIFO was full
+    pub total_lost: u16,
+    /// The number of lost entries for each sensor follows this
+    pub lost: [u16; 0],
+}
+
+#[derive(Debug, Clone)]
+pub struct MotionSenseFifoInfo {
+    pub info: EcResponseMotionSenseFifoInfo,
+    /// The number of lost entries for each sensor, indexed by sensor number
+    pub lost: Vec<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MotionSenseDump {
+    /// See [`MOTIONSENSE_MODULE_FLAG_ACTIVE`]
+    pub module_flags: u8,
+    /// The total number of sensors, which can be more than the number of `sensors` returned
+    pub sensor_count: u8,
+    /// See [`MOTIONSENSE_SENSOR_FLAG_PRESENT`]
+    pub sensors: Vec<EcResponseMotionSensorData>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MotionSenseFrequencies {
+    /// In mHz
+    pub min_frequency: u32,
+    /// In mHz
+    pub max_frequency: u32,
+    /// The max number of events from this sensor that could be in the FIFO
+    pub fifo_max_event_count: u32,
+}
+
+/// Unknown values are kept as the raw number in the `Err`
+#[derive(Debug, Clone, Copy)]
+pub struct MotionSenseInfo {
+    pub sensor_type: Result<MotionSensorType, u8>,
+    pub location: Result<MotionSensorLocation, u8>,
+    pub chip: Result<MotionSensorChip, u8>,
+    /// Only available with version 3 of the info sub-command
+    pub frequencies: Option<MotionSenseFrequencies>,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseMotionSenseInfo {
+    sensor_type: u8,
+    location: u8,
+    chip: u8,
+    _padding: u8,
+    min_frequency: u32,
+    max_frequency: u32,
+    fifo_max_event_count: u32,
+}
+
+/// The calibration offset of a sensor, in the sensor's units
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct MotionSenseOffset {
+    /// The temperature at calibration in 1/100 of a degree C, or [`EC_MOTION_SENSE_INVALID_CALIB_TEMP`]
+    pub temp: i16,
+    pub offset: [i16; 3],
+}
+
+/// The calibration scale of a sensor, where [`MOTION_SENSE_DEFAULT_SCALE`] is a scale of 1
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct MotionSenseScale {
+    /// The temperature at calibration in 1/100 of a degree C, or [`EC_MOTION_SENSE_INVALID_CALIB_TEMP`]
+    pub temp: i16,
+    pub scale: [u16; 3],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct TabletModeLidAngle {
+    /// The lid angle in degrees where the device switches to tablet mode
+    pub lid_angle: u16,
+    /// The hysteresis in degrees
+    pub hys_degree: u16,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsMotionSenseRate {
+    sensor_num: u8,
+    roundup: u8,
+    reserved: u16,
+    data: i32,
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsMotionSenseCalib {
+    sensor_num: u8,
+    flags: u16,
+    temp: i16,
+    values: [i16; 3],
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsMotionSenseSpoof {
+    sensor_id: u8,
+    spoof_enable: u8,
+    reserved: u8,
+    components: [i16; 3],
+}
+
+/// Sends a motion sense sub-command, which is the `cmd` byte followed by the sub-command's params
+fn motion_sense_command_bytes<File: AsRawFd>(
+    file: &mut File,
+    version: u8,
+    cmd: MotionSenseCommand,
+    params: &[u8],
+    output_size: usize,
+) -> EcCmdResult<Vec<u8>> {
+    let mut input = vec![cmd as u8];
+    input.extend_from_slice(params);
+    ec_command_with_dynamic_output_size(
+        CrosEcCmd::MotionSenseCmd,
+        version,
+        &input,
+        output_size,
+        file.as_raw_fd(),
+    )
+}
+
+fn motion_sense_command<File: AsRawFd, Params: NoUninit, Response: AnyBitPattern>(
+    file: &mut File,
+    cmd: MotionSenseCommand,
+    params: &Params,
+) -> EcCmdResult<Response> {
+    let response =
+        motion_sense_command_bytes(file, 2, cmd, bytes_of(params), size_of::<Response>())?;
+    Ok(pod_read_unaligned(&response))
+}
+
+fn motion_sense_rate<File: AsRawFd>(
+    file: &mut File,
+    cmd: MotionSenseCommand,
+    sensor_num: u8,
+    value: Option<i32>,
+    roundup: bool,
+) -> EcCmdResult<i32> {
+    motion_sense_command(
+        file,
+        cmd,
+        &EcParamsMotionSenseRate {
+            sensor_num,
+            roundup: roundup as u8,
+            reserved: 0,
+            data: value.unwrap_or(EC_MOTION_SENSE_NO_VALUE),
+        },
+    )
+}
+
+/// Gets the number of sensors and the latest data of up to `max_sensor_count` sensors
+pub fn motion_sense_dump<File: AsRawFd>(
+    file: &mut File,
+    max_sensor_count: u8,
+) -> EcCmdResult<MotionSenseDump> {
+    let response = motion_sense_command_bytes(
+        file,
+        2,
+        MotionSenseCommand::Dump,
+        &[max_sensor_count],
+        2 + max_sensor_count as usize * size_of::<EcResponseMotionSensorData>(),
+    )?;
+    let sensor_count = response[1];
+    let num_sensors = sensor_count.min(max_sensor_count) as usize;
+    Ok(MotionSenseDump {
+        module_flags: response[0],
+        sensor_count,
+        sensors: response[2..2 + num_sensors * size_of::<EcResponseMotionSensorData>()]
+            .chunks_exact(size_of::<EcResponseMotionSensorData>())
+            .map(pod_read_unaligned)
+            .collect(),
+    })
+}
+
+/// Gets the number of sensors
+pub fn motion_sense_sensor_count<File: AsRawFd>(file: &mut File) -> EcCmdResult<u8> {
+    Ok(motion_sense_dump(file, 0)?.sensor_count)
+}
+
+/// `version` can be 1 or 3. Version 3 also gets the sensor's frequency range.
+pub fn motion_sense_info<File: AsRawFd>(
+    file: &mut File,
+    version: u8,
+    sensor_num: u8,
+) -> EcCmdResult<MotionSenseInfo> {
+    let output_size = if version >= 3 {
+        size_of::<EcResponseMotionSenseInfo>()
+    } else {
+        3
+    };
+    let mut response = motion_sense_command_bytes(
+        file,
+        version,
+        MotionSenseCommand::Info,
+        &[sensor_num],
+        output_size,
+    )?;
+    response.resize(size_of::<EcResponseMotionSenseInfo>(), 0);
+    let info: EcResponseMotionSenseInfo = pod_read_unaligned(&response);
+    Ok(MotionSenseInfo {
+        sensor_type: MotionSensorType::from_repr(info.sensor_type).ok_or(info.sensor_type),
+        location: MotionSensorLocation::from_repr(info.location).ok_or(info.location),
+        chip: MotionSensorChip::from_repr(info.chip).ok_or(info.chip),
+        frequencies: (version >= 3).then_some(MotionSenseFrequencies {
+            min_frequency: info.min_frequency,
+            max_frequency: info.max_frequency,
+            fifo_max_event_count: info.fifo_max_event_count,
+        }),
+    })
+}
+
+/// Gets or sets the rate in ms at which the EC reads the sensors when the FIFO isn't used
+pub fn motion_sense_ec_rate<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    rate_ms: Option<i32>,
+) -> EcCmdResult<i32> {
+    motion_sense_rate(file, MotionSenseCommand::EcRate, sensor_num, rate_ms, false)
+}
+
+/// Gets or sets the output data rate of a sensor in mHz.
+/// If `roundup` is true, the sensor will use the closest rate that is at least the requested rate.
+pub fn motion_sense_sensor_odr<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    odr: Option<i32>,
+    roundup: bool,
+) -> EcCmdResult<i32> {
+    motion_sense_rate(
+        file,
+        MotionSenseCommand::SensorOdr,
+        sensor_num,
+        odr,
+        roundup,
+    )
+}
+
+/// Gets or sets the range of a sensor, such as in G for accelerometers.
+/// If `roundup` is true, the sensor will use the closest range that is at least the requested range.
+pub fn motion_sense_sensor_range<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    range: Option<i32>,
+    roundup: bool,
+) -> EcCmdResult<i32> {
+    motion_sense_rate(
+        file,
+        MotionSenseCommand::SensorRange,
+        sensor_num,
+        range,
+        roundup,
+    )
+}
+
+/// Gets or sets the lid angle in degrees above which the keyboard doesn't wake the device
+pub fn motion_sense_kb_wake_angle<File: AsRawFd>(
+    file: &mut File,
+    angle: Option<i16>,
+) -> EcCmdResult<i32> {
+    motion_sense_command(
+        file,
+        MotionSenseCommand::KbWakeAngle,
+        &angle.unwrap_or(EC_MOTION_SENSE_NO_VALUE as i16),
+    )
+}
+
+/// Gets the latest data of a sensor
+pub fn motion_sense_data<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+) -> EcCmdResult<EcResponseMotionSensorData> {
+    motion_sense_command(file, MotionSenseCommand::Data, &sensor_num)
+}
+
+fn parse_fifo_info(response: &[u8]) -> MotionSenseFifoInfo {
+    let header_size = size_of::<EcResponseMotionSenseFifoInfo>();
+    MotionSenseFifoInfo {
+        info: pod_read_unaligned(&response[..header_size]),
+        lost: response[header_size..]
+            .chunks_exact(size_of::<u16>())
+            .map(pod_read_unaligned)
+            .collect(),
+    }
+}
+
+/// Gets the FIFO info and clears the lost counts. Use [`motion_sense_sensor_count`] to get `sensor_count`.
+pub fn motion_sense_fifo_info<File: AsRawFd>(
+    file: &mut File,
+    sensor_count: u8,
+) -> EcCmdResult<MotionSenseFifoInfo> {
+    let response = motion_sense_command_bytes(
+        file,
+        2,
+        MotionSenseCommand::FifoInfo,
+        &[],
+        size_of::<EcResponseMotionSenseFifoInfo>() + sensor_count as usize * size_of::<u16>(),
+    )?;
+    Ok(parse_fifo_info(&response))
+}
+
+/// Adds an entry with [`MOTIONSENSE_SENSOR_FLAG_FLUSH`] for the sensor to the FIFO, and returns the FIFO info
+pub fn motion_sense_fifo_flush<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    sensor_count: u8,
+) -> EcCmdResult<MotionSenseFifoInfo> {
+    let response = motion_sense_command_bytes(
+        file,
+        2,
+        MotionSenseCommand::FifoFlush,
+        &[sensor_num],
+        size_of::<EcResponseMotionSenseFifoInfo>() + sensor_count as usize * size_of::<u16>(),
+    )?;
+    Ok(parse_fifo_info(&response))
+}
+
+/// Reads and removes as many entries from the FIFO as fit in one response
+pub fn motion_sense_fifo_read<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+) -> EcCmdResult<Vec<EcResponseMotionSensorData>> {
+    let max_data_vector = (protocol_info.max_ec_output_size() - size_of::<u32>())
+        / size_of::<EcResponseMotionSensorData>();
+    let response = motion_sense_command_bytes(
+        file,
+        2,
+        MotionSenseCommand::FifoRead,
+        bytes_of(&(max_data_vector as u32)),
+        size_of::<u32>() + max_data_vector * size_of::<EcResponseMotionSensorData>(),
+    )?;
+    let number_data = pod_read_unaligned::<u32>(&response[..size_of::<u32>()]) as usize;
+    Ok(response[size_of::<u32>()..]
+        .chunks_exact(size_of::<EcResponseMotionSensorData>())
+        .take(number_data)
+        .map(pod_read_unaligned)
+        .collect())
+}
+
+/// Starts or stops calibration of a sensor. The sensor must be still while it is calibrating.
+pub fn motion_sense_perform_calib<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    enable: bool,
+) -> EcCmdResult<MotionSenseOffset> {
+    motion_sense_command(
+        file,
+        MotionSenseCommand::PerformCalib,
+        &[sensor_num, enable as u8],
+    )
+}
+
+/// Gets the calibration offset of a sensor, or sets it if `offset` is `Some`
+pub fn motion_sense_sensor_offset<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    offset: Option<MotionSenseOffset>,
+) -> EcCmdResult<MotionSenseOffset> {
+    let offset = offset.map(|offset| (MOTION_SENSE_SET_OFFSET, offset));
+    let (flags, offset) = offset.unwrap_or((0, MotionSenseOffset::zeroed()));
+    motion_sense_command(
+        file,
+        MotionSenseCommand::SensorOffset,
+        &EcParamsMotionSenseCalib {
+            sensor_num,
+            flags,
+            temp: offset.temp,
+            values: offset.offset,
+        },
+    )
+}
+
+/// Gets the calibration scale of a sensor, or sets it if `scale` is `Some`
+pub fn motion_sense_sensor_scale<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    scale: Option<MotionSenseScale>,
+) -> EcCmdResult<MotionSenseScale> {
+    let scale = scale.map(|scale| (MOTION_SENSE_SET_OFFSET, scale));
+    let (flags, scale) = scale.unwrap_or((0, MotionSenseScale::zeroed()));
+    motion_sense_command(
+        file,
+        MotionSenseCommand::SensorScale,
+        &EcParamsMotionSenseCalib {
+            sensor_num,
+            flags,
+            temp: scale.temp,
+            values: scale.scale.map(|scale| scale as i16),
+        },
+    )
+}
+
+/// Returns the angle between the lid and the base in degrees, or [`LID_ANGLE_UNRELIABLE`]
+pub fn motion_sense_lid_angle<File: AsRawFd>(file: &mut File) -> EcCmdResult<u16> {
+    motion_sense_command(file, MotionSenseCommand::LidAngle, &())
+}
+
+/// Enables or disables the host event when there is new data in the FIFO, or just gets the current state if `enable` is `None`
+pub fn motion_sense_fifo_int_enable<File: AsRawFd>(
+    file: &mut File,
+    enable: Option<bool>,
+) -> EcCmdResult<bool> {
+    let enable = enable.map_or(EC_MOTION_SENSE_NO_VALUE as i8, |enable| enable as i8);
+    let enabled: i32 = motion_sense_command(file, MotionSenseCommand::FifoIntEnable, &enable)?;
+    Ok(enabled != 0)
+}
+
+/// Makes a sensor report fake values. `components` is only used with [`MotionSenseSpoofMode::Custom`].
+/// Returns whether spoofing is enabled for [`MotionSenseSpoofMode::Query`].
+pub fn motion_sense_spoof<File: AsRawFd>(
+    file: &mut File,
+    sensor_num: u8,
+    mode: MotionSenseSpoofMode,
+    components: [i16; 3],
+) -> EcCmdResult<bool> {
+    let enabled: i32 = motion_sense_command(
+        file,
+        MotionSenseCommand::Spoof,
+        &EcParamsMotionSenseSpoof {
+            sensor_id: sensor_num,
+            spoof_enable: mode as u8,
+            reserved: 0,
+            components,
+        },
+    )?;
+    Ok(enabled != 0)
+}
+
+/// Gets or sets the lid angle threshold for tablet mode
+pub fn motion_sense_tablet_mode_lid_angle<File: AsRawFd>(
+    file: &mut File,
+    threshold: Option<TabletModeLidAngle>,
+) -> EcCmdResult<TabletModeLidAngle> {
+    let params = threshold.map_or(
+        [
+            EC_MOTION_SENSE_NO_VALUE as i16,
+            EC_MOTION_SENSE_NO_VALUE as i16,
+        ],
+        |threshold| [threshold.lid_angle as i16, threshold.hys_degree as i16],
+    );
+    motion_sense_command(file, MotionSenseCommand::TabletModeLidAngle, &params)
+}
@@ -0,0 +1,123 @@
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+
+use bytemuck::{bytes_of, pod_read_unaligned, Pod, Zeroable};
+use num_traits::FromPrimitive;
+
+use crate::{ec_command::ec_command_with_dynamic_output_size, EcCmdResult};
+
+use super::{version::EcImage, CrosEcCmd};
+
+pub const PD_RW_HASH_SIZE: usize = 20;
+/// The firmware version number is all 1s when the TCPC doesn't report it
+const PD_CHIP_INFO_FW_VERSION_UNSUPPORTED: u64 = u64::MAX;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsPdChipInfo {
+    port: u8,
+    live: u8,
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponsePdChipInfoV1 {
+    vendor_id: u16,
+    product_id: u16,
+    device_id: u16,
+    fw_version_number: u64,
+    min_req_fw_version_number: u64,
+}
+
+/// Info about the TCPC of a port
+#[derive(Debug, Clone, Copy)]
+pub struct PdChipInfo {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub device_id: u16,
+    /// `None` if the TCPC doesn't report its firmware version
+    pub fw_version: Option<u64>,
+    /// The oldest firmware version the EC supports. This is only available with version 1.
+    pub min_req_fw_version: Option<u64>,
+}
+
+/// Gets the info of the TCPC of a port.
+/// If `live` is true, the EC reads the info from the chip instead of using the info it cached at boot.
+pub fn pd_chip_info<File: AsRawFd>(
+    file: &mut File,
+    version: u8,
+    port: u8,
+    live: bool,
+) -> EcCmdResult<PdChipInfo> {
+    let params = EcParamsPdChipInfo {
+        port,
+        live: live as u8,
+    };
+    let response_size = match version {
+        0 => size_of::<EcResponsePdChipInfoV1>() - size_of::<u64>(),
+        _ => size_of::<EcResponsePdChipInfoV1>(),
+    };
+    let mut response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::PdChipInfo,
+        version,
+        bytes_of(&params),
+        response_size,
+        file.as_raw_fd(),
+    )?;
+    // Version 0 doesn't have the min required version, so it is treated as unsupported
+    response.resize(size_of::<EcResponsePdChipInfoV1>(), 0xff);
+    let response: EcResponsePdChipInfoV1 = pod_read_unaligned(&response);
+    let fw_version =
+        |version: u64| Some(version).filter(|v| *v != PD_CHIP_INFO_FW_VERSION_UNSUPPORTED);
+    Ok(PdChipInfo {
+        vendor_id: response.vendor_id,
+        product_id: response.product_id,
+        device_id: response.device_id,
+        fw_version: fw_version(response.fw_version_number),
+        min_req_fw_version: fw_version(response.min_req_fw_version_number),
+    })
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseUsbPdDevInfo {
+    dev_id: u16,
+    dev_rw_hash: [u8; PD_RW_HASH_SIZE],
+    reserved: u8,
+    current_image: u32,
+}
+
+/// Info about a device with updatable firmware, such as a dock, which is connected to a port
+#[derive(Debug, Clone, Copy)]
+pub struct UsbPdDevInfo {
+    /// 0 if no device is connected
+    pub dev_id: u16,
+    pub dev_rw_hash: [u8; PD_RW_HASH_SIZE],
+    pub current_image: Result<EcImage, u32>,
+}
+
+impl UsbPdDevInfo {
+    pub fn dev_id_major(&self) -> u16 {
+        self.dev_id & 0x3ff
+    }
+
+    pub fn dev_id_minor(&self) -> u16 {
+        self.dev_id >> 10
+    }
+}
+
+pub fn usb_pd_dev_info<File: AsRawFd>(file: &mut File, port: u8) -> EcCmdResult<UsbPdDevInfo> {
+    let response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::UsbPdDevInfo,
+        0,
+        &[port],
+        size_of::<EcResponseUsbPdDevInfo>(),
+        file.as_raw_fd(),
+    )?;
+    let response: EcResponseUsbPdDevInfo = pod_read_unaligned(&response);
+    Ok(UsbPdDevInfo {
+        dev_id: response.dev_id,
+        dev_rw_hash: response.dev_rw_hash,
+        current_image: EcImage::from_u32(response.current_image).ok_or(response.current_image),
+    })
+}
@@ -0,0 +1,308 @@
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+use std::time::{Duration, SystemTime};
+
+use bytemuck::{pod_read_unaligned, Pod, Zeroable};
+use strum_macros::{FromRepr, IntoStaticStr};
+use uom::si::electric_current::milliampere;
+use uom::si::electric_potential::millivolt;
+use uom::si::f32::{ElectricCurrent, ElectricPotential};
+
+use crate::{ec_command::ec_command_with_dynamic_output_size, EcCmdResult};
+
+use super::{
+    usb_pd::{UsbChargerType, UsbPowerRole},
+    CrosEcCmd,
+};
+
+/// 1 LSB of the timestamp is 1024 us
+pub const PD_LOG_TIMESTAMP_SHIFT: u32 = 10;
+pub const PD_LOG_SIZE_MASK: u8 = 0x1f;
+pub const PD_LOG_PORT_SHIFT: u8 = 5;
+
+pub const PD_EVENT_MCU_CHARGE: u8 = 0x00;
+pub const PD_EVENT_MCU_CONNECT: u8 = 0x01;
+/// Reserved for custom board events
+pub const PD_EVENT_MCU_BOARD_CUSTOM: u8 = 0x02;
+pub const PD_EVENT_ACC_RW_FAIL: u8 = 0x20;
+pub const PD_EVENT_ACC_RW_ERASE: u8 = 0x21;
+pub const PD_EVENT_PS_FAULT: u8 = 0x40;
+pub const PD_EVENT_VIDEO_DP_MODE: u8 = 0x60;
+pub const PD_EVENT_VIDEO_CODEC: u8 = 0x61;
+/// The type of the entry when the log is empty
+pub const PD_EVENT_NO_ENTRY: u8 = 0xff;
+
+/// The port partner is a dual role device
+pub const CHARGE_FLAGS_DUAL_ROLE: u16 = 1 << 15;
+/// The port is the pending override port
+pub const CHARGE_FLAGS_DELAYED_OVERRIDE: u16 = 1 << 14;
+/// The port is the override port
+pub const CHARGE_FLAGS_OVERRIDE: u16 = 1 << 13;
+pub const CHARGE_FLAGS_TYPE_SHIFT: u16 = 3;
+pub const CHARGE_FLAGS_TYPE_MASK: u16 = 0xf << CHARGE_FLAGS_TYPE_SHIFT;
+pub const CHARGE_FLAGS_ROLE_MASK: u16 = 0b111;
+
+/// The entry header plus the biggest payload
+const PD_LOG_ENTRY_MAX_SIZE: usize = 32;
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponsePdLog {
+    timestamp: u32,
+    event_type: u8,
+    size_port: u8,
+    data: u16,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct UsbChgMeasures {
+    voltage_max: u16,
+    voltage_now: u16,
+    current_max: u16,
+    current_lim: u16,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct McdpVersion {
+    major: u8,
+    minor: u8,
+    build: u16,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct McdpInfo {
+    family: [u8; 2],
+    chip_id: [u8; 2],
+    irom: McdpVersion,
+    fw: McdpVersion,
+}
+
+#[repr(u16)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsFault {
+    Ocp = 1,
+    FastOcp,
+    Ovp,
+    Discharge,
+}
+
+/// A power supply change on a charge port
+#[derive(Debug, Clone, Copy)]
+pub struct PdLogCharge {
+    pub role: Result<UsbPowerRole, u8>,
+    pub charger_type: Result<UsbChargerType, u8>,
+    /// The port partner is a dual role device
+    pub dualrole: bool,
+    /// This port is the charge override port
+    pub override_port: bool,
+    /// This port will become the charge override port
+    pub delayed_override: bool,
+    pub voltage_max: ElectricPotential,
+    pub voltage_now: ElectricPotential,
+    pub current_max: ElectricCurrent,
+    pub current_lim: ElectricCurrent,
+}
+
+/// The version of a video codec in a dongle, as `major.minor.build`
+pub type VideoCodecVersion = (u8, u8, u16);
+
+#[derive(Debug, Clone)]
+pub enum PdLogEvent {
+    Charge(PdLogCharge),
+    /// A new partner connected to the port
+    Connect,
+    BoardCustom,
+    /// The RW signature check of an accessory failed
+    AccessoryRwFail,
+    /// The RW image of an accessory was erased
+    AccessoryRwErase,
+    PowerSupplyFault(Result<PsFault, u16>),
+    /// DP mode was enabled or disabled on a video dongle
+    DpMode(bool),
+    VideoCodec {
+        family: u16,
+        chip_id: u16,
+        irom: VideoCodecVersion,
+        fw: VideoCodecVersion,
+    },
+    /// An event type which the EC doesn't define, or an entry whose payload is too short
+    Unknown {
+        event_type: u8,
+        data: u16,
+        payload: Vec<u8>,
+    },
+}
+
+impl PdLogEvent {
+    fn decode(event_type: u8, data: u16, payload: &[u8]) -> Self {
+        let unknown = || Self::Unknown {
+            event_type,
+            data,
+            payload: payload.to_vec(),
+        };
+        match event_type {
+            PD_EVENT_MCU_CHARGE => {
+                let Some(measures) = payload.get(..size_of::<UsbChgMeasures>()) else {
+                    return unknown();
+                };
+                let measures: UsbChgMeasures = pod_read_unaligned(measures);
+                let role = (data & CHARGE_FLAGS_ROLE_MASK) as u8;
+                let charger_type =
+                    ((data & CHARGE_FLAGS_TYPE_MASK) >> CHARGE_FLAGS_TYPE_SHIFT) as u8;
+                Self::Charge(PdLogCharge {
+                    role: UsbPowerRole::from_repr(role).ok_or(role),
+                    charger_type: UsbChargerType::from_repr(charger_type).ok_or(charger_type),
+                    dualrole: data & CHARGE_FLAGS_DUAL_ROLE != 0,
+                    override_port: data & CHARGE_FLAGS_OVERRIDE != 0,
+                    delayed_override: data & CHARGE_FLAGS_DELAYED_OVERRIDE != 0,
+                    voltage_max: ElectricPotential::new::<millivolt>(measures.voltage_max as f32),
+                    voltage_now: ElectricPotential::new::<millivolt>(measures.voltage_now as f32),
+                    current_max: ElectricCurrent::new::<milliampere>(measures.current_max as f32),
+                    current_lim: ElectricCurrent::new::<milliampere>(measures.current_lim as f32),
+                })
+            }
+            PD_EVENT_MCU_CONNECT => Self::Connect,
+            PD_EVENT_MCU_BOARD_CUSTOM => Self::BoardCustom,
+            PD_EVENT_ACC_RW_FAIL => Self::AccessoryRwFail,
+            PD_EVENT_ACC_RW_ERASE => Self::AccessoryRwErase,
+            PD_EVENT_PS_FAULT => Self::PowerSupplyFault(PsFault::from_repr(data).ok_or(data)),
+            PD_EVENT_VIDEO_DP_MODE => Self::DpMode(data == 1),
+            PD_EVENT_VIDEO_CODEC => {
+                let Some(info) = payload.get(..size_of::<McdpInfo>()) else {
+                    return unknown();
+                };
+                let info: McdpInfo = pod_read_unaligned(info);
+                let version = |version: McdpVersion| (version.major, version.minor, version.build);
+                Self::VideoCodec {
+                    family: u16::from_be_bytes(info.family),
+                    chip_id: u16::from_be_bytes(info.chip_id),
+                    irom: version(info.irom),
+                    fw: version(info.fw),
+                }
+            }
+            _ => unknown(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PdLogEntry {
+    pub port: u8,
+    /// How long before the entry was read the event happened
+    pub age: Duration,
+    /// The time of the event, based on when the entry was read
+    pub time: SystemTime,
+    pub event: PdLogEvent,
+}
+
+impl PdLogEntry {
+    /// The time of the event in milliseconds since the EC booted, given the uptime when the entry was read.
+    /// See [`super::get_uptime_info::EcResponseUptimeInfo::time_since_ec_boot_ms`].
+    pub fn time_since_ec_boot_ms(&self, uptime_ms: u32) -> Option<u32> {
+        uptime_ms.checked_sub(self.age.as_millis().try_into().ok()?)
+    }
+}
+
+/// Gets the oldest entry and removes it from the log. Returns `None` when the log is empty.
+pub fn pd_get_log_entry<File: AsRawFd>(file: &mut File) -> EcCmdResult<Option<PdLogEntry>> {
+    let response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::PdGetLogEntry,
+        0,
+        &[],
+        PD_LOG_ENTRY_MAX_SIZE,
+        file.as_raw_fd(),
+    )?;
+    let now = SystemTime::now();
+    let (header, payload) = response.split_at(size_of::<EcResponsePdLog>());
+    let header: EcResponsePdLog = pod_read_unaligned(header);
+    if header.event_type == PD_EVENT_NO_ENTRY {
+        return Ok(None);
+    }
+    // The timestamp is how long ago the event happened, in units of 1024 us
+    let age = Duration::from_micros((header.timestamp as u64) << PD_LOG_TIMESTAMP_SHIFT);
+    let payload_size = ((header.size_port & PD_LOG_SIZE_MASK) as usize).min(payload.len());
+    Ok(Some(PdLogEntry {
+        port: header.size_port >> PD_LOG_PORT_SHIFT,
+        age,
+        time: now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH),
+        event: PdLogEvent::decode(header.event_type, header.data, &payload[..payload_size]),
+    }))
+}
+
+/// Reads and removes every entry in the log, oldest first
+pub fn pd_get_log<File: AsRawFd>(file: &mut File) -> EcCmdResult<Vec<PdLogEntry>> {
+    let mut entries = Vec::new();
+    while let Some(entry) = pd_get_log_entry(file)? {
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_charge() {
+        let data = CHARGE_FLAGS_DUAL_ROLE
+            | (UsbChargerType::Pd as u16) << CHARGE_FLAGS_TYPE_SHIFT
+            | UsbPowerRole::Sink as u16;
+        #[rustfmt::skip]
+        let payload = [
+            // voltage_max, voltage_now, current_max, current_lim
+            0x20, 0x4e, 0x58, 0x4d, 0xb8, 0x0b, 0x54, 0x0b,
+        ];
+        let PdLogEvent::Charge(charge) = PdLogEvent::decode(PD_EVENT_MCU_CHARGE, data, &payload)
+        else {
+            panic!("not a charge event");
+        };
+        assert_eq!(charge.role, Ok(UsbPowerRole::Sink));
+        assert_eq!(charge.charger_type, Ok(UsbChargerType::Pd));
+        assert!(charge.dualrole);
+        assert!(!charge.override_port);
+        assert!(!charge.delayed_override);
+        assert_eq!(charge.voltage_max.get::<millivolt>().round(), 20000.0);
+        assert_eq!(charge.voltage_now.get::<millivolt>().round(), 19800.0);
+        assert_eq!(charge.current_max.get::<milliampere>().round(), 3000.0);
+        assert_eq!(charge.current_lim.get::<milliampere>().round(), 2900.0);
+    }
+
+    #[test]
+    fn decode_video_codec() {
+        #[rustfmt::skip]
+        let payload = [
+            // family and chip ID, which are big endian
+            0x20, 0x10, 0x00, 0x02,
+            // irom and fw versions
+            0x01, 0x02, 0x04, 0x03,
+            0x03, 0x04, 0x06, 0x05,
+        ];
+        let PdLogEvent::VideoCodec {
+            family,
+            chip_id,
+            irom,
+            fw,
+        } = PdLogEvent::decode(PD_EVENT_VIDEO_CODEC, 0, &payload)
+        else {
+            panic!("not a video codec event");
+        };
+        assert_eq!(family, 0x2010);
+        assert_eq!(chip_id, 0x0002);
+        assert_eq!(irom, (1, 2, 0x0304));
+        assert_eq!(fw, (3, 4, 0x0506));
+    }
+
+    #[test]
+    fn decode_short_payload() {
+        assert!(matches!(
+            PdLogEvent::decode(PD_EVENT_VIDEO_CODEC, 0, &[0x20, 0x10]),
+            PdLogEvent::Unknown {
+                event_type: PD_EVENT_VIDEO_CODEC,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+use strum_macros::FromRepr;
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+/// The duty cycle that means the PWM is always on
+pub const EC_PWM_MAX_DUTY: u16 = 0xffff;
+
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum PwmType {
+    /// All types, indexed by board-specific enum pwm_channel
+    Generic,
+    /// Keyboard backlight
+    KbLight,
+    /// Display backlight
+    DisplayLight,
+}
+
+#[repr(C, align(4))]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsPwmSetDuty {
+    /// Duty cycle, [`EC_PWM_MAX_DUTY`] = 100%
+    duty: u16,
+    pwm_type: u8,
+    /// Type-specific index, or 0 if unique
+    index: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsPwmGetDuty {
+    pwm_type: u8,
+    index: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponsePwmGetDuty {
+    duty: u16,
+}
+
+/// `index` is only needed for [`PwmType::Generic`], and should be 0 otherwise.
+/// `duty` is out of [`EC_PWM_MAX_DUTY`].
+pub fn pwm_set_duty<File: AsRawFd>(
+    file: &mut File,
+    pwm_type: PwmType,
+    index: u8,
+    duty: u16,
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::PwmSetDuty,
+        0,
+        &EcParamsPwmSetDuty {
+            duty,
+            pwm_type: pwm_type as u8,
+            index,
+        },
+        file.as_raw_fd(),
+    )
+}
+
+/// Returns the duty out of [`EC_PWM_MAX_DUTY`]
+pub fn pwm_get_duty<File: AsRawFd>(
+    file: &mut File,
+    pwm_type: PwmType,
+    index: u8,
+) -> EcCmdResult<u16> {
+    let response: EcResponsePwmGetDuty = ec_command_bytemuck(
+        CrosEcCmd::PwmGetDuty,
+        0,
+        &EcParamsPwmGetDuty {
+            pwm_type: pwm_type as u8,
+            index,
+        },
+        file.as_raw_fd(),
+    )?;
+    Ok(response.duty)
+}
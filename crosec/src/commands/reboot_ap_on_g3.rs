@@ -0,0 +1,21 @@
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+/// Makes the EC boot the AP again after the AP shuts down into G3.
+/// If `delay` is specified, the EC waits that long after the shutdown, which needs version 1 of the command.
+/// The delay is rounded down to whole seconds.
+pub fn reboot_ap_on_g3<File: AsRawFd>(file: &mut File, delay: Option<Duration>) -> EcCmdResult<()> {
+    match delay {
+        Some(delay) => ec_command_bytemuck(
+            CrosEcCmd::RebootApOnG3,
+            1,
+            &(delay.as_secs().min(u32::MAX as u64) as u32),
+            file.as_raw_fd(),
+        ),
+        None => ec_command_bytemuck(CrosEcCmd::RebootApOnG3, 0, &(), file.as_raw_fd()),
+    }
+}
@@ -0,0 +1,71 @@
+use std::os::fd::AsRawFd;
+
+use bytemuck::{Pod, Zeroable};
+use strum_macros::{EnumIter, IntoStaticStr};
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum RebootEcCommand {
+    /// Cancel a pending reboot
+    Cancel = 0,
+    /// Jump to the RO image without rebooting
+    JumpRo = 1,
+    /// Jump to the RW image without rebooting
+    JumpRw = 2,
+    Cold = 4,
+    /// Don't allow jumping between images until the next reboot
+    DisableJump = 5,
+    Hibernate = 6,
+    /// Hibernate, and clear the AP_OFF flag so the AP boots when the EC wakes up
+    HibernateClearApOff = 7,
+    /// Cold reboot without booting the AP
+    ColdApOff = 8,
+    /// Only apply the flags, such as clearing the AP idle state
+    NoOp = 9,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum RebootEcFlag {
+    /// Only reboot once the AP shuts down
+    OnApShutdown = 1 << 1,
+    /// Switch the RW slot before rebooting
+    SwitchRwSlot = 1 << 2,
+    /// Clear the AP idle state before rebooting
+    ClearApIdle = 1 << 3,
+}
+
+flag_set!(RebootEcFlags, RebootEcFlag, u8);
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsRebootEc {
+    cmd: u8,
+    flags: u8,
+}
+
+/// Reboots the EC or jumps to another image.
+///
+/// The EC responds before rebooting, so it won't respond to commands for a short time after this returns.
+/// [`crate::reboot::reboot_ec_and_wait`] waits until the EC is ready again.
+pub fn reboot_ec<File: AsRawFd>(
+    file: &mut File,
+    command: RebootEcCommand,
+    flags: RebootEcFlags,
+) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::RebootEc,
+        0,
+        &EcParamsRebootEc {
+            cmd: command as u8,
+            flags: flags.0,
+        },
+        file.as_raw_fd(),
+    )
+}
@@ -0,0 +1,31 @@
+use std::os::fd::AsRawFd;
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum RwsigAction {
+    /// Stay in RO instead of jumping to RW once the signature is verified
+    Abort,
+    /// Verify the signature and jump to RW right away instead of waiting for the timeout
+    Continue,
+}
+
+/// Checks the signature of the RW image. Returns `true` if it's valid.
+pub fn rwsig_check_status<File: AsRawFd>(file: &mut File) -> EcCmdResult<bool> {
+    let status: u32 = ec_command_bytemuck(CrosEcCmd::RwsigCheckStatus, 0, &(), file.as_raw_fd())?;
+    Ok(status != 0)
+}
+
+/// Controls what RO does with RW while it is waiting to jump to it
+pub fn rwsig_action<File: AsRawFd>(file: &mut File, action: RwsigAction) -> EcCmdResult<()> {
+    ec_command_bytemuck(
+        CrosEcCmd::RwsigAction,
+        0,
+        &(action as u32),
+        file.as_raw_fd(),
+    )
+}
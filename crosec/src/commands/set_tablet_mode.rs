@@ -0,0 +1,19 @@
+use std::os::fd::AsRawFd;
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TabletMode {
+    /// Let the EC decide based on the lid angle and switches
+    Default,
+    ForceTablet,
+    ForceClamshell,
+}
+
+pub fn set_tablet_mode<File: AsRawFd>(file: &mut File, mode: TabletMode) -> EcCmdResult<()> {
+    ec_command_bytemuck(CrosEcCmd::SetTabletMode, 0, &(mode as u8), file.as_raw_fd())
+}
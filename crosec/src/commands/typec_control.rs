@@ -0,0 +1,233 @@
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+
+use bytemuck::{bytes_of, pod_read_unaligned, Pod, Zeroable};
+use num_traits::FromPrimitive;
+
+use crate::{
+    ec_command::ec_command_with_dynamic_output_size, EcCmdResult, EcError, EcResponseStatus,
+};
+
+use super::{usb_pd::TypecPartnerType, CrosEcCmd};
+
+/// Use this as the mux index to set every mux in the chain
+pub const TYPEC_USB_MUX_SET_ALL_CHIPS: u8 = 0xff;
+/// The max number of 32-bit objects in a VDM, including the VDM header
+pub const VDO_MAX_SIZE: usize = 7;
+
+/// The union of sub-command params is padded to this size
+const TYPEC_CONTROL_PARAMS_SIZE: usize = 128;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum TypecControlCommand {
+    ExitModes,
+    ClearEvents,
+    EnterMode,
+    TbtUfpReply,
+    UsbMuxSet,
+    BistShareMode,
+    SendVdmReq,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TypecMode {
+    Dp,
+    Tbt,
+    Usb4,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TypecTbtUfpReply {
+    Nak,
+    Ack,
+}
+
+/// The structured VDM commands
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+pub enum VdmCommand {
+    DiscoverIdentity = 1,
+    DiscoverSvid = 2,
+    DiscoverModes = 3,
+    EnterMode = 4,
+    ExitMode = 5,
+    Attention = 6,
+}
+
+/// Builds the header of a structured VDM request using structured VDM version 2.0.
+/// `object_position` is the 1-based index of the mode for mode entry and exit, and 0 otherwise.
+pub fn structured_vdm_header(svid: u16, object_position: u8, command: VdmCommand) -> u32 {
+    const VDM_STRUCTURED: u32 = 1 << 15;
+    const VDM_VERSION_2_0: u32 = 1 << 13;
+    (svid as u32) << 16
+        | VDM_STRUCTURED
+        | VDM_VERSION_2_0
+        | ((object_position as u32) & 0b111) << 8
+        | command as u32
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct TypecVdmReq {
+    vdm_data: [u32; VDO_MAX_SIZE],
+    vdm_data_objects: u8,
+    partner_type: u8,
+}
+
+fn typec_control<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    command: TypecControlCommand,
+    params: &[u8],
+) -> EcCmdResult<()> {
+    let mut input = vec![port, command as u8, 0, 0];
+    input.extend_from_slice(params);
+    input.resize(4 + TYPEC_CONTROL_PARAMS_SIZE, 0);
+    ec_command_with_dynamic_output_size(CrosEcCmd::TypecControl, 0, &input, 0, file.as_raw_fd())?;
+    Ok(())
+}
+
+/// Exits all of the alternate modes on the port
+pub fn typec_exit_modes<File: AsRawFd>(file: &mut File, port: u8) -> EcCmdResult<()> {
+    typec_control(file, port, TypecControlCommand::ExitModes, &[])
+}
+
+/// Clears the events in `mask` (see `PD_STATUS_EVENT_*`) from the Type-C status
+pub fn typec_clear_events<File: AsRawFd>(file: &mut File, port: u8, mask: u32) -> EcCmdResult<()> {
+    typec_control(
+        file,
+        port,
+        TypecControlCommand::ClearEvents,
+        bytes_of(&mask),
+    )
+}
+
+/// Enters a mode. This is needed when the EC leaves mode entry to the AP.
+pub fn typec_enter_mode<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    mode: TypecMode,
+) -> EcCmdResult<()> {
+    typec_control(file, port, TypecControlCommand::EnterMode, &[mode as u8])
+}
+
+/// Replies to a Thunderbolt mode entry request while we are the UFP
+pub fn typec_tbt_ufp_reply<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    reply: TypecTbtUfpReply,
+) -> EcCmdResult<()> {
+    typec_control(file, port, TypecControlCommand::TbtUfpReply, &[reply as u8])
+}
+
+/// Sets a mux in the chain to a `USB_PD_MUX_*` state. Use [`TYPEC_USB_MUX_SET_ALL_CHIPS`] to set all of them.
+pub fn typec_usb_mux_set<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    mux_index: u8,
+    mux_flags: u8,
+) -> EcCmdResult<()> {
+    typec_control(
+        file,
+        port,
+        TypecControlCommand::UsbMuxSet,
+        &[mux_index, mux_flags],
+    )
+}
+
+/// Enables or disables BIST shared test mode
+pub fn typec_bist_share_mode<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    enable: bool,
+) -> EcCmdResult<()> {
+    typec_control(
+        file,
+        port,
+        TypecControlCommand::BistShareMode,
+        &[enable as u8],
+    )
+}
+
+/// Sends a VDM, where the first object is the VDM header (see [`structured_vdm_header`]).
+/// The response can be gotten with [`typec_vdm_response`] after the `PD_STATUS_EVENT_VDM_REQ_REPLY` event.
+/// Fails with [`EcResponseStatus::InvalidParam`] without sending anything unless the VDM has 1 to [`VDO_MAX_SIZE`] objects.
+pub fn typec_send_vdm_req<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    partner_type: TypecPartnerType,
+    vdm: &[u32],
+) -> EcCmdResult<()> {
+    if vdm.is_empty() || vdm.len() > VDO_MAX_SIZE {
+        return Err(EcError::Response(EcResponseStatus::InvalidParam));
+    }
+    let mut req = TypecVdmReq {
+        vdm_data: [0; VDO_MAX_SIZE],
+        vdm_data_objects: vdm.len() as u8,
+        partner_type: partner_type as u8,
+    };
+    let mut vdm_data = req.vdm_data;
+    vdm_data[..vdm.len()].copy_from_slice(vdm);
+    req.vdm_data = vdm_data;
+    typec_control(file, port, TypecControlCommand::SendVdmReq, bytes_of(&req))
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseTypecVdmResponse {
+    vdm_data_objects: u8,
+    partner_type: u8,
+    vdm_response_err: u16,
+    vdm_response: [u32; VDO_MAX_SIZE],
+    vdm_attention_objects: u8,
+    vdm_attention_left: u8,
+    reserved1: u16,
+    vdm_attention: [u32; 2],
+}
+
+#[derive(Debug, Clone)]
+pub struct TypecVdmResponse {
+    /// The raw partner type, see [`TypecPartnerType`]
+    pub partner_type: u8,
+    /// The result of the VDM request. Unknown codes are kept as the raw number in the `Err`.
+    pub result: Result<EcResponseStatus, u16>,
+    /// The response, starting with the VDM header
+    pub response: Vec<u32>,
+    /// The oldest queued Attention message, starting with the VDM header
+    pub attention: Vec<u32>,
+    /// The number of Attention messages which are still queued
+    pub attention_left: u8,
+}
+
+/// Gets the response to the last VDM sent with [`typec_send_vdm_req`], and the oldest Attention message
+pub fn typec_vdm_response<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+) -> EcCmdResult<TypecVdmResponse> {
+    let response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::TypecVdmResponse,
+        0,
+        &[port],
+        size_of::<EcResponseTypecVdmResponse>(),
+        file.as_raw_fd(),
+    )?;
+    let response: EcResponseTypecVdmResponse = pod_read_unaligned(&response);
+    let vdm_response = response.vdm_response;
+    let vdm_attention = response.vdm_attention;
+    let result =
+        EcResponseStatus::from_u16(response.vdm_response_err).ok_or(response.vdm_response_err);
+    Ok(TypecVdmResponse {
+        partner_type: response.partner_type,
+        result,
+        response: vdm_response[..(response.vdm_data_objects as usize).min(VDO_MAX_SIZE)].to_vec(),
+        attention: vdm_attention
+            [..(response.vdm_attention_objects as usize).min(vdm_attention.len())]
+            .to_vec(),
+        attention_left: response.vdm_attention_left,
+    })
+}
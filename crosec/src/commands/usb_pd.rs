@@ -0,0 +1,664 @@
+use std::fmt::{Display, Formatter};
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+
+use bytemuck::{pod_read_unaligned, Pod, Zeroable};
+use strum_macros::{EnumIter, FromRepr, IntoStaticStr};
+use uom::si::{
+    electric_current::milliampere,
+    electric_potential::millivolt,
+    f32::{ElectricCurrent, ElectricPotential, Power},
+    power::{microwatt, milliwatt},
+};
+
+use crate::{
+    ec_command::{ec_command_bytemuck, ec_command_with_dynamic_output_size},
+    fmap::fmap_string,
+    EcCmdResult,
+};
+
+use super::{get_protocol_info::EcResponseGetProtocolInfo, CrosEcCmd};
+
+/// Communication is enabled
+pub const PD_CTRL_RESP_ENABLED_COMMS: u8 = 1 << 0;
+/// A device is connected
+pub const PD_CTRL_RESP_ENABLED_CONNECTED: u8 = 1 << 1;
+/// The partner is PD capable
+pub const PD_CTRL_RESP_ENABLED_PD_CAPABLE: u8 = 1 << 2;
+
+/// 0 is sink and 1 is source
+pub const PD_CTRL_RESP_ROLE_POWER: u8 = 1 << 0;
+/// 0 is UFP and 1 is DFP
+pub const PD_CTRL_RESP_ROLE_DATA: u8 = 1 << 1;
+/// We are sourcing VCONN
+pub const PD_CTRL_RESP_ROLE_VCONN: u8 = 1 << 2;
+/// The partner is dual role power
+pub const PD_CTRL_RESP_ROLE_DR_POWER: u8 = 1 << 3;
+/// The partner is dual role data
+pub const PD_CTRL_RESP_ROLE_DR_DATA: u8 = 1 << 4;
+/// The partner is capable of USB communication
+pub const PD_CTRL_RESP_ROLE_USB_COMM: u8 = 1 << 5;
+/// The partner has unconstrained power
+pub const PD_CTRL_RESP_ROLE_UNCONSTRAINED: u8 = 1 << 6;
+
+pub const USB_PD_CTRL_ACTIVE_CABLE: u8 = 1 << 0;
+pub const USB_PD_CTRL_OPTICAL_CABLE: u8 = 1 << 1;
+/// 3rd gen TBT device (or AMA) / 2nd gen TBT adapter
+pub const USB_PD_CTRL_TBT_LEGACY_ADAPTER: u8 = 1 << 2;
+pub const USB_PD_CTRL_ACTIVE_LINK_UNIDIR: u8 = 1 << 3;
+pub const USB_PD_CTRL_RETIMER_CABLE: u8 = 1 << 4;
+
+pub const USB_PD_MUX_USB_ENABLED: u8 = 1 << 0;
+pub const USB_PD_MUX_DP_ENABLED: u8 = 1 << 1;
+pub const USB_PD_MUX_POLARITY_INVERTED: u8 = 1 << 2;
+pub const USB_PD_MUX_HPD_IRQ: u8 = 1 << 3;
+pub const USB_PD_MUX_HPD_LVL: u8 = 1 << 4;
+pub const USB_PD_MUX_SAFE_MODE: u8 = 1 << 5;
+pub const USB_PD_MUX_TBT_COMPAT_ENABLED: u8 = 1 << 6;
+pub const USB_PD_MUX_USB4_ENABLED: u8 = 1 << 7;
+
+pub const PD_STATUS_EVENT_SOP_DISC_DONE: u32 = 1 << 0;
+pub const PD_STATUS_EVENT_SOP_PRIME_DISC_DONE: u32 = 1 << 1;
+pub const PD_STATUS_EVENT_HARD_RESET: u32 = 1 << 2;
+pub const PD_STATUS_EVENT_DISCONNECTED: u32 = 1 << 3;
+pub const PD_STATUS_EVENT_MUX_0_SET_DONE: u32 = 1 << 4;
+pub const PD_STATUS_EVENT_MUX_1_SET_DONE: u32 = 1 << 5;
+pub const PD_STATUS_EVENT_VDM_REQ_REPLY: u32 = 1 << 6;
+pub const PD_STATUS_EVENT_VDM_REQ_FAILED: u32 = 1 << 7;
+pub const PD_STATUS_EVENT_VDM_ATTENTION: u32 = 1 << 8;
+
+/// Use this as the port to get the power info of the port which is charging
+pub const PD_POWER_CHARGING_PORT: u8 = 0xff;
+
+/// The max number of PDOs in a capabilities message
+pub const PDO_MAX_OBJECTS: usize = 7;
+/// The max number of VDOs after a VDM header
+pub const VDO_MAX_OBJECTS: usize = 6;
+
+pub const PDO_FIXED_DUAL_ROLE: u32 = 1 << 29;
+/// For sources, USB suspend is supported. For sinks, the sink needs more than vSafe5V.
+pub const PDO_FIXED_SUSPEND: u32 = 1 << 28;
+pub const PDO_FIXED_UNCONSTRAINED: u32 = 1 << 27;
+pub const PDO_FIXED_COMM_CAP: u32 = 1 << 26;
+pub const PDO_FIXED_DATA_SWAP: u32 = 1 << 25;
+
+pub const USB_SID_PD: u16 = 0xff00;
+pub const USB_SID_DISPLAYPORT: u16 = 0xff01;
+pub const USB_VID_INTEL: u16 = 0x8087;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum UsbPdControlRole {
+    #[default]
+    NoChange,
+    ToggleOn,
+    ToggleOff,
+    ForceSink,
+    ForceSource,
+    Freeze,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum UsbPdControlMux {
+    #[default]
+    NoChange,
+    None,
+    Usb,
+    Dp,
+    Dock,
+    Auto,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum UsbPdControlSwap {
+    #[default]
+    None,
+    Data,
+    Power,
+    Vconn,
+}
+
+/// Possible port partner connections based on the CC line states
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdCcState {
+    /// No port partner attached
+    None,
+    /// No UFP accessory connected
+    UfpNone,
+    UfpAudioAccessory,
+    UfpDebugAccessory,
+    /// Plain UFP attached
+    UfpAttached,
+    /// Plain DFP attached
+    DfpAttached,
+    DfpDebugAccessory,
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbPowerRole {
+    Disconnected,
+    Source,
+    Sink,
+    SinkNotCharging,
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbChargerType {
+    None,
+    Pd,
+    C,
+    Proprietary,
+    Bc12Dcp,
+    Bc12Cdp,
+    Bc12Sdp,
+    Other,
+    Vbus,
+    Unknown,
+    Dedicated,
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdPowerRole {
+    Sink,
+    Source,
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdDataRole {
+    Ufp,
+    Dfp,
+    Disconnected,
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdVconnRole {
+    Off,
+    Source,
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpcCcPolarity {
+    Cc1,
+    Cc2,
+    /// CC1 with a debug accessory
+    Cc1Dts,
+    /// CC2 with a debug accessory
+    Cc2Dts,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TypecPartnerType {
+    /// The port partner
+    Sop,
+    /// The cable plug closest to us
+    SopPrime,
+    /// The cable plug at the other end
+    SopPrimePrime,
+}
+
+/// Converts the DP pin mode bitmask (`MODE_DP_PIN_*`) to the pin assignment letter
+pub fn dp_pin_assignment(dp_pin: u8) -> Option<char> {
+    if dp_pin.count_ones() == 1 && dp_pin.trailing_zeros() < 6 {
+        Some((b'A' + dp_pin.trailing_zeros() as u8) as char)
+    } else {
+        None
+    }
+}
+
+/// The name of a standard or vendor ID which is used for alternate modes
+pub fn svid_name(svid: u16) -> Option<&'static str> {
+    match svid {
+        USB_SID_PD => Some("USB PD"),
+        USB_SID_DISPLAYPORT => Some("DisplayPort"),
+        USB_VID_INTEL => Some("Intel (Thunderbolt)"),
+        _ => None,
+    }
+}
+
+/// A power data object, which describes one of the power levels which a source offers or a sink wants
+#[derive(Debug, Clone, Copy)]
+pub enum Pdo {
+    Fixed {
+        voltage: ElectricPotential,
+        max_current: ElectricCurrent,
+        /// See `PDO_FIXED_*`
+        flags: u32,
+    },
+    Battery {
+        min_voltage: ElectricPotential,
+        max_voltage: ElectricPotential,
+        max_power: Power,
+    },
+    Variable {
+        min_voltage: ElectricPotential,
+        max_voltage: ElectricPotential,
+        max_current: ElectricCurrent,
+    },
+    /// Programmable power supply
+    Pps {
+        min_voltage: ElectricPotential,
+        max_voltage: ElectricPotential,
+        max_current: ElectricCurrent,
+    },
+    /// An augmented PDO type which isn't PPS
+    Unknown(u32),
+}
+
+fn bits(pdo: u32, low: u32, count: u32) -> f32 {
+    ((pdo >> low) & ((1 << count) - 1)) as f32
+}
+
+impl From<u32> for Pdo {
+    fn from(pdo: u32) -> Self {
+        let mv = ElectricPotential::new::<millivolt>;
+        let ma = ElectricCurrent::new::<milliampere>;
+        match pdo >> 30 {
+            0 => Self::Fixed {
+                voltage: mv(bits(pdo, 10, 10) * 50.0),
+                max_current: ma(bits(pdo, 0, 10) * 10.0),
+                flags: pdo
+                    & (PDO_FIXED_DUAL_ROLE
+                        | PDO_FIXED_SUSPEND
+                        | PDO_FIXED_UNCONSTRAINED
+                        | PDO_FIXED_COMM_CAP
+                        | PDO_FIXED_DATA_SWAP),
+            },
+            1 => Self::Battery {
+                min_voltage: mv(bits(pdo, 10, 10) * 50.0),
+                max_voltage: mv(bits(pdo, 20, 10) * 50.0),
+                max_power: Power::new::<milliwatt>(bits(pdo, 0, 10) * 250.0),
+            },
+            2 => Self::Variable {
+                min_voltage: mv(bits(pdo, 10, 10) * 50.0),
+                max_voltage: mv(bits(pdo, 20, 10) * 50.0),
+                max_current: ma(bits(pdo, 0, 10) * 10.0),
+            },
+            _ if (pdo >> 28) & 0b11 == 0 => Self::Pps {
+                min_voltage: mv(bits(pdo, 8, 8) * 100.0),
+                max_voltage: mv(bits(pdo, 17, 8) * 100.0),
+                max_current: ma(bits(pdo, 0, 7) * 50.0),
+            },
+            _ => Self::Unknown(pdo),
+        }
+    }
+}
+
+impl Display for Pdo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed {
+                voltage,
+                max_current,
+                flags,
+            } => {
+                write!(
+                    f,
+                    "Fixed {}mV {}mA",
+                    voltage.get::<millivolt>(),
+                    max_current.get::<milliampere>()
+                )?;
+                for (flag, name) in [
+                    (PDO_FIXED_DUAL_ROLE, "DRP"),
+                    (PDO_FIXED_SUSPEND, "SUSPEND"),
+                    (PDO_FIXED_UNCONSTRAINED, "UNCONSTRAINED"),
+                    (PDO_FIXED_COMM_CAP, "COMM_CAP"),
+                    (PDO_FIXED_DATA_SWAP, "DRD"),
+                ] {
+                    if flags & flag != 0 {
+                        write!(f, " {name}")?;
+                    }
+                }
+                Ok(())
+            }
+            Self::Battery {
+                min_voltage,
+                max_voltage,
+                max_power,
+            } => write!(
+                f,
+                "Battery {}-{}mV {}mW",
+                min_voltage.get::<millivolt>(),
+                max_voltage.get::<millivolt>(),
+                max_power.get::<milliwatt>()
+            ),
+            Self::Variable {
+                min_voltage,
+                max_voltage,
+                max_current,
+            } => write!(
+                f,
+                "Variable {}-{}mV {}mA",
+                min_voltage.get::<millivolt>(),
+                max_voltage.get::<millivolt>(),
+                max_current.get::<milliampere>()
+            ),
+            Self::Pps {
+                min_voltage,
+                max_voltage,
+                max_current,
+            } => write!(
+                f,
+                "PPS {}-{}mV {}mA",
+                min_voltage.get::<millivolt>(),
+                max_voltage.get::<millivolt>(),
+                max_current.get::<milliampere>()
+            ),
+            Self::Unknown(pdo) => write!(f, "Unknown {pdo:#010x}"),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsUsbPdControl {
+    port: u8,
+    role: u8,
+    mux: u8,
+    swap: u8,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseUsbPdControlV2 {
+    enabled: u8,
+    role: u8,
+    polarity: u8,
+    state: [u8; 32],
+    cc_state: u8,
+    dp_mode: u8,
+    reserved: u8,
+    control_flags: u8,
+    cable_speed: u8,
+    cable_gen: u8,
+}
+
+#[derive(Debug, Clone)]
+pub enum UsbPdState {
+    /// Version 0 only has the number of the state
+    Number(u8),
+    Name(String),
+}
+
+/// Only in version 2
+#[derive(Debug, Clone, Copy)]
+pub struct UsbPdCableInfo {
+    pub cc_state: Result<PdCcState, u8>,
+    /// See [`dp_pin_assignment`]
+    pub dp_mode: u8,
+    /// See `USB_PD_CTRL_*`
+    pub control_flags: u8,
+    /// The TBT cable speed
+    pub cable_speed: u8,
+    /// The TBT cable generation
+    pub cable_gen: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct UsbPdControlStatus {
+    /// See `PD_CTRL_RESP_ENABLED_*`
+    pub enabled: u8,
+    /// See `PD_CTRL_RESP_ROLE_*`
+    pub role: u8,
+    pub polarity: u8,
+    pub state: UsbPdState,
+    pub cable_info: Option<UsbPdCableInfo>,
+}
+
+/// Gets the number of USB-PD ports
+pub fn usb_pd_ports<File: AsRawFd>(file: &mut File) -> EcCmdResult<u8> {
+    ec_command_bytemuck(CrosEcCmd::UsbPdPorts, 0, &(), file.as_raw_fd())
+}
+
+/// Optionally changes the role, mux, or swaps something, and gets the status of the port.
+/// `version` can be 0, 1, or 2. Later versions have more info.
+pub fn usb_pd_control<File: AsRawFd>(
+    file: &mut File,
+    version: u8,
+    port: u8,
+    role: UsbPdControlRole,
+    mux: UsbPdControlMux,
+    swap: UsbPdControlSwap,
+) -> EcCmdResult<UsbPdControlStatus> {
+    let output_size = match version {
+        0 => 4,
+        1 => 3 + 32,
+        _ => size_of::<EcResponseUsbPdControlV2>(),
+    };
+    let mut response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::UsbPdControl,
+        version,
+        bytemuck::bytes_of(&EcParamsUsbPdControl {
+            port,
+            role: role as u8,
+            mux: mux as u8,
+            swap: swap as u8,
+        }),
+        output_size,
+        file.as_raw_fd(),
+    )?;
+    let state = match version {
+        0 => UsbPdState::Number(response[3]),
+        _ => UsbPdState::Name(fmap_string(&response[3..3 + 32])),
+    };
+    response.resize(size_of::<EcResponseUsbPdControlV2>(), 0);
+    let response: EcResponseUsbPdControlV2 = pod_read_unaligned(&response);
+    Ok(UsbPdControlStatus {
+        enabled: response.enabled,
+        role: response.role,
+        polarity: response.polarity,
+        state,
+        cable_info: (version >= 2).then_some(UsbPdCableInfo {
+            cc_state: PdCcState::from_repr(response.cc_state).ok_or(response.cc_state),
+            dp_mode: response.dp_mode,
+            control_flags: response.control_flags,
+            cable_speed: response.cable_speed,
+            cable_gen: response.cable_gen,
+        }),
+    })
+}
+
+#[repr(C, align(4))]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseUsbPdPowerInfo {
+    role: u8,
+    charger_type: u8,
+    dualrole: u8,
+    reserved1: u8,
+    voltage_max: u16,
+    voltage_now: u16,
+    current_max: u16,
+    current_lim: u16,
+    max_power: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UsbPdPowerInfo {
+    pub role: Result<UsbPowerRole, u8>,
+    pub charger_type: Result<UsbChargerType, u8>,
+    /// The port can be a source or a sink
+    pub dualrole: bool,
+    pub voltage_max: ElectricPotential,
+    pub voltage_now: ElectricPotential,
+    pub current_max: ElectricCurrent,
+    pub current_lim: ElectricCurrent,
+    pub max_power: Power,
+}
+
+/// Use [`PD_POWER_CHARGING_PORT`] as the port to get the port which is charging
+pub fn usb_pd_power_info<File: AsRawFd>(file: &mut File, port: u8) -> EcCmdResult<UsbPdPowerInfo> {
+    let info: EcResponseUsbPdPowerInfo =
+        ec_command_bytemuck(CrosEcCmd::UsbPdPowerInfo, 0, &port, file.as_raw_fd())?;
+    Ok(UsbPdPowerInfo {
+        role: UsbPowerRole::from_repr(info.role).ok_or(info.role),
+        charger_type: UsbChargerType::from_repr(info.charger_type).ok_or(info.charger_type),
+        dualrole: info.dualrole != 0,
+        voltage_max: ElectricPotential::new::<millivolt>(info.voltage_max as f32),
+        voltage_now: ElectricPotential::new::<millivolt>(info.voltage_now as f32),
+        current_max: ElectricCurrent::new::<milliampere>(info.current_max as f32),
+        current_lim: ElectricCurrent::new::<milliampere>(info.current_lim as f32),
+        max_power: Power::new::<microwatt>(info.max_power as f32),
+    })
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy, Debug)]
+pub struct EcResponseTypecStatus {
+    /// PD communication is enabled
+    pub pd_enabled: u8,
+    pub dev_connected: u8,
+    /// The device is SOP PD capable
+    pub sop_connected: u8,
+    pub source_cap_count: u8,
+    pub power_role: u8,
+    pub data_role: u8,
+    pub vconn_role: u8,
+    pub sink_cap_count: u8,
+    pub polarity: u8,
+    pub cc_state: u8,
+    /// See [`dp_pin_assignment`]
+    pub dp_pin: u8,
+    /// See `USB_PD_MUX_*`
+    pub mux_state: u8,
+    /// The name of the Type-C state
+    pub tc_state: [u8; 32],
+    /// See `PD_STATUS_EVENT_*`
+    pub events: u32,
+    /// BCD, where the upper 8 bits are the major version and the lower 8 bits are the minor version
+    pub sop_revision: u16,
+    pub sop_prime_revision: u16,
+    pub source_cap_pdos: [u32; PDO_MAX_OBJECTS],
+    pub sink_cap_pdos: [u32; PDO_MAX_OBJECTS],
+}
+
+impl EcResponseTypecStatus {
+    pub fn tc_state(&self) -> String {
+        fmap_string(&self.tc_state)
+    }
+
+    pub fn power_role(&self) -> Result<PdPowerRole, u8> {
+        PdPowerRole::from_repr(self.power_role).ok_or(self.power_role)
+    }
+
+    pub fn data_role(&self) -> Result<PdDataRole, u8> {
+        PdDataRole::from_repr(self.data_role).ok_or(self.data_role)
+    }
+
+    pub fn vconn_role(&self) -> Result<PdVconnRole, u8> {
+        PdVconnRole::from_repr(self.vconn_role).ok_or(self.vconn_role)
+    }
+
+    pub fn polarity(&self) -> Result<TcpcCcPolarity, u8> {
+        TcpcCcPolarity::from_repr(self.polarity).ok_or(self.polarity)
+    }
+
+    pub fn cc_state(&self) -> Result<PdCcState, u8> {
+        PdCcState::from_repr(self.cc_state).ok_or(self.cc_state)
+    }
+
+    pub fn source_caps(&self) -> Vec<Pdo> {
+        let pdos = self.source_cap_pdos;
+        let count = (self.source_cap_count as usize).min(PDO_MAX_OBJECTS);
+        pdos[..count].iter().copied().map(Pdo::from).collect()
+    }
+
+    pub fn sink_caps(&self) -> Vec<Pdo> {
+        let pdos = self.sink_cap_pdos;
+        let count = (self.sink_cap_count as usize).min(PDO_MAX_OBJECTS);
+        pdos[..count].iter().copied().map(Pdo::from).collect()
+    }
+}
+
+pub fn typec_status<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+) -> EcCmdResult<EcResponseTypecStatus> {
+    let response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::TypecStatus,
+        0,
+        &[port],
+        size_of::<EcResponseTypecStatus>(),
+        file.as_raw_fd(),
+    )?;
+    Ok(pod_read_unaligned(&response))
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseTypecDiscovery {
+    identity_count: u8,
+    svid_count: u8,
+    reserved: u16,
+    discovery_vdo: [u32; VDO_MAX_OBJECTS],
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct SvidModeInfo {
+    svid: u16,
+    mode_count: u16,
+    mode_vdo: [u32; VDO_MAX_OBJECTS],
+}
+
+#[derive(Debug, Clone)]
+pub struct TypecSvid {
+    /// See [`svid_name`]
+    pub svid: u16,
+    pub mode_vdos: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypecDiscovery {
+    /// The VDOs from the Discover Identity response, starting with the ID header
+    pub identity_vdos: Vec<u32>,
+    pub svids: Vec<TypecSvid>,
+}
+
+/// Gets what was discovered about the port partner or cable
+pub fn typec_discovery<File: AsRawFd>(
+    file: &mut File,
+    port: u8,
+    partner_type: TypecPartnerType,
+    protocol_info: &EcResponseGetProtocolInfo,
+) -> EcCmdResult<TypecDiscovery> {
+    let mut response = ec_command_with_dynamic_output_size(
+        CrosEcCmd::TypecDiscovery,
+        0,
+        &[port, partner_type as u8],
+        protocol_info.max_ec_output_size(),
+        file.as_raw_fd(),
+    )?;
+    let header_size = size_of::<EcResponseTypecDiscovery>();
+    let header: EcResponseTypecDiscovery = pod_read_unaligned(&response[..header_size]);
+    let discovery_vdo = header.discovery_vdo;
+    let svid_count = header.svid_count as usize;
+    response.resize(header_size + svid_count * size_of::<SvidModeInfo>(), 0);
+    Ok(TypecDiscovery {
+        identity_vdos: discovery_vdo[..(header.identity_count as usize).min(VDO_MAX_OBJECTS)]
+            .to_vec(),
+        svids: response[header_size..]
+            .chunks_exact(size_of::<SvidModeInfo>())
+            .map(pod_read_unaligned::<SvidModeInfo>)
+            .map(|info| {
+                let mode_vdo = info.mode_vdo;
+                TypecSvid {
+                    svid: info.svid,
+                    mode_vdos: mode_vdo[..(info.mode_count as usize).min(VDO_MAX_OBJECTS)].to_vec(),
+                }
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,171 @@
+use std::os::fd::AsRawFd;
+use std::thread::sleep;
+use std::time::Duration;
+
+use bytemuck::{Pod, Zeroable};
+use sha2::{Digest, Sha256};
+use strum_macros::FromRepr;
+
+use crate::{ec_command::ec_command_bytemuck, EcCmdResult};
+
+use super::CrosEcCmd;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum VbootHashCommand {
+    Get,
+    Abort,
+    Start,
+    Recalc,
+}
+
+const EC_VBOOT_HASH_TYPE_SHA256: u8 = 0;
+
+/// Use this as the offset to hash the RO image. The EC picks the size, which is the part of the image that is used.
+pub const EC_VBOOT_HASH_OFFSET_RO: u32 = 0xfffffffe;
+/// Use this as the offset to hash the image which is running. The EC picks the size.
+pub const EC_VBOOT_HASH_OFFSET_ACTIVE: u32 = 0xfffffffd;
+/// Use this as the offset to hash the image which isn't running. The EC picks the size.
+pub const EC_VBOOT_HASH_OFFSET_UPDATE: u32 = 0xfffffffc;
+
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VbootHashStatus {
+    /// No hash has been calculated, or it was aborted
+    None,
+    Done,
+    /// The hash is being calculated
+    Busy,
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcParamsVbootHash {
+    cmd: u8,
+    hash_type: u8,
+    nonce_size: u8,
+    reserved0: u8,
+    offset: u32,
+    size: u32,
+    nonce_data: [u8; 64],
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct EcResponseVbootHash {
+    status: u8,
+    hash_type: u8,
+    digest_size: u8,
+    reserved0: u8,
+    offset: u32,
+    size: u32,
+    hash_digest: [u8; 64],
+}
+
+#[derive(Debug, Clone)]
+pub struct VbootHash {
+    pub status: Result<VbootHashStatus, u8>,
+    /// The offset in flash which was hashed
+    pub offset: u32,
+    /// The number of bytes which were hashed
+    pub size: u32,
+    /// The SHA-256 digest, or empty if the hash isn't done
+    pub digest: Vec<u8>,
+}
+
+/// Calculates the SHA-256 of data locally, which is the hash type the EC uses
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+impl VbootHash {
+    /// Checks that the hash is done and matches the SHA-256 of `data`, which should start at the offset that was hashed.
+    /// Only the number of bytes that the EC hashed are used, so `data` can be a whole image even if the EC picked the size.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        match data.get(..self.size as usize) {
+            Some(data) => self.status == Ok(VbootHashStatus::Done) && self.digest == sha256(data),
+            None => false,
+        }
+    }
+}
+
+fn vboot_hash_command<File: AsRawFd>(
+    file: &mut File,
+    command: VbootHashCommand,
+    offset: u32,
+    size: u32,
+) -> EcCmdResult<VbootHash> {
+    let response: EcResponseVbootHash = ec_command_bytemuck(
+        CrosEcCmd::VbootHash,
+        0,
+        &EcParamsVbootHash {
+            cmd: command as u8,
+            hash_type: EC_VBOOT_HASH_TYPE_SHA256,
+            nonce_size: 0,
+            reserved0: 0,
+            offset,
+            size,
+            nonce_data: [0; 64],
+        },
+        file.as_raw_fd(),
+    )?;
+    let status = VbootHashStatus::from_repr(response.status).ok_or(response.status);
+    let digest_size = match status {
+        Ok(VbootHashStatus::Done) => {
+            (response.digest_size as usize).min(response.hash_digest.len())
+        }
+        _ => 0,
+    };
+    Ok(VbootHash {
+        status,
+        offset: response.offset,
+        size: response.size,
+        digest: response.hash_digest[..digest_size].to_vec(),
+    })
+}
+
+/// Gets the status of the last hash, and the digest if it's done
+pub fn vboot_hash_get<File: AsRawFd>(file: &mut File) -> EcCmdResult<VbootHash> {
+    vboot_hash_command(file, VbootHashCommand::Get, 0, 0)
+}
+
+/// Stops calculating the hash
+pub fn vboot_hash_abort<File: AsRawFd>(file: &mut File) -> EcCmdResult<VbootHash> {
+    vboot_hash_command(file, VbootHashCommand::Abort, 0, 0)
+}
+
+/// Starts hashing `size` bytes of flash at `offset` in the background.
+/// The offset can also be one of the `EC_VBOOT_HASH_OFFSET_*` values.
+pub fn vboot_hash_start<File: AsRawFd>(
+    file: &mut File,
+    offset: u32,
+    size: u32,
+) -> EcCmdResult<VbootHash> {
+    vboot_hash_command(file, VbootHashCommand::Start, offset, size)
+}
+
+/// Hashes flash, and doesn't respond until the hash is done.
+/// This can take longer than the kernel's timeout for big sizes, which [`vboot_hash`] avoids.
+pub fn vboot_hash_recalc<File: AsRawFd>(
+    file: &mut File,
+    offset: u32,
+    size: u32,
+) -> EcCmdResult<VbootHash> {
+    vboot_hash_command(file, VbootHashCommand::Recalc, offset, size)
+}
+
+/// Hashes flash and polls every `poll_interval` until the hash is done.
+/// Returns the last response, which has a status other than [`VbootHashStatus::Busy`].
+pub fn vboot_hash<File: AsRawFd>(
+    file: &mut File,
+    offset: u32,
+    size: u32,
+    poll_interval: Duration,
+) -> EcCmdResult<VbootHash> {
+    let mut hash = vboot_hash_start(file, offset, size)?;
+    while hash.status == Ok(VbootHashStatus::Busy) {
+        sleep(poll_interval);
+        hash = vboot_hash_get(file)?;
+    }
+    Ok(hash)
+}
@@ -24,8 +24,8 @@ struct EcResponseVersionV1 {
     cros_fwid_rw: [u8; 32],
 }
 
-#[derive(FromPrimitive)]
-enum EcImage {
+#[derive(FromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcImage {
     Unknown = 0,
     Ro = 1,
     Rw = 2,
@@ -33,6 +33,13 @@ enum EcImage {
     RwB = 4,
 }
 
+/// Gets which image the EC is running
+pub fn ec_cmd_current_image<File: AsRawFd>(file: &mut File) -> EcCmdResult<Result<EcImage, u32>> {
+    let response: EcResponseVersionV1 =
+        ec_command_bytemuck(CrosEcCmd::Version, 0, &(), file.as_raw_fd())?;
+    Ok(EcImage::from_u32(response.current_image).ok_or(response.current_image))
+}
+
 pub fn ec_cmd_version(
     file: &mut File,
     protocol_info: &EcResponseGetProtocolInfo,
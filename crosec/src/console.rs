@@ -1,30 +1,153 @@
-use std::fs::File;
 use std::os::fd::AsRawFd;
+use std::thread::sleep;
+use std::time::Duration;
 
 use crate::commands::get_protocol_info::EcResponseGetProtocolInfo;
 use crate::commands::CrosEcCmd;
 use crate::ec_command::{ec_command_bytemuck, ec_command_with_dynamic_output_size};
 use crate::EcCmdResult;
 
-pub fn console(file: &mut File, protocol_info: &EcResponseGetProtocolInfo) -> EcCmdResult<String> {
-    ec_command_bytemuck::<_, ()>(CrosEcCmd::ConsoleSnapshot, 0, &(), file.as_raw_fd())?;
-    let mut console = String::default();
-    loop {
-        let output = ec_command_with_dynamic_output_size(
+/// Which part of the console buffer version 1 of `EC_CMD_CONSOLE_READ` reads
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleReadSubcommand {
+    /// Read the output up to the last snapshot, continuing from the last read
+    Next = 0,
+    /// Read the output between the last two snapshots
+    Recent = 1,
+}
+
+/// Saves the end of the console buffer, which is where reads stop
+pub fn console_snapshot<File: AsRawFd>(file: &mut File) -> EcCmdResult<()> {
+    ec_command_bytemuck::<_, ()>(CrosEcCmd::ConsoleSnapshot, 0, &(), file.as_raw_fd())
+}
+
+/// Reads part of the console buffer. Returns an empty `Vec` when there is nothing left to read.
+/// If `subcommand` is `None`, version 0 is used, which reads like [`ConsoleReadSubcommand::Next`].
+pub fn console_read<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+    subcommand: Option<ConsoleReadSubcommand>,
+) -> EcCmdResult<Vec<u8>> {
+    let mut output = match subcommand {
+        Some(subcommand) => ec_command_with_dynamic_output_size(
+            CrosEcCmd::ConsoleRead,
+            1,
+            &[subcommand as u8],
+            protocol_info.max_ec_output_size(),
+            file.as_raw_fd(),
+        )?,
+        None => ec_command_with_dynamic_output_size(
             CrosEcCmd::ConsoleRead,
             0,
             Default::default(),
             protocol_info.max_ec_output_size(),
             file.as_raw_fd(),
-        )?;
-        let chunk = String::from_utf8(output).unwrap();
-        // Get rid of trailing null characters
-        let chunk = chunk.trim_end_matches('\0');
-        if !chunk.is_empty() {
-            console += chunk;
-        } else {
+        )?,
+    };
+    // Get rid of trailing null characters
+    let len = output
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(output.len());
+    output.truncate(len);
+    Ok(output)
+}
+
+/// Turns console output into a string. Invalid UTF-8 is replaced, and null and carriage return characters are removed.
+pub(crate) fn decode_output(output: &[u8]) -> String {
+    String::from_utf8_lossy(output).replace(['\0', '\r'], "")
+}
+
+pub fn console<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+) -> EcCmdResult<String> {
+    console_snapshot(file)?;
+    let mut console = Vec::default();
+    loop {
+        let chunk = console_read(file, protocol_info, None)?;
+        if chunk.is_empty() {
             break;
         }
+        console.extend(chunk);
+    }
+    Ok(decode_output(&console))
+}
+
+/// An iterator over new console output. Each item is the output since the last item.
+///
+/// The console is polled, since the EC doesn't send an event for new output.
+/// Invalid UTF-8 is replaced, but characters which are split between reads are kept together.
+pub struct ConsoleFollower<'a, File: AsRawFd> {
+    file: &'a mut File,
+    protocol_info: EcResponseGetProtocolInfo,
+    poll_interval: Duration,
+    /// The start of a character which was cut off at the end of the last read
+    incomplete: Vec<u8>,
+}
+
+impl<'a, File: AsRawFd> ConsoleFollower<'a, File> {
+    /// Only output which comes after this is created is read. This needs version 1 of `EC_CMD_CONSOLE_READ`.
+    pub fn new(
+        file: &'a mut File,
+        protocol_info: EcResponseGetProtocolInfo,
+        poll_interval: Duration,
+    ) -> EcCmdResult<Self> {
+        // Skip the output which is already in the buffer
+        console_snapshot(file)?;
+        Ok(Self {
+            file,
+            protocol_info,
+            poll_interval,
+            incomplete: Default::default(),
+        })
+    }
+
+    /// Gets the output since the last poll, which can be empty
+    pub fn poll(&mut self) -> EcCmdResult<String> {
+        console_snapshot(self.file)?;
+        let mut output = std::mem::take(&mut self.incomplete);
+        loop {
+            let chunk = console_read(
+                self.file,
+                &self.protocol_info,
+                Some(ConsoleReadSubcommand::Recent),
+            )?;
+            if chunk.is_empty() {
+                break;
+            }
+            output.extend(chunk);
+        }
+        if let Err(e) = std::str::from_utf8(&output) {
+            if e.error_len().is_none() {
+                self.incomplete = output.split_off(e.valid_up_to());
+            }
+        }
+        Ok(decode_output(&output))
+    }
+
+    /// Waits for new output without blocking the thread
+    pub async fn next_async(&mut self) -> EcCmdResult<String> {
+        loop {
+            let output = self.poll()?;
+            if !output.is_empty() {
+                return Ok(output);
+            }
+            async_std::task::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+impl<'a, File: AsRawFd> Iterator for ConsoleFollower<'a, File> {
+    type Item = EcCmdResult<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.poll() {
+                Ok(output) if output.is_empty() => sleep(self.poll_interval),
+                result => return Some(result),
+            }
+        }
     }
-    Ok(console)
 }
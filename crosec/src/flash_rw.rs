@@ -0,0 +1,472 @@
+use std::fs::File;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::commands::efs_verify::efs_verify;
+use crate::commands::flash::{
+    flash_erase, flash_erase_async, flash_info_latest, flash_region_info, flash_write,
+    EcResponseFlashRegionInfo, FlashInfoV1, FlashRegion,
+};
+use crate::commands::get_protocol_info::{get_protocol_info, EcResponseGetProtocolInfo};
+use crate::commands::reboot_ec::{reboot_ec, RebootEcCommand};
+use crate::commands::vboot_hash::{vboot_hash, VbootHash};
+use crate::commands::version::{ec_cmd_current_image, EcImage};
+use crate::fmap::{self, Fmap};
+use crate::{EcCmdResult, EcError, EcResponseStatus};
+
+/// The size of the chunks that progress is reported for
+const WRITE_CHUNK_SIZE: usize = 0x10000;
+/// How long to wait for the EC to respond after jumping to another image
+const JUMP_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What [`flash_rw`] needs from the EC. This is implemented for the EC device file by [`FlashRwDevice`],
+/// and can be implemented for a simulated EC to test updates without real hardware.
+pub trait FlashRwTarget {
+    fn current_image(&mut self) -> EcCmdResult<Result<EcImage, u32>>;
+    fn reboot(&mut self, command: RebootEcCommand) -> EcCmdResult<()>;
+    fn flash_region_info(&mut self, region: FlashRegion) -> EcCmdResult<EcResponseFlashRegionInfo>;
+    fn flash_erase(&mut self, offset: u32, size: u32) -> EcCmdResult<()>;
+    fn flash_write(&mut self, offset: u32, data: &[u8]) -> EcCmdResult<()>;
+    /// Only needs to be implemented if the EC supports early firmware selection
+    fn efs_verify(&mut self, _region: FlashRegion) -> EcCmdResult<()> {
+        Err(EcError::Response(EcResponseStatus::InvalidCommand))
+    }
+    fn vboot_hash(&mut self, offset: u32, size: u32) -> EcCmdResult<VbootHash>;
+}
+
+/// The EC device file as a [`FlashRwTarget`]
+pub struct FlashRwDevice<'a> {
+    file: &'a mut File,
+    /// What [`flash_write`] needs, which is queried on the first write
+    write_info: Option<(EcResponseGetProtocolInfo, FlashInfoV1)>,
+}
+
+impl<'a> FlashRwDevice<'a> {
+    pub fn new(file: &'a mut File) -> Self {
+        Self {
+            file,
+            write_info: None,
+        }
+    }
+}
+
+impl FlashRwTarget for FlashRwDevice<'_> {
+    fn current_image(&mut self) -> EcCmdResult<Result<EcImage, u32>> {
+        ec_cmd_current_image(self.file)
+    }
+
+    fn reboot(&mut self, command: RebootEcCommand) -> EcCmdResult<()> {
+        reboot_ec(self.file, command, Default::default())
+    }
+
+    fn flash_region_info(&mut self, region: FlashRegion) -> EcCmdResult<EcResponseFlashRegionInfo> {
+        flash_region_info(self.file, region)
+    }
+
+    fn flash_erase(&mut self, offset: u32, size: u32) -> EcCmdResult<()> {
+        // Erasing the whole RW image can take longer than the kernel's timeout, so erase in the background if possible
+        match flash_erase_async(self.file, offset, size, POLL_INTERVAL, |_| {}) {
+            Err(EcError::Response(
+                EcResponseStatus::InvalidVersion | EcResponseStatus::InvalidCommand,
+            )) => flash_erase(self.file, offset, size),
+            result => result,
+        }
+    }
+
+    fn flash_write(&mut self, offset: u32, data: &[u8]) -> EcCmdResult<()> {
+        let (protocol_info, flash_info) = match self.write_info {
+            Some(write_info) => write_info,
+            None => *self
+                .write_info
+                .insert((get_protocol_info(self.file)?, flash_info_latest(self.file)?)),
+        };
+        flash_write(self.file, &protocol_info, &flash_info, offset, data)
+    }
+
+    fn efs_verify(&mut self, region: FlashRegion) -> EcCmdResult<()> {
+        efs_verify(self.file, region)
+    }
+
+    fn vboot_hash(&mut self, offset: u32, size: u32) -> EcCmdResult<VbootHash> {
+        vboot_hash(self.file, offset, size, POLL_INTERVAL)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("EC command failed: {0}")]
+    Ec(#[from] EcError),
+    #[error("invalid image: {0}")]
+    Fmap(#[from] fmap::Error),
+    #[error("the EC is running image {0:?} instead of {1:?}")]
+    WrongImage(Result<EcImage, u32>, EcImage),
+    #[error("the EC didn't respond after jumping to {0:?}")]
+    Timeout(EcImage),
+    #[error("the RW image is {image} bytes, but the EC's update region is {region} bytes")]
+    RegionSize { image: u32, region: u32 },
+    #[error("the new RW image failed verification")]
+    VerifyFailed,
+}
+
+/// The steps of [`flash_rw`], which are reported as they start
+#[derive(Debug, Clone, Copy)]
+pub enum FlashRwStep {
+    JumpToRo,
+    Erase,
+    Write { written: usize, total: usize },
+    Verify,
+    JumpToRw,
+}
+
+fn wait_for_image<Target: FlashRwTarget>(target: &mut Target, image: EcImage) -> Result<(), Error> {
+    let start = Instant::now();
+    let mut last_image = None;
+    while start.elapsed() < JUMP_TIMEOUT {
+        match target.current_image() {
+            Ok(Ok(current_image)) if current_image == image => return Ok(()),
+            Ok(current_image) => last_image = Some(current_image),
+            // The EC doesn't respond while it's jumping
+            Err(_) => {}
+        }
+        sleep(POLL_INTERVAL);
+    }
+    Err(match last_image {
+        Some(last_image) => Error::WrongImage(last_image, image),
+        None => Error::Timeout(image),
+    })
+}
+
+fn verify_rw<Target: FlashRwTarget>(
+    target: &mut Target,
+    offset: u32,
+    rw: &[u8],
+) -> Result<(), Error> {
+    match target.efs_verify(FlashRegion::Update) {
+        Ok(()) => return Ok(()),
+        Err(EcError::Response(EcResponseStatus::InvalidCommand)) => {}
+        Err(_) => return Err(Error::VerifyFailed),
+    }
+    // The EC doesn't support EFS, so compare the hash of the flash with the image instead
+    let hash = target.vboot_hash(offset, rw.len() as u32)?;
+    if hash.size == rw.len() as u32 && hash.matches(rw) {
+        Ok(())
+    } else {
+        Err(Error::VerifyFailed)
+    }
+}
+
+/// Updates the RW image of the EC with the RW image in `image`, which must be a full EC image with an FMAP.
+///
+/// If the EC is running RW, it jumps to RO first. The new RW image is written to the region the EC reports as
+/// [`FlashRegion::Update`], which is the inactive slot on ECs with two RW slots, and is the region that gets verified.
+/// After the new RW image is written and verified, the EC jumps to it.
+/// `on_step` is called at the start of each step.
+pub fn flash_rw<Target: FlashRwTarget>(
+    target: &mut Target,
+    image: &[u8],
+    mut on_step: impl FnMut(FlashRwStep),
+) -> Result<(), Error> {
+    let fmap = Fmap::find(image)?;
+    let rw_area = fmap.area("EC_RW")?;
+    let rw = fmap.area_data(image, "EC_RW")?;
+    // Make sure it's an EC image and not some other firmware with an FMAP
+    fmap.area("EC_RO")?;
+
+    match target.current_image()? {
+        Ok(EcImage::Ro) => {}
+        Ok(EcImage::Rw) => {
+            on_step(FlashRwStep::JumpToRo);
+            target.reboot(RebootEcCommand::JumpRo)?;
+            wait_for_image(target, EcImage::Ro)?;
+        }
+        current_image => return Err(Error::WrongImage(current_image, EcImage::Ro)),
+    }
+
+    let update_region = target.flash_region_info(FlashRegion::Update)?;
+    if update_region.size != rw_area.size {
+        return Err(Error::RegionSize {
+            image: rw_area.size,
+            region: update_region.size,
+        });
+    }
+
+    on_step(FlashRwStep::Erase);
+    target.flash_erase(update_region.offset, update_region.size)?;
+
+    for (chunk_index, chunk) in rw.chunks(WRITE_CHUNK_SIZE).enumerate() {
+        let written = chunk_index * WRITE_CHUNK_SIZE;
+        on_step(FlashRwStep::Write {
+            written,
+            total: rw.len(),
+        });
+        target.flash_write(update_region.offset + written as u32, chunk)?;
+    }
+
+    on_step(FlashRwStep::Verify);
+    verify_rw(target, update_region.offset, rw)?;
+
+    on_step(FlashRwStep::JumpToRw);
+    target.reboot(RebootEcCommand::JumpRw)?;
+    wait_for_image(target, EcImage::Rw)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::vboot_hash::{sha256, VbootHashStatus};
+
+    use super::*;
+
+    const REGION_SIZE: u32 = 0x100;
+    const RO_OFFSET: u32 = 0;
+    const RW_OFFSET: u32 = 0x100;
+    /// The second RW slot, which is the update region of the simulated EFS EC
+    const RW_B_OFFSET: u32 = 0x200;
+    const FMAP_OFFSET: usize = 0x300;
+
+    /// An EC with flash in memory
+    struct SimulatedEc {
+        flash: Vec<u8>,
+        image: EcImage,
+        update_region: EcResponseFlashRegionInfo,
+        /// If the EC supports EFS, the RW image which passes verification
+        efs_valid_rw: Option<Vec<u8>>,
+        /// Flips a bit of every write, like a broken flash
+        corrupt_writes: bool,
+        /// Overrides the image which the EC jumps to
+        jump_to: Option<EcImage>,
+        /// The EC stops responding after jumping
+        hang_on_jump: bool,
+        responding: bool,
+        hash_count: usize,
+    }
+
+    impl SimulatedEc {
+        fn new(image: EcImage) -> Self {
+            Self {
+                flash: vec![0xff; FMAP_OFFSET],
+                image,
+                update_region: EcResponseFlashRegionInfo {
+                    offset: RW_OFFSET,
+                    size: REGION_SIZE,
+                },
+                efs_valid_rw: None,
+                corrupt_writes: false,
+                jump_to: None,
+                hang_on_jump: false,
+                responding: true,
+                hash_count: 0,
+            }
+        }
+
+        fn region(&self, offset: u32) -> &[u8] {
+            &self.flash[offset as usize..(offset + REGION_SIZE) as usize]
+        }
+    }
+
+    impl FlashRwTarget for SimulatedEc {
+        fn current_image(&mut self) -> EcCmdResult<Result<EcImage, u32>> {
+            if self.responding {
+                Ok(Ok(self.image))
+            } else {
+                Err(EcError::DeviceError(nix::errno::Errno::ETIMEDOUT))
+            }
+        }
+
+        fn reboot(&mut self, command: RebootEcCommand) -> EcCmdResult<()> {
+            let image = match command {
+                RebootEcCommand::JumpRo => EcImage::Ro,
+                RebootEcCommand::JumpRw => EcImage::Rw,
+                _ => return Err(EcError::Response(EcResponseStatus::InvalidParam)),
+            };
+            self.image = self.jump_to.unwrap_or(image);
+            self.responding = !self.hang_on_jump;
+            Ok(())
+        }
+
+        fn flash_region_info(
+            &mut self,
+            region: FlashRegion,
+        ) -> EcCmdResult<EcResponseFlashRegionInfo> {
+            match region {
+                FlashRegion::Update => Ok(self.update_region),
+                _ => Err(EcError::Response(EcResponseStatus::InvalidParam)),
+            }
+        }
+
+        fn flash_erase(&mut self, offset: u32, size: u32) -> EcCmdResult<()> {
+            self.flash[offset as usize..(offset + size) as usize].fill(0xff);
+            Ok(())
+        }
+
+        fn flash_write(&mut self, offset: u32, data: &[u8]) -> EcCmdResult<()> {
+            let flash = &mut self.flash[offset as usize..offset as usize + data.len()];
+            flash.copy_from_slice(data);
+            if self.corrupt_writes {
+                flash[0] ^= 1;
+            }
+            Ok(())
+        }
+
+        fn efs_verify(&mut self, region: FlashRegion) -> EcCmdResult<()> {
+            let Some(valid_rw) = &self.efs_valid_rw else {
+                return Err(EcError::Response(EcResponseStatus::InvalidCommand));
+            };
+            assert!(matches!(region, FlashRegion::Update));
+            if self.region(self.update_region.offset) == valid_rw.as_slice() {
+                Ok(())
+            } else {
+                Err(EcError::Response(EcResponseStatus::Error))
+            }
+        }
+
+        fn vboot_hash(&mut self, offset: u32, size: u32) -> EcCmdResult<VbootHash> {
+            self.hash_count += 1;
+            let data = &self.flash[offset as usize..(offset + size) as usize];
+            Ok(VbootHash {
+                status: Ok(VbootHashStatus::Done),
+                offset,
+                size,
+                digest: sha256(data).to_vec(),
+            })
+        }
+    }
+
+    fn fmap_area(offset: u32, size: u32, name: &str) -> Vec<u8> {
+        let mut area = offset.to_le_bytes().to_vec();
+        area.extend_from_slice(&size.to_le_bytes());
+        let mut name_bytes = [0; 32];
+        name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+        area.extend_from_slice(&name_bytes);
+        area.extend_from_slice(&0u16.to_le_bytes());
+        area
+    }
+
+    /// An EC image with an RO image of 0x11s, `rw`, and an FMAP
+    fn ec_image(rw: &[u8]) -> Vec<u8> {
+        let mut image = vec![0x11; RW_OFFSET as usize];
+        image.extend_from_slice(rw);
+        image.resize(FMAP_OFFSET, 0xff);
+        image.extend_from_slice(b"__FMAP__");
+        image.extend_from_slice(&[1, 1]);
+        image.extend_from_slice(&0u64.to_le_bytes());
+        image.extend_from_slice(&(FMAP_OFFSET as u32).to_le_bytes());
+        image.extend_from_slice(&[0; 32]);
+        image.extend_from_slice(&2u16.to_le_bytes());
+        image.extend(fmap_area(RO_OFFSET, REGION_SIZE, "EC_RO"));
+        image.extend(fmap_area(RW_OFFSET, REGION_SIZE, "EC_RW"));
+        image
+    }
+
+    fn new_rw() -> Vec<u8> {
+        (0..REGION_SIZE).map(|byte| byte as u8).collect()
+    }
+
+    /// Runs [`flash_rw`] and returns the steps which were reported
+    fn run(ec: &mut SimulatedEc, image: &[u8]) -> (Result<(), Error>, Vec<String>) {
+        let mut steps = Vec::new();
+        let result = flash_rw(ec, image, |step| {
+            steps.push(match step {
+                FlashRwStep::Write { .. } => "Write".to_owned(),
+                step => format!("{step:?}"),
+            })
+        });
+        (result, steps)
+    }
+
+    #[test]
+    fn writes_update_region() {
+        let rw = new_rw();
+        let mut ec = SimulatedEc::new(EcImage::Ro);
+        // On an EC with two RW slots, the update region is the inactive slot, not where EC_RW is in the image
+        ec.update_region.offset = RW_B_OFFSET;
+        ec.efs_valid_rw = Some(rw.clone());
+        let (result, steps) = run(&mut ec, &ec_image(&rw));
+        result.unwrap();
+        assert_eq!(steps, ["Erase", "Write", "Verify", "JumpToRw"]);
+        assert_eq!(ec.region(RW_B_OFFSET), rw);
+        assert!(ec.region(RW_OFFSET).iter().all(|byte| *byte == 0xff));
+        assert_eq!(ec.hash_count, 0);
+        assert_eq!(ec.image, EcImage::Rw);
+    }
+
+    #[test]
+    fn jumps_to_ro_first() {
+        let rw = new_rw();
+        let mut ec = SimulatedEc::new(EcImage::Rw);
+        ec.efs_valid_rw = Some(rw.clone());
+        let (result, steps) = run(&mut ec, &ec_image(&rw));
+        result.unwrap();
+        assert_eq!(steps, ["JumpToRo", "Erase", "Write", "Verify", "JumpToRw"]);
+        assert_eq!(ec.region(RW_OFFSET), rw);
+        assert_eq!(ec.image, EcImage::Rw);
+    }
+
+    #[test]
+    fn verifies_with_hash_without_efs() {
+        let rw = new_rw();
+        let mut ec = SimulatedEc::new(EcImage::Ro);
+        run(&mut ec, &ec_image(&rw)).0.unwrap();
+        assert_eq!(ec.hash_count, 1);
+        assert_eq!(ec.region(RW_OFFSET), rw);
+        assert_eq!(ec.image, EcImage::Rw);
+    }
+
+    #[test]
+    fn hash_mismatch() {
+        let mut ec = SimulatedEc::new(EcImage::Ro);
+        ec.corrupt_writes = true;
+        let (result, steps) = run(&mut ec, &ec_image(&new_rw()));
+        assert!(matches!(result, Err(Error::VerifyFailed)));
+        assert_eq!(steps, ["Erase", "Write", "Verify"]);
+        // The EC must not jump to a broken image
+        assert_eq!(ec.image, EcImage::Ro);
+    }
+
+    #[test]
+    fn efs_verification_fails() {
+        let mut ec = SimulatedEc::new(EcImage::Ro);
+        ec.efs_valid_rw = Some(vec![0; REGION_SIZE as usize]);
+        let (result, _) = run(&mut ec, &ec_image(&new_rw()));
+        assert!(matches!(result, Err(Error::VerifyFailed)));
+        assert_eq!(ec.image, EcImage::Ro);
+    }
+
+    #[test]
+    fn update_region_size_mismatch() {
+        let mut ec = SimulatedEc::new(EcImage::Ro);
+        ec.update_region.size = REGION_SIZE * 2;
+        let (result, steps) = run(&mut ec, &ec_image(&new_rw()));
+        assert!(matches!(
+            result,
+            Err(Error::RegionSize {
+                image: REGION_SIZE,
+                region
+            }) if region == REGION_SIZE * 2
+        ));
+        assert!(steps.is_empty());
+    }
+
+    #[test]
+    fn stays_in_wrong_image() {
+        let mut ec = SimulatedEc::new(EcImage::Rw);
+        ec.jump_to = Some(EcImage::Rw);
+        let (result, steps) = run(&mut ec, &ec_image(&new_rw()));
+        assert!(matches!(
+            result,
+            Err(Error::WrongImage(Ok(EcImage::Rw), EcImage::Ro))
+        ));
+        assert_eq!(steps, ["JumpToRo"]);
+    }
+
+    #[test]
+    fn no_response_after_jump() {
+        let mut ec = SimulatedEc::new(EcImage::Rw);
+        ec.hang_on_jump = true;
+        let (result, steps) = run(&mut ec, &ec_image(&new_rw()));
+        assert!(matches!(result, Err(Error::Timeout(EcImage::Ro))));
+        assert_eq!(steps, ["JumpToRo"]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::mem::size_of;
+
+use bytemuck::{pod_read_unaligned, Pod, Zeroable};
+use thiserror::Error;
+
+pub const FMAP_SIGNATURE: &[u8; 8] = b"__FMAP__";
+pub const FMAP_VER_MAJOR: u8 = 1;
+pub const FMAP_STRLEN: usize = 32;
+
+/// The area is static, such as RO code
+pub const FMAP_AREA_STATIC: u16 = 1 << 0;
+/// The area is compressed
+pub const FMAP_AREA_COMPRESSED: u16 = 1 << 1;
+/// The area is read-only
+pub const FMAP_AREA_RO: u16 = 1 << 2;
+/// The area is preserved when the flash is updated
+pub const FMAP_AREA_PRESERVE: u16 = 1 << 3;
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct FmapHeader {
+    signature: [u8; 8],
+    ver_major: u8,
+    ver_minor: u8,
+    base: u64,
+    size: u32,
+    name: [u8; FMAP_STRLEN],
+    nareas: u16,
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct FmapAreaRaw {
+    offset: u32,
+    size: u32,
+    name: [u8; FMAP_STRLEN],
+    flags: u16,
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("the image doesn't have an FMAP")]
+    NotFound,
+    #[error("the FMAP is cut off")]
+    Truncated,
+    #[error("the image doesn't have an area named {0}")]
+    MissingArea(String),
+    #[error("area {0} is outside of the image")]
+    AreaOutOfBounds(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FmapArea {
+    /// The offset from the start of the image
+    pub offset: u32,
+    pub size: u32,
+    pub name: String,
+    /// See `FMAP_AREA_*`
+    pub flags: u16,
+}
+
+/// The flash map, which describes where each area is in a firmware image
+#[derive(Debug, Clone)]
+pub struct Fmap {
+    /// Where the FMAP was found in the image
+    pub fmap_offset: usize,
+    pub ver_major: u8,
+    pub ver_minor: u8,
+    /// The address of the image in the flash chip's address space
+    pub base: u64,
+    pub size: u32,
+    pub name: String,
+    pub areas: Vec<FmapArea>,
+}
+
+/// Decodes a string which is padded with NULs, like the names in the FMAP
+pub(crate) fn fmap_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+impl Fmap {
+    /// Finds and parses the FMAP in an image.
+    /// The signature can also be in code which uses it, so a match is only used if its header makes sense.
+    pub fn find(image: &[u8]) -> Result<Self, Error> {
+        let mut found_truncated = false;
+        for offset in (0..image.len().saturating_sub(FMAP_SIGNATURE.len() - 1))
+            .filter(|offset| image[*offset..].starts_with(FMAP_SIGNATURE))
+        {
+            match Self::parse(image, offset) {
+                Ok(fmap) => return Ok(fmap),
+                Err(Error::Truncated) => found_truncated = true,
+                Err(_) => {}
+            }
+        }
+        Err(if found_truncated {
+            Error::Truncated
+        } else {
+            Error::NotFound
+        })
+    }
+
+    /// Parses the FMAP which is at `offset` in the image
+    pub fn parse(image: &[u8], offset: usize) -> Result<Self, Error> {
+        let header = image
+            .get(offset..offset + size_of::<FmapHeader>())
+            .ok_or(Error::Truncated)?;
+        let header: FmapHeader = pod_read_unaligned(header);
+        if &header.signature != FMAP_SIGNATURE || header.ver_major != FMAP_VER_MAJOR {
+            return Err(Error::NotFound);
+        }
+        let areas_offset = offset + size_of::<FmapHeader>();
+        let areas = image
+            .get(areas_offset..areas_offset + header.nareas as usize * size_of::<FmapAreaRaw>())
+            .ok_or(Error::Truncated)?;
+        Ok(Self {
+            fmap_offset: offset,
+            ver_major: header.ver_major,
+            ver_minor: header.ver_minor,
+            base: header.base,
+            size: header.size,
+            name: fmap_string(&header.name),
+            areas: areas
+                .chunks_exact(size_of::<FmapAreaRaw>())
+                .map(pod_read_unaligned::<FmapAreaRaw>)
+                .map(|area| FmapArea {
+                    offset: area.offset,
+                    size: area.size,
+                    name: fmap_string(&area.name),
+                    flags: area.flags,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn area(&self, name: &str) -> Result<&FmapArea, Error> {
+        self.areas
+            .iter()
+            .find(|area| area.name == name)
+            .ok_or_else(|| Error::MissingArea(name.to_owned()))
+    }
+
+    /// Gets the contents of an area of the image
+    pub fn area_data<'a>(&self, image: &'a [u8], name: &str) -> Result<&'a [u8], Error> {
+        let area = self.area(name)?;
+        image
+            .get(area.offset as usize..area.offset as usize + area.size as usize)
+            .ok_or_else(|| Error::AreaOutOfBounds(name.to_owned()))
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::Read;
+use std::os::fd::AsRawFd;
+
+use thiserror::Error;
+
+use crate::commands::fp_download::{fp_download_template, FpTemplate};
+use crate::commands::fp_info::{fp_info, EcResponseFpInfo};
+use crate::commands::fp_mode::{fp_mode, FpMode};
+use crate::commands::get_protocol_info::{get_protocol_info, EcResponseGetProtocolInfo};
+use crate::wait_event;
+use crate::wait_event::fingerprint::{
+    EcMkbpEventFingerprintEnroll, EcMkbpEventFingerprintEnrollError, EcMkbpEventFingerprintRust,
+};
+use crate::EcError;
+
+use super::wait_fingerprint_event;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Ec(#[from] wait_event::Error),
+    #[error("no fingerprint event came in time")]
+    Timeout,
+    #[error("the FPMCU had an internal error while enrolling")]
+    Internal,
+    #[error("the FPMCU can't store any more templates")]
+    TemplatesFull,
+}
+
+impl From<EcError> for Error {
+    fn from(e: EcError) -> Self {
+        Self::Ec(e.into())
+    }
+}
+
+/// Why a touch didn't add to the enrollment. The user should touch the sensor again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FpEnrollFeedback {
+    /// The image was too noisy, possibly because the sensor or finger is dirty
+    LowQuality,
+    /// The finger is in the same spot as the last touch, so it should be moved a bit
+    Immobile,
+    /// Too little of the finger was on the sensor
+    LowCoverage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FpEnrollProgress {
+    /// The user should touch the sensor
+    PlaceFinger,
+    /// A touch was captured
+    Touch {
+        /// How much of the enrollment is done
+        percentage: u8,
+        feedback: Option<FpEnrollFeedback>,
+    },
+    /// The user should lift their finger before the next touch
+    LiftFinger,
+}
+
+const ENROLL_MODE: u32 = FpMode::EnrollSession as u32 | FpMode::EnrollImage as u32;
+const FINGER_UP_MODE: u32 = FpMode::EnrollSession as u32 | FpMode::FingerUp as u32;
+
+/// Enrolls a finger, which takes a number of touches that depends on the sensor.
+///
+/// This does what you would otherwise do by setting the `EnrollSession` and `EnrollImage` modes
+/// and then waiting for fingerprint events, as described in [`FpMode`].
+pub struct FpEnrollment<'a, File: AsRawFd + Read> {
+    file: &'a mut File,
+    fp_info: EcResponseFpInfo,
+    protocol_info: EcResponseGetProtocolInfo,
+    timeout: Option<i32>,
+}
+
+impl<'a, File: AsRawFd + Read> FpEnrollment<'a, File> {
+    /// If `timeout` is specified in milliseconds, enrolling fails if the user takes longer than that for a step
+    pub fn new(file: &'a mut File, timeout: Option<i32>) -> Result<Self, Error> {
+        let fp_info = fp_info(file)?;
+        if fp_info.template_valid >= fp_info.template_max {
+            return Err(Error::TemplatesFull);
+        }
+        let protocol_info = get_protocol_info(file)?;
+        Ok(Self {
+            file,
+            fp_info,
+            protocol_info,
+            timeout,
+        })
+    }
+
+    fn wait_fingerprint_event(&mut self) -> Result<EcMkbpEventFingerprintRust, Error> {
+        wait_fingerprint_event(self.file, self.timeout)
+            .map_err(wait_event::Error::WaitEvent)?
+            .ok_or(Error::Timeout)
+    }
+
+    fn wait_enroll_event(&mut self) -> Result<EcMkbpEventFingerprintEnroll, Error> {
+        loop {
+            if let EcMkbpEventFingerprintRust::Enroll(enroll) = self.wait_fingerprint_event()? {
+                return Ok(enroll);
+            }
+        }
+    }
+
+    fn wait_finger_up(&mut self) -> Result<(), Error> {
+        while !matches!(
+            self.wait_fingerprint_event()?,
+            EcMkbpEventFingerprintRust::FingerUp
+        ) {}
+        Ok(())
+    }
+
+    fn touch(&mut self) -> Result<FpEnrollProgress, Error> {
+        fp_mode(self.file, ENROLL_MODE)?;
+        let enroll = self.wait_enroll_event()?;
+        let feedback = match enroll.error {
+            None => None,
+            Some(EcMkbpEventFingerprintEnrollError::LowQuality) => {
+                Some(FpEnrollFeedback::LowQuality)
+            }
+            Some(EcMkbpEventFingerprintEnrollError::Immobile) => Some(FpEnrollFeedback::Immobile),
+            Some(EcMkbpEventFingerprintEnrollError::LowCoverage) => {
+                Some(FpEnrollFeedback::LowCoverage)
+            }
+            Some(EcMkbpEventFingerprintEnrollError::Internal) => return Err(Error::Internal),
+        };
+        Ok(FpEnrollProgress::Touch {
+            percentage: enroll.percentage,
+            feedback,
+        })
+    }
+
+    /// Enrolls a finger, calling `on_progress` to tell the user what to do and how far along the enrollment is.
+    /// Returns the new template, which is also loaded into the FPMCU.
+    ///
+    /// If this fails, call [`FpEnrollment::cancel`] to get the FPMCU out of enroll mode.
+    pub fn run(
+        &mut self,
+        mut on_progress: impl FnMut(FpEnrollProgress),
+    ) -> Result<FpTemplate, Error> {
+        loop {
+            on_progress(FpEnrollProgress::PlaceFinger);
+            let progress = self.touch()?;
+            on_progress(progress);
+            if let FpEnrollProgress::Touch {
+                percentage: 100, ..
+            } = progress
+            {
+                break;
+            }
+            on_progress(FpEnrollProgress::LiftFinger);
+            fp_mode(self.file, FINGER_UP_MODE)?;
+            self.wait_finger_up()?;
+        }
+        // The new template is added after the templates which were already loaded
+        self.fp_info = fp_info(self.file)?;
+        let index = self.fp_info.template_valid.saturating_sub(1) as usize;
+        Ok(fp_download_template(
+            self.file,
+            &self.fp_info,
+            &self.protocol_info,
+            index,
+        ))
+    }
+
+    /// Stops enrolling. The finger which was being enrolled isn't added.
+    pub fn cancel(self) -> Result<(), Error> {
+        fp_mode(self.file, FpMode::Reset as u32)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::io::Read;
+use std::os::fd::AsRawFd;
+
+use thiserror::Error;
+
+use crate::commands::fp_download::{fp_download_template, FpTemplate};
+use crate::commands::fp_info::fp_info;
+use crate::commands::fp_mode::{fp_mode, FpMode};
+use crate::commands::get_protocol_info::{get_protocol_info, EcResponseGetProtocolInfo};
+use crate::wait_event;
+use crate::wait_event::fingerprint::{
+    EcMkbpEventFingerprintMatchResult, EcMkbpEventFingerprintRust,
+};
+use crate::EcError;
+
+use super::{upload_templates, wait_fingerprint_event, TemplateError};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Ec(#[from] wait_event::Error),
+    #[error("no fingerprint event came in time")]
+    Timeout,
+    #[error(transparent)]
+    Templates(#[from] TemplateError),
+}
+
+impl From<EcError> for Error {
+    fn from(e: EcError) -> Self {
+        Self::Ec(e.into())
+    }
+}
+
+pub struct FpMatch {
+    pub result: EcMkbpEventFingerprintMatchResult,
+    /// Templates which the FPMCU updated while matching, and their indexes in the FPMCU.
+    /// Save these in place of the old templates so future matches are more accurate.
+    pub updated_templates: Vec<(usize, FpTemplate)>,
+}
+
+/// Matches fingers against a set of templates
+pub struct FpMatcher<'a, File: AsRawFd + Read> {
+    file: &'a mut File,
+    protocol_info: EcResponseGetProtocolInfo,
+    /// The index in the FPMCU of the first template which was uploaded
+    first_index: usize,
+    template_count: usize,
+    timeout: Option<i32>,
+}
+
+impl<'a, File: AsRawFd + Read> FpMatcher<'a, File> {
+    /// Uploads `templates` after the templates which are already loaded.
+    /// If `timeout` is specified in milliseconds, matching fails if there's no touch in time.
+    pub fn new(
+        file: &'a mut File,
+        templates: &[FpTemplate],
+        timeout: Option<i32>,
+    ) -> Result<Self, Error> {
+        let protocol_info = get_protocol_info(file)?;
+        let fp_info = fp_info(file)?;
+        let first_index = fp_info.template_valid as usize;
+        upload_templates(file, &protocol_info, &fp_info, templates)?;
+        Ok(Self {
+            file,
+            protocol_info,
+            first_index,
+            template_count: templates.len(),
+            timeout,
+        })
+    }
+
+    /// Converts an index in the FPMCU, such as the one in a match result, to an index in the templates given to [`FpMatcher::new`].
+    /// Returns `None` if the template was already loaded before.
+    pub fn template_index(&self, fpmcu_index: usize) -> Option<usize> {
+        fpmcu_index
+            .checked_sub(self.first_index)
+            .filter(|index| *index < self.template_count)
+    }
+
+    fn wait_fingerprint_event(&mut self) -> Result<EcMkbpEventFingerprintRust, Error> {
+        wait_fingerprint_event(self.file, self.timeout)
+            .map_err(wait_event::Error::WaitEvent)?
+            .ok_or(Error::Timeout)
+    }
+
+    /// Downloads the templates which are marked as dirty, which clears the dirty flag
+    fn download_updated_templates(&mut self) -> Result<Vec<(usize, FpTemplate)>, Error> {
+        let fp_info = fp_info(self.file)?;
+        Ok((0..fp_info.template_valid as usize)
+            .filter(|index| *index < 32 && fp_info.template_dirty & (1 << index) != 0)
+            .map(|index| {
+                let template =
+                    fp_download_template(self.file, &fp_info, &self.protocol_info, index);
+                (index, template)
+            })
+            .collect())
+    }
+
+    /// Waits for a finger to touch the sensor and matches it.
+    /// Then waits for the finger to be lifted, so the same touch isn't matched again.
+    pub fn match_finger(&mut self) -> Result<FpMatch, Error> {
+        fp_mode(self.file, FpMode::Match as u32)?;
+        let result = loop {
+            if let EcMkbpEventFingerprintRust::Match(result) = self.wait_fingerprint_event()? {
+                break result;
+            }
+        };
+        let updated_templates = match result {
+            EcMkbpEventFingerprintMatchResult::Match(_) => self.download_updated_templates()?,
+            EcMkbpEventFingerprintMatchResult::NoMatch(_) => Vec::new(),
+        };
+        fp_mode(self.file, FpMode::FingerUp as u32)?;
+        while !matches!(
+            self.wait_fingerprint_event()?,
+            EcMkbpEventFingerprintRust::FingerUp
+        ) {}
+        Ok(FpMatch {
+            result,
+            updated_templates,
+        })
+    }
+
+    /// Stops waiting for a finger
+    pub fn cancel(self) -> Result<(), Error> {
+        fp_mode(self.file, FpMode::Reset as u32)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! High-level fingerprint APIs which handle the order of commands and events,
+//! built on the commands in [`crate::commands`]
+
+use std::io::Read;
+use std::os::fd::AsRawFd;
+
+use thiserror::Error;
+
+use crate::commands::fp_download::FpTemplate;
+use crate::commands::fp_info::EcResponseFpInfo;
+use crate::commands::fp_upload_template::fp_upload_template;
+use crate::commands::get_protocol_info::EcResponseGetProtocolInfo;
+use crate::wait_event::event::{EcMkbpEvent, EcMkbpEventType};
+use crate::wait_event::fingerprint::EcMkbpEventFingerprintRust;
+use crate::wait_event::{wait_event_sync, PollData};
+use crate::EcError;
+
+pub mod enroll;
+pub mod matcher;
+pub mod session;
+pub mod store;
+
+/// Why templates couldn't be loaded into the FPMCU
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("the FPMCU can only store {0} templates")]
+    TooMany(u16),
+    #[error("template {index} is {size} bytes, but the FPMCU uses {expected} bytes")]
+    Size {
+        index: usize,
+        size: usize,
+        expected: u32,
+    },
+    #[error("failed to upload template {index}: {source}")]
+    Upload { index: usize, source: EcError },
+}
+
+/// Uploads `templates` after the templates which are already loaded.
+/// All of the templates are checked before any are uploaded, so a bad template doesn't leave some of them loaded.
+fn upload_templates<File: AsRawFd>(
+    file: &mut File,
+    protocol_info: &EcResponseGetProtocolInfo,
+    fp_info: &EcResponseFpInfo,
+    templates: &[FpTemplate],
+) -> Result<(), TemplateError> {
+    if fp_info.template_valid as usize + templates.len() > fp_info.template_max as usize {
+        return Err(TemplateError::TooMany(fp_info.template_max));
+    }
+    for (index, template) in templates.iter().enumerate() {
+        if template.buffer().len() != fp_info.template_size as usize {
+            return Err(TemplateError::Size {
+                index,
+                size: template.buffer().len(),
+                expected: fp_info.template_size,
+            });
+        }
+    }
+    for (index, template) in templates.iter().enumerate() {
+        fp_upload_template(file, protocol_info, fp_info, template)
+            .map_err(|source| TemplateError::Upload { index, source })?;
+    }
+    Ok(())
+}
+
+/// Waits for the next fingerprint event. Returns `None` if there's no event before the timeout in milliseconds.
+fn wait_fingerprint_event<File: AsRawFd + Read>(
+    file: &mut File,
+    timeout: Option<i32>,
+) -> Result<Option<EcMkbpEventFingerprintRust>, i32> {
+    loop {
+        match wait_event_sync(file, [EcMkbpEventType::Fingerprint], timeout)? {
+            PollData::EventHappened(EcMkbpEvent::Fingerprint(event)) => {
+                return Ok(Some(event.rust()))
+            }
+            PollData::Timeout => return Ok(None),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::io::Read;
+use std::os::fd::AsRawFd;
+
+use thiserror::Error;
+
+use crate::commands::fp_download::FpTemplate;
+use crate::commands::fp_get_encryption_status::{fp_get_encryption_status, FpEncryptionStatus};
+use crate::commands::fp_info::fp_info;
+use crate::commands::fp_mode::{fp_mode, FpMode};
+use crate::commands::fp_set_context::{fp_set_context, UserId};
+use crate::commands::fp_set_seed::{fp_set_seed, FP_CONTEXT_TPM_BYTES};
+use crate::commands::get_protocol_info::get_protocol_info;
+use crate::EcError;
+
+use super::enroll::{self, FpEnrollment};
+use super::matcher::{self, FpMatcher};
+use super::{upload_templates, TemplateError};
+
+/// Which step of starting the session failed
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to get the encryption status: {0}")]
+    EncryptionStatus(EcError),
+    #[error("failed to set the seed: {0}")]
+    SetSeed(EcError),
+    #[error("failed to reset the fingerprint mode: {0}")]
+    ResetMode(EcError),
+    #[error("failed to set the context: {0}")]
+    SetContext(EcError),
+    #[error("failed to get the fingerprint info: {0}")]
+    FpInfo(EcError),
+    #[error("failed to get the protocol info: {0}")]
+    ProtocolInfo(EcError),
+    #[error(transparent)]
+    Templates(#[from] TemplateError),
+}
+
+/// The FPMCU set up for a user, with the user's templates loaded
+pub struct FpSession<'a, File: AsRawFd + Read> {
+    file: &'a mut File,
+    user_id: UserId,
+    /// Whether the seed was set by this session, as opposed to already being set
+    seed_set: bool,
+}
+
+impl<'a, File: AsRawFd + Read> FpSession<'a, File> {
+    /// Sets up the FPMCU for a user in the order it needs:
+    /// sets the seed if it isn't set yet, resets the mode, sets the user ID as the context, and uploads the templates.
+    ///
+    /// This can be called again, such as when a different user logs in. The seed can only be set once per FPMCU boot,
+    /// so it must be the same seed every time. Setting the context unloads the templates of the last user.
+    ///
+    /// There's no timeout, because nothing here waits for an event. Each step is a host command, which the kernel
+    /// driver times out, and setting the context polls for up to 2 seconds while the FPMCU derives the key.
+    pub fn start(
+        file: &'a mut File,
+        seed: [u8; FP_CONTEXT_TPM_BYTES],
+        user_id: UserId,
+        templates: &[FpTemplate],
+    ) -> Result<Self, Error> {
+        let encryption_status = fp_get_encryption_status(file).map_err(Error::EncryptionStatus)?;
+        let seed_already_set = encryption_status.valid_flags & FpEncryptionStatus::SeedSet as u32
+            != 0
+            && encryption_status.status & FpEncryptionStatus::SeedSet as u32 != 0;
+        if !seed_already_set {
+            fp_set_seed(file, seed).map_err(Error::SetSeed)?;
+        }
+        fp_mode(file, FpMode::Reset as u32).map_err(Error::ResetMode)?;
+        fp_set_context(file, user_id).map_err(Error::SetContext)?;
+
+        let fp_info = fp_info(file).map_err(Error::FpInfo)?;
+        let protocol_info = get_protocol_info(file).map_err(Error::ProtocolInfo)?;
+        upload_templates(file, &protocol_info, &fp_info, templates)?;
+        Ok(Self {
+            file,
+            user_id,
+            seed_set: !seed_already_set,
+        })
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    /// `false` if the seed was already set before the session started
+    pub fn seed_set(&self) -> bool {
+        self.seed_set
+    }
+
+    /// Enrolls a new finger for the user. The new template is loaded after the user's templates.
+    pub fn enroll(
+        &mut self,
+        timeout: Option<i32>,
+    ) -> Result<FpEnrollment<'_, File>, enroll::Error> {
+        FpEnrollment::new(self.file, timeout)
+    }
+
+    /// Matches fingers against the user's templates.
+    /// Indexes in match results are the indexes of the templates given to [`FpSession::start`].
+    pub fn matcher(&mut self, timeout: Option<i32>) -> Result<FpMatcher<'_, File>, matcher::Error> {
+        FpMatcher::new(self.file, &[], timeout)
+    }
+}
@@ -0,0 +1,444 @@
+//! Saves fingerprint templates to disk, like biod does.
+//!
+//! Templates downloaded from the FPMCU are already encrypted with a key derived from the seed
+//! and the user ID, so they are saved as they are. They can only be used by the same FPMCU with the same seed and user ID.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::mem::size_of;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use bytemuck::{bytes_of, pod_read_unaligned, Pod, Zeroable};
+use thiserror::Error;
+
+use crate::commands::fp_download::FpTemplate;
+use crate::commands::fp_info::EcResponseFpInfo;
+use crate::commands::fp_set_context::UserId;
+
+const STORED_TEMPLATE_MAGIC: [u8; 4] = *b"CRFP";
+/// The version of the file format, not the template format
+const STORED_TEMPLATE_VERSION: u32 = 1;
+const TEMPLATE_EXTENSION: &str = "fptemplate";
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid finger name {0:?}")]
+    InvalidFingerName(String),
+    #[error("{0} isn't a stored template")]
+    InvalidFile(PathBuf),
+    #[error("the template was made by a different sensor")]
+    DifferentSensor,
+    #[error("the template has version {stored}, but the FPMCU uses version {fpmcu}")]
+    DifferentTemplateVersion { stored: u32, fpmcu: u32 },
+    #[error("the template is {stored} bytes, but the FPMCU uses {fpmcu} bytes")]
+    DifferentTemplateSize { stored: u32, fpmcu: u32 },
+}
+
+#[repr(C)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct StoredTemplateHeader {
+    magic: [u8; 4],
+    version: u32,
+    template_version: u32,
+    vendor_id: u32,
+    product_id: u32,
+    model_id: u32,
+    sensor_version: u32,
+    template_size: u32,
+    /// Seconds since the Unix epoch
+    created: u64,
+}
+
+/// What a template is compatible with, from the [`EcResponseFpInfo`] of the FPMCU which made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FpTemplateMetadata {
+    pub template_version: u32,
+    pub vendor_id: u32,
+    pub product_id: u32,
+    pub model_id: u32,
+    pub sensor_version: u32,
+    pub template_size: u32,
+    pub created: SystemTime,
+}
+
+impl FpTemplateMetadata {
+    pub fn new(fp_info: &EcResponseFpInfo) -> Self {
+        Self {
+            template_version: fp_info.template_version,
+            vendor_id: fp_info.vendor_id,
+            product_id: fp_info.product_id,
+            model_id: fp_info.model_id,
+            sensor_version: fp_info.version,
+            template_size: fp_info.template_size,
+            created: SystemTime::now(),
+        }
+    }
+
+    /// Checks that the template can be uploaded to the FPMCU with this info
+    pub fn check_compatible(&self, fp_info: &EcResponseFpInfo) -> Result<(), Error> {
+        if (
+            self.vendor_id,
+            self.product_id,
+            self.model_id,
+            self.sensor_version,
+        ) != (
+            fp_info.vendor_id,
+            fp_info.product_id,
+            fp_info.model_id,
+            fp_info.version,
+        ) {
+            return Err(Error::DifferentSensor);
+        }
+        if self.template_version != fp_info.template_version {
+            return Err(Error::DifferentTemplateVersion {
+                stored: self.template_version,
+                fpmcu: fp_info.template_version,
+            });
+        }
+        if self.template_size != fp_info.template_size {
+            return Err(Error::DifferentTemplateSize {
+                stored: self.template_size,
+                fpmcu: fp_info.template_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+pub struct StoredTemplate {
+    pub metadata: FpTemplateMetadata,
+    pub template: FpTemplate,
+}
+
+/// Stores templates in a directory for each user, with a file for each finger
+pub struct FpTemplateStore {
+    root: PathBuf,
+}
+
+impl FpTemplateStore {
+    /// `root` is created when the first template is saved
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn user_dir(&self, user_id: &UserId) -> PathBuf {
+        let user_id: String = user_id.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.root.join(user_id)
+    }
+
+    fn template_path(&self, user_id: &UserId, finger: &str) -> Result<PathBuf, Error> {
+        if finger.is_empty() || finger.starts_with('.') || finger.contains(['/', '\0']) {
+            return Err(Error::InvalidFingerName(finger.to_owned()));
+        }
+        Ok(self
+            .user_dir(user_id)
+            .join(format!("{finger}.{TEMPLATE_EXTENSION}")))
+    }
+
+    /// Saves a template which was downloaded from the FPMCU with this info, replacing the finger's old template.
+    /// The file is only readable by the current user.
+    pub fn save(
+        &self,
+        user_id: &UserId,
+        finger: &str,
+        template: &FpTemplate,
+        fp_info: &EcResponseFpInfo,
+    ) -> Result<(), Error> {
+        let path = self.template_path(user_id, finger)?;
+        let metadata = FpTemplateMetadata::new(fp_info);
+        let header = StoredTemplateHeader {
+            magic: STORED_TEMPLATE_MAGIC,
+            version: STORED_TEMPLATE_VERSION,
+            template_version: metadata.template_version,
+            vendor_id: metadata.vendor_id,
+            product_id: metadata.product_id,
+            model_id: metadata.model_id,
+            sensor_version: metadata.sensor_version,
+            template_size: template.buffer().len() as u32,
+            created: metadata
+                .created
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        fs::create_dir_all(self.user_dir(user_id))?;
+        // Write to a temporary file and then rename it, so the old template isn't lost if writing fails
+        let temp_path = path.with_extension("tmp");
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&temp_path)?;
+        file.write_all(bytes_of(&header))?;
+        file.write_all(template.buffer())?;
+        file.sync_all()?;
+        fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    fn read(path: &Path) -> Result<(FpTemplateMetadata, Vec<u8>), Error> {
+        let data = fs::read(path)?;
+        let invalid_file = || Error::InvalidFile(path.to_owned());
+        if data.len() < size_of::<StoredTemplateHeader>() {
+            return Err(invalid_file());
+        }
+        let (header, template) = data.split_at(size_of::<StoredTemplateHeader>());
+        let header: StoredTemplateHeader = pod_read_unaligned(header);
+        if header.magic != STORED_TEMPLATE_MAGIC
+            || header.version != STORED_TEMPLATE_VERSION
+            || header.template_size as usize != template.len()
+        {
+            return Err(invalid_file());
+        }
+        let metadata = FpTemplateMetadata {
+            template_version: header.template_version,
+            vendor_id: header.vendor_id,
+            product_id: header.product_id,
+            model_id: header.model_id,
+            sensor_version: header.sensor_version,
+            template_size: header.template_size,
+            created: SystemTime::UNIX_EPOCH + Duration::from_secs(header.created),
+        };
+        Ok((metadata, template.to_vec()))
+    }
+
+    /// Loads a template, and checks that it can be uploaded to the FPMCU with this info
+    pub fn load(
+        &self,
+        user_id: &UserId,
+        finger: &str,
+        fp_info: &EcResponseFpInfo,
+    ) -> Result<StoredTemplate, Error> {
+        let (metadata, template) = Self::read(&self.template_path(user_id, finger)?)?;
+        metadata.check_compatible(fp_info)?;
+        Ok(StoredTemplate {
+            metadata,
+            // Safe because the template was made by the same kind of FPMCU with the same template format.
+            // The FPMCU rejects templates which were encrypted with a different seed or user ID.
+            template: unsafe { FpTemplate::from_vec_unchecked(template) },
+        })
+    }
+
+    /// The fingers which have a template saved for the user, sorted by name
+    pub fn fingers(&self, user_id: &UserId) -> Result<Vec<String>, Error> {
+        let entries = match fs::read_dir(self.user_dir(user_id)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut fingers = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext == TEMPLATE_EXTENSION)
+            {
+                if let Some(finger) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    fingers.push(finger.to_owned());
+                }
+            }
+        }
+        fingers.sort();
+        Ok(fingers)
+    }
+
+    /// Loads all of the user's templates which can be uploaded to the FPMCU with this info.
+    /// Fails if any template is incompatible, so templates aren't silently ignored.
+    pub fn load_all(
+        &self,
+        user_id: &UserId,
+        fp_info: &EcResponseFpInfo,
+    ) -> Result<Vec<(String, StoredTemplate)>, Error> {
+        self.fingers(user_id)?
+            .into_iter()
+            .map(|finger| {
+                let template = self.load(user_id, &finger, fp_info)?;
+                Ok((finger, template))
+            })
+            .collect()
+    }
+
+    pub fn delete(&self, user_id: &UserId, finger: &str) -> Result<(), Error> {
+        fs::remove_file(self.template_path(user_id, finger)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use bytemuck::Zeroable;
+
+    use super::*;
+
+    const USER_ID: UserId = [0xab; 32];
+
+    /// A store in a new directory, which is deleted when the test is done
+    struct TestStore {
+        store: FpTemplateStore,
+    }
+
+    impl TestStore {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("crosec-fp-store-{}-{name}", process::id()));
+            let _ = fs::remove_dir_all(&root);
+            Self {
+                store: FpTemplateStore::new(root),
+            }
+        }
+
+        fn template_path(&self, finger: &str) -> PathBuf {
+            self.store.template_path(&USER_ID, finger).unwrap()
+        }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.store.root);
+        }
+    }
+
+    fn fp_info() -> EcResponseFpInfo {
+        EcResponseFpInfo {
+            vendor_id: 0x4649_4e47,
+            product_id: 9,
+            model_id: 0x5216,
+            version: 1,
+            template_size: 16,
+            template_max: 5,
+            template_version: 4,
+            ..Zeroable::zeroed()
+        }
+    }
+
+    fn template() -> FpTemplate {
+        unsafe { FpTemplate::from_vec_unchecked((0..16).collect()) }
+    }
+
+    #[test]
+    fn round_trip() {
+        let test = TestStore::new("round-trip");
+        let fp_info = fp_info();
+        assert!(test.store.fingers(&USER_ID).unwrap().is_empty());
+        test.store
+            .save(&USER_ID, "right-thumb", &template(), &fp_info)
+            .unwrap();
+        test.store
+            .save(&USER_ID, "left-index", &template(), &fp_info)
+            .unwrap();
+        assert_eq!(
+            test.store.fingers(&USER_ID).unwrap(),
+            ["left-index", "right-thumb"]
+        );
+        let stored = test.store.load(&USER_ID, "left-index", &fp_info).unwrap();
+        assert_eq!(stored.template.buffer(), template().buffer());
+        assert_eq!(
+            FpTemplateMetadata {
+                created: stored.metadata.created,
+                ..FpTemplateMetadata::new(&fp_info)
+            },
+            stored.metadata
+        );
+        assert_eq!(test.store.load_all(&USER_ID, &fp_info).unwrap().len(), 2);
+        // Other users don't see the templates
+        assert!(test.store.fingers(&[0; 32]).unwrap().is_empty());
+
+        test.store.delete(&USER_ID, "left-index").unwrap();
+        assert_eq!(test.store.fingers(&USER_ID).unwrap(), ["right-thumb"]);
+    }
+
+    #[test]
+    fn different_sensor() {
+        let test = TestStore::new("different-sensor");
+        test.store
+            .save(&USER_ID, "left-index", &template(), &fp_info())
+            .unwrap();
+        let other_sensor = EcResponseFpInfo {
+            model_id: 0x5217,
+            ..fp_info()
+        };
+        assert!(matches!(
+            test.store.load(&USER_ID, "left-index", &other_sensor),
+            Err(Error::DifferentSensor)
+        ));
+        assert!(matches!(
+            test.store.load_all(&USER_ID, &other_sensor),
+            Err(Error::DifferentSensor)
+        ));
+    }
+
+    #[test]
+    fn different_template_version() {
+        let test = TestStore::new("different-template-version");
+        test.store
+            .save(&USER_ID, "left-index", &template(), &fp_info())
+            .unwrap();
+        let updated_firmware = EcResponseFpInfo {
+            template_version: 5,
+            ..fp_info()
+        };
+        assert!(matches!(
+            test.store.load(&USER_ID, "left-index", &updated_firmware),
+            Err(Error::DifferentTemplateVersion {
+                stored: 4,
+                fpmcu: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn invalid_files() {
+        let test = TestStore::new("invalid-files");
+        let fp_info = fp_info();
+        test.store
+            .save(&USER_ID, "left-index", &template(), &fp_info)
+            .unwrap();
+        let path = test.template_path("left-index");
+        let data = fs::read(&path).unwrap();
+
+        fs::write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(matches!(
+            test.store.load(&USER_ID, "left-index", &fp_info),
+            Err(Error::InvalidFile(_))
+        ));
+
+        fs::write(&path, &data[..size_of::<StoredTemplateHeader>() - 1]).unwrap();
+        assert!(matches!(
+            test.store.load(&USER_ID, "left-index", &fp_info),
+            Err(Error::InvalidFile(_))
+        ));
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        fs::write(&path, bad_magic).unwrap();
+        assert!(matches!(
+            test.store.load(&USER_ID, "left-index", &fp_info),
+            Err(Error::InvalidFile(_))
+        ));
+
+        // A raw template without the header
+        fs::write(&path, template().buffer()).unwrap();
+        assert!(matches!(
+            test.store.load(&USER_ID, "left-index", &fp_info),
+            Err(Error::InvalidFile(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_finger_names() {
+        let test = TestStore::new("invalid-finger-names");
+        for finger in ["", ".hidden", "../other-user", "a/b"] {
+            assert!(matches!(
+                test.store.save(&USER_ID, finger, &template(), &fp_info()),
+                Err(Error::InvalidFingerName(_))
+            ));
+        }
+    }
+}
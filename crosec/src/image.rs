@@ -0,0 +1,231 @@
+use std::fs::File;
+use std::mem::size_of;
+
+use bytemuck::{pod_read_unaligned, Pod, Zeroable};
+use strum_macros::{FromRepr, IntoStaticStr};
+use thiserror::Error;
+
+use crate::commands::get_protocol_info::get_protocol_info;
+use crate::commands::version::ec_cmd_version;
+use crate::fmap::{self, fmap_string, Fmap};
+use crate::EcError;
+
+pub const VB21_MAGIC_PACKED_KEY: u32 = 0x3262764b;
+pub const VB21_MAGIC_SIGNATURE: u32 = 0x32627653;
+/// The size of a key ID, which is the SHA-1 of the key
+pub const VB2_ID_NUM_BYTES: usize = 20;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid FMAP: {0}")]
+    Fmap(#[from] fmap::Error),
+    #[error("area {0} doesn't have a valid vb21 struct")]
+    InvalidVb21(&'static str),
+    #[error("EC command failed: {0}")]
+    Ec(#[from] EcError),
+}
+
+#[repr(u16)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vb2SignatureAlgorithm {
+    None,
+    Rsa1024,
+    Rsa2048,
+    Rsa4096,
+    Rsa8192,
+    Rsa2048Exp3,
+    Rsa3072Exp3,
+}
+
+#[repr(u16)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vb2HashAlgorithm {
+    None,
+    Sha1,
+    Sha256,
+    Sha512,
+    Sha224,
+    Sha384,
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct Vb21StructCommon {
+    magic: u32,
+    struct_version_major: u16,
+    struct_version_minor: u16,
+    total_size: u32,
+    fixed_size: u32,
+    desc_size: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct Vb21PackedKeyRaw {
+    c: Vb21StructCommon,
+    key_offset: u32,
+    key_size: u32,
+    sig_alg: u16,
+    hash_alg: u16,
+    key_version: u32,
+    id: [u8; VB2_ID_NUM_BYTES],
+}
+
+#[repr(C, packed)]
+#[derive(Pod, Zeroable, Clone, Copy)]
+struct Vb21SignatureRaw {
+    c: Vb21StructCommon,
+    sig_offset: u32,
+    sig_size: u32,
+    data_size: u32,
+    sig_alg: u16,
+    hash_alg: u16,
+    id: [u8; VB2_ID_NUM_BYTES],
+}
+
+/// The public key in RO which RW is verified with
+#[derive(Debug, Clone)]
+pub struct Vb21PackedKey {
+    pub description: String,
+    pub sig_alg: Result<Vb2SignatureAlgorithm, u16>,
+    pub hash_alg: Result<Vb2HashAlgorithm, u16>,
+    pub key_version: u32,
+    pub id: [u8; VB2_ID_NUM_BYTES],
+    pub key: Vec<u8>,
+}
+
+/// The signature of the RW image
+#[derive(Debug, Clone)]
+pub struct Vb21Signature {
+    pub description: String,
+    pub sig_alg: Result<Vb2SignatureAlgorithm, u16>,
+    pub hash_alg: Result<Vb2HashAlgorithm, u16>,
+    /// The number of bytes of RW which are signed
+    pub data_size: u32,
+    /// The ID of the key which made the signature
+    pub id: [u8; VB2_ID_NUM_BYTES],
+    pub signature: Vec<u8>,
+}
+
+/// Checks the common header of a vb21 struct, and returns the description and the struct
+fn vb21_struct<Raw: Pod>(
+    data: &[u8],
+    magic: u32,
+    area: &'static str,
+) -> Result<(Raw, String), Error> {
+    let raw = data
+        .get(..size_of::<Raw>())
+        .ok_or(Error::InvalidVb21(area))?;
+    let common: Vb21StructCommon = pod_read_unaligned(&raw[..size_of::<Vb21StructCommon>()]);
+    if common.magic != magic {
+        return Err(Error::InvalidVb21(area));
+    }
+    let description = data
+        .get(common.fixed_size as usize..common.fixed_size as usize + common.desc_size as usize)
+        .ok_or(Error::InvalidVb21(area))?;
+    Ok((pod_read_unaligned(raw), fmap_string(description)))
+}
+
+fn vb21_data<'a>(
+    data: &'a [u8],
+    offset: u32,
+    size: u32,
+    area: &'static str,
+) -> Result<&'a [u8], Error> {
+    data.get(offset as usize..offset as usize + size as usize)
+        .ok_or(Error::InvalidVb21(area))
+}
+
+impl Vb21PackedKey {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        const AREA: &str = "KEY_RO";
+        let (raw, description) =
+            vb21_struct::<Vb21PackedKeyRaw>(data, VB21_MAGIC_PACKED_KEY, AREA)?;
+        Ok(Self {
+            description,
+            sig_alg: Vb2SignatureAlgorithm::from_repr(raw.sig_alg).ok_or(raw.sig_alg),
+            hash_alg: Vb2HashAlgorithm::from_repr(raw.hash_alg).ok_or(raw.hash_alg),
+            key_version: raw.key_version,
+            id: raw.id,
+            key: vb21_data(data, raw.key_offset, raw.key_size, AREA)?.to_vec(),
+        })
+    }
+}
+
+impl Vb21Signature {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        const AREA: &str = "SIG_RW";
+        let (raw, description) = vb21_struct::<Vb21SignatureRaw>(data, VB21_MAGIC_SIGNATURE, AREA)?;
+        Ok(Self {
+            description,
+            sig_alg: Vb2SignatureAlgorithm::from_repr(raw.sig_alg).ok_or(raw.sig_alg),
+            hash_alg: Vb2HashAlgorithm::from_repr(raw.hash_alg).ok_or(raw.hash_alg),
+            data_size: raw.data_size,
+            id: raw.id,
+            signature: vb21_data(data, raw.sig_offset, raw.sig_size, AREA)?.to_vec(),
+        })
+    }
+}
+
+/// The info in an EC image which can be found without running it
+#[derive(Debug, Clone)]
+pub struct EcImageInfo {
+    pub fmap: Fmap,
+    /// The same as `version_string_ro` from the version command
+    pub ro_version: Option<String>,
+    /// The same as `version_string_rw` from the version command
+    pub rw_version: Option<String>,
+    pub key: Option<Vb21PackedKey>,
+    pub signature: Option<Vb21Signature>,
+}
+
+/// Whether the versions in an image match the EC's
+#[derive(Debug, Clone)]
+pub struct VersionComparison {
+    pub ec_ro_version: String,
+    pub ec_rw_version: String,
+    pub ro_matches: bool,
+    pub rw_matches: bool,
+}
+
+impl EcImageInfo {
+    /// Parses an EC image, such as `ec.bin`. Areas which the image doesn't have are `None`.
+    pub fn parse(image: &[u8]) -> Result<Self, Error> {
+        let fmap = Fmap::find(image)?;
+        let area = |name: &str| match fmap.area_data(image, name) {
+            Ok(data) => Ok(Some(data)),
+            Err(fmap::Error::MissingArea(_)) => Ok(None),
+            Err(e) => Err(e),
+        };
+        let ro_version = area("RO_FRID")?.map(fmap_string);
+        let rw_version = area("RW_FWID")?.map(fmap_string);
+        let key = area("KEY_RO")?.map(Vb21PackedKey::parse).transpose()?;
+        let signature = area("SIG_RW")?.map(Vb21Signature::parse).transpose()?;
+        Ok(Self {
+            fmap,
+            ro_version,
+            rw_version,
+            key,
+            signature,
+        })
+    }
+
+    /// The RW image was signed with the key in RO. This only checks the key ID and doesn't verify the signature.
+    pub fn signed_with_ro_key(&self) -> Option<bool> {
+        Some(self.key.as_ref()?.id == self.signature.as_ref()?.id)
+    }
+
+    /// Compares the versions in the image with the versions the EC reports
+    pub fn compare_with_ec(&self, file: &mut File) -> Result<VersionComparison, Error> {
+        let protocol_info = get_protocol_info(file)?;
+        let (ec_ro_version, ec_rw_version, ..) = ec_cmd_version(file, &protocol_info)?;
+        let ec_ro_version = fmap_string(ec_ro_version.as_bytes());
+        let ec_rw_version = fmap_string(ec_rw_version.as_bytes());
+        Ok(VersionComparison {
+            ro_matches: self.ro_version.as_ref() == Some(&ec_ro_version),
+            rw_matches: self.rw_version.as_ref() == Some(&ec_rw_version),
+            ec_ro_version,
+            ec_rw_version,
+        })
+    }
+}
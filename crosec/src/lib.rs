@@ -4,13 +4,53 @@ use nix::errno::Errno;
 use num_derive::FromPrimitive;
 use thiserror::Error;
 
+/// Defines `$name`, a set of `$flag`s stored in a `$bits`.
+/// `$flag` must be an enum of single bits which derives `EnumIter`.
+macro_rules! flag_set {
+    ($name:ident, $flag:ident, $bits:ty) => {
+        #[doc = concat!("A set of [`", stringify!($flag), "`]s")]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct $name(pub $bits);
+
+        impl $name {
+            pub fn contains(&self, flag: $flag) -> bool {
+                self.0 & flag as $bits != 0
+            }
+
+            /// The flags which are set. Unknown bits are skipped.
+            pub fn iter(&self) -> impl Iterator<Item = $flag> + '_ {
+                <$flag as strum::IntoEnumIterator>::iter().filter(|flag| self.contains(*flag))
+            }
+        }
+
+        impl FromIterator<$flag> for $name {
+            fn from_iter<T: IntoIterator<Item = $flag>>(iter: T) -> Self {
+                Self(
+                    iter.into_iter()
+                        .fold(0, |flags, flag| flags | flag as $bits),
+                )
+            }
+        }
+    };
+}
+
 pub mod battery;
 pub mod commands;
 pub mod console;
 pub mod ec_command;
+pub mod flash_rw;
+pub mod fmap;
+pub mod fp;
 pub mod get_number_of_fans;
+pub mod image;
+pub mod motion_sense_fifo;
+pub mod panic_info;
+pub mod pd_log;
 pub mod read_mem_any;
 pub mod read_mem_string;
+pub mod reboot;
+pub mod switches;
+pub mod uart_console;
 pub mod wait_event;
 
 #[derive(FromPrimitive, Debug, Copy, Clone)]
@@ -57,8 +97,12 @@ pub const EC_FAN_SPEED_ENTRIES: usize = 4;
 pub const EC_FAN_SPEED_NOT_PRESENT: u16 = 0xffff;
 pub const EC_FAN_SPEED_STALLED: u16 = 0xfffe;
 pub const EC_MEM_MAP_MAX_TEXT_SIZE: usize = 8;
+/// The number of columns in the keyboard matrix. Each scan of the matrix is one byte per column.
+pub const KEYBOARD_COLS_MAX: usize = 13;
 
 pub const EC_MEM_MAP_FAN: u8 = 0x10;
+/// Switch flags (8-bit)
+pub const EC_MEM_MAP_SWITCHES: u8 = 0x30;
 /// Version of data in 0x40 - 0x7f
 pub const EC_MEM_MAP_BATTERY_VERSION: u8 = 0x24;
 /// Battery Present Voltage
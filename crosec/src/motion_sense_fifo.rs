@@ -0,0 +1,525 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+
+use bytemuck::pod_read_unaligned;
+
+use crate::commands::get_protocol_info::{get_protocol_info, EcResponseGetProtocolInfo};
+use crate::commands::motion_sense::{
+    motion_sense_fifo_info, motion_sense_fifo_int_enable, motion_sense_fifo_read,
+    motion_sense_info, motion_sense_sensor_count, EcResponseMotionSensorData, MotionSensorLocation,
+    MotionSensorType, MOTIONSENSE_SENSOR_FLAG_FLUSH, MOTIONSENSE_SENSOR_FLAG_ODR,
+    MOTIONSENSE_SENSOR_FLAG_TABLET_MODE, MOTIONSENSE_SENSOR_FLAG_TIMESTAMP,
+    MOTIONSENSE_SENSOR_FLAG_WAKEUP,
+};
+use crate::wait_event::event::{EcMkbpEvent, EcMkbpEventType};
+use crate::wait_event::{wait_event_sync, Error, PollData};
+use crate::EcError;
+
+/// The hinge is along the x axis of the standard reference frame on most devices
+pub const DEFAULT_HINGE_AXIS: [f32; 3] = [1.0, 0.0, 0.0];
+
+/// A sample from the FIFO with its timestamp
+#[derive(Debug, Clone, Copy)]
+pub struct MotionSenseSample {
+    pub sensor_num: u8,
+    /// The EC timestamp in microseconds. Unlike the timestamps in the FIFO, this doesn't wrap around.
+    pub timestamp_us: u64,
+    /// See `MOTIONSENSE_SENSOR_FLAG_*`
+    pub flags: u8,
+    pub data: [i16; 3],
+}
+
+impl MotionSenseSample {
+    /// This sample is a marker for a FIFO flush and doesn't have any data
+    pub fn is_flush(&self) -> bool {
+        self.flags & MOTIONSENSE_SENSOR_FLAG_FLUSH != 0
+    }
+
+    /// This sample is a marker for a change of the sensor's ODR and doesn't have any data
+    pub fn odr_changed(&self) -> bool {
+        self.flags & MOTIONSENSE_SENSOR_FLAG_ODR != 0
+    }
+
+    pub fn is_wakeup(&self) -> bool {
+        self.flags & MOTIONSENSE_SENSOR_FLAG_WAKEUP != 0
+    }
+
+    pub fn is_tablet_mode(&self) -> bool {
+        self.flags & MOTIONSENSE_SENSOR_FLAG_TABLET_MODE != 0
+    }
+}
+
+/// Turns FIFO entries into timestamped samples. This doesn't talk to the EC, so it can decode recorded FIFO data.
+///
+/// The EC puts a timestamp entry in the FIFO before the samples it applies to.
+/// Samples which come before the first timestamp are dropped because their time is unknown.
+#[derive(Debug, Clone, Default)]
+pub struct MotionSenseFifoDecoder {
+    timestamp_us: Option<u64>,
+    /// The last timestamp of each sensor, which is what the next timestamp of the sensor is unwrapped against
+    sensor_timestamps_us: HashMap<u8, u64>,
+    lost: Vec<u64>,
+}
+
+impl MotionSenseFifoDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The EC timestamp is 32 bits, so it wraps around about every 71 minutes.
+    /// Timestamps of different sensors can be slightly out of order, so a timestamp is only counted as wrapped
+    /// if it is more than half of the range away from the sensor's last timestamp, like the kernel does.
+    fn unwrap_timestamp(&mut self, sensor_num: u8, raw_timestamp: u32) -> u64 {
+        let previous = self
+            .sensor_timestamps_us
+            .get(&sensor_num)
+            .copied()
+            .or(self.timestamp_us);
+        let timestamp_us = match previous {
+            Some(previous) => {
+                let previous_low32 = previous as u32;
+                let timestamp_us = (previous & !(u32::MAX as u64)) | raw_timestamp as u64;
+                if raw_timestamp < previous_low32
+                    && previous_low32.wrapping_sub(raw_timestamp) > u32::MAX / 2
+                {
+                    timestamp_us + (1 << 32)
+                } else if raw_timestamp > previous_low32
+                    && raw_timestamp.wrapping_sub(previous_low32) > u32::MAX / 2
+                {
+                    // From before a wrap which another timestamp already crossed
+                    timestamp_us.saturating_sub(1 << 32)
+                } else {
+                    timestamp_us
+                }
+            }
+            None => raw_timestamp as u64,
+        };
+        self.sensor_timestamps_us.insert(sensor_num, timestamp_us);
+        timestamp_us
+    }
+
+    /// Returns a sample if the entry is sensor data or a flush or ODR change marker,
+    /// or `None` if it is only a timestamp
+    pub fn decode(&mut self, entry: &EcResponseMotionSensorData) -> Option<MotionSenseSample> {
+        let data = match entry.timestamp() {
+            Some(raw_timestamp) => {
+                self.timestamp_us = Some(self.unwrap_timestamp(entry.sensor_num, raw_timestamp));
+                // Flushes and ODR changes are timestamp entries with the flag set, and don't have any data
+                if entry.flags & (MOTIONSENSE_SENSOR_FLAG_FLUSH | MOTIONSENSE_SENSOR_FLAG_ODR) == 0
+                {
+                    return None;
+                }
+                [0; 3]
+            }
+            None => entry.data,
+        };
+        Some(MotionSenseSample {
+            sensor_num: entry.sensor_num,
+            timestamp_us: self.timestamp_us?,
+            flags: entry.flags & !MOTIONSENSE_SENSOR_FLAG_TIMESTAMP,
+            data,
+        })
+    }
+
+    /// Decodes FIFO entries which are stored back to back, such as in a recording of FIFO reads
+    pub fn decode_bytes(&mut self, bytes: &[u8]) -> Vec<MotionSenseSample> {
+        bytes
+            .chunks_exact(size_of::<EcResponseMotionSensorData>())
+            .map(pod_read_unaligned::<EcResponseMotionSensorData>)
+            .filter_map(|entry| self.decode(&entry))
+            .collect()
+    }
+
+    /// Adds the per-sensor lost counts from FIFO info, which the EC resets every time it is read
+    pub fn add_lost(&mut self, lost: &[u16]) {
+        if self.lost.len() < lost.len() {
+            self.lost.resize(lost.len(), 0);
+        }
+        for (total, lost) in self.lost.iter_mut().zip(lost) {
+            *total += *lost as u64;
+        }
+    }
+
+    /// The total number of samples lost because the FIFO was full, indexed by sensor number
+    pub fn lost(&self) -> &[u64] {
+        &self.lost
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Removes the part of `v` which is along the hinge
+fn project(v: [f32; 3], hinge: [f32; 3]) -> [f32; 3] {
+    let along = dot(v, hinge) / dot(hinge, hinge);
+    [
+        v[0] - along * hinge[0],
+        v[1] - along * hinge[1],
+        v[2] - along * hinge[2],
+    ]
+}
+
+/// Calculates the lid angle in degrees (0 is closed, 180 is flat, 360 is tablet mode) from the base and lid accelerometer readings,
+/// which must be in the standard reference frame like the EC reports them.
+///
+/// Returns `None` if the angle can't be calculated reliably, which happens when the hinge is close to vertical.
+pub fn lid_angle(base: [i16; 3], lid: [i16; 3], hinge: [f32; 3]) -> Option<f32> {
+    let base = base.map(f32::from);
+    let lid = lid.map(f32::from);
+    let base_projection = project(base, hinge);
+    let lid_projection = project(lid, hinge);
+    let base_length = dot(base_projection, base_projection).sqrt();
+    let lid_length = dot(lid_projection, lid_projection).sqrt();
+    // If most of gravity is along the hinge, the angle around the hinge is mostly noise
+    if base_length < dot(base, base).sqrt() * 0.3 || lid_length < dot(lid, lid).sqrt() * 0.3 {
+        return None;
+    }
+    let cos = (dot(base_projection, lid_projection) / (base_length * lid_length)).clamp(-1.0, 1.0);
+    // When the lid is closed, it is upside down compared to the base, so the readings point in opposite directions
+    let angle = 180.0 - cos.acos().to_degrees();
+    if dot(cross(base_projection, lid_projection), hinge) > 0.0 {
+        Some(360.0 - angle)
+    } else {
+        Some(angle)
+    }
+}
+
+/// Keeps track of the lid angle using the latest samples from the base and lid accelerometers
+#[derive(Debug, Clone)]
+pub struct LidAngleTracker {
+    pub base_sensor: u8,
+    pub lid_sensor: u8,
+    pub hinge: [f32; 3],
+    base: Option<[i16; 3]>,
+    lid: Option<[i16; 3]>,
+}
+
+impl LidAngleTracker {
+    pub fn new(base_sensor: u8, lid_sensor: u8) -> Self {
+        Self {
+            base_sensor,
+            lid_sensor,
+            hinge: DEFAULT_HINGE_AXIS,
+            base: None,
+            lid: None,
+        }
+    }
+
+    /// Finds the base and lid accelerometers. Returns `None` if the device doesn't have both.
+    pub fn from_sensors<File: AsRawFd>(file: &mut File) -> Result<Option<Self>, EcError> {
+        let mut base_sensor = None;
+        let mut lid_sensor = None;
+        for sensor_num in 0..motion_sense_sensor_count(file)? {
+            let info = motion_sense_info(file, 1, sensor_num)?;
+            if info.sensor_type == Ok(MotionSensorType::Accel) {
+                match info.location {
+                    Ok(MotionSensorLocation::Base) => base_sensor = Some(sensor_num),
+                    Ok(MotionSensorLocation::Lid) => lid_sensor = Some(sensor_num),
+                    _ => {}
+                }
+            }
+        }
+        Ok(base_sensor
+            .zip(lid_sensor)
+            .map(|(base_sensor, lid_sensor)| Self::new(base_sensor, lid_sensor)))
+    }
+
+    /// Returns the new lid angle if the sample is from the base or lid accelerometer
+    pub fn update(&mut self, sample: &MotionSenseSample) -> Option<f32> {
+        if sample.is_flush() || sample.odr_changed() {
+            return None;
+        }
+        if sample.sensor_num == self.base_sensor {
+            self.base = Some(sample.data);
+        } else if sample.sensor_num == self.lid_sensor {
+            self.lid = Some(sample.data);
+        } else {
+            return None;
+        }
+        self.angle()
+    }
+
+    /// The lid angle from the latest samples
+    pub fn angle(&self) -> Option<f32> {
+        lid_angle(self.base?, self.lid?, self.hinge)
+    }
+}
+
+/// An iterator over samples from the motion sense FIFO.
+/// Every time the EC sends a `SensorFifo` event, the FIFO is read until it is empty.
+pub struct MotionSenseFifo<'a, File: AsRawFd + Read> {
+    file: &'a mut File,
+    protocol_info: EcResponseGetProtocolInfo,
+    sensor_count: u8,
+    decoder: MotionSenseFifoDecoder,
+    samples: VecDeque<MotionSenseSample>,
+    fifo_empty: bool,
+    timeout: Option<i32>,
+}
+
+impl<'a, File: AsRawFd + Read> MotionSenseFifo<'a, File> {
+    /// Enables the FIFO event. If `timeout` is specified in milliseconds, the iterator ends when no event comes in time.
+    pub fn new(file: &'a mut File, timeout: Option<i32>) -> Result<Self, EcError> {
+        let protocol_info = get_protocol_info(file)?;
+        let sensor_count = motion_sense_sensor_count(file)?;
+        motion_sense_fifo_int_enable(file, Some(true))?;
+        Ok(Self {
+            file,
+            protocol_info,
+            sensor_count,
+            decoder: Default::default(),
+            samples: Default::default(),
+            fifo_empty: false,
+            timeout,
+        })
+    }
+
+    /// The total number of samples lost because the FIFO was full, indexed by sensor number
+    pub fn lost(&self) -> &[u64] {
+        self.decoder.lost()
+    }
+
+    fn read_fifo(&mut self) -> Result<(), EcError> {
+        let entries = motion_sense_fifo_read(self.file, &self.protocol_info)?;
+        self.fifo_empty = entries.is_empty();
+        self.samples.extend(
+            entries
+                .iter()
+                .filter_map(|entry| self.decoder.decode(entry)),
+        );
+        Ok(())
+    }
+}
+
+impl<'a, File: AsRawFd + Read> Iterator for MotionSenseFifo<'a, File> {
+    type Item = Result<MotionSenseSample, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sample) = self.samples.pop_front() {
+                return Some(Ok(sample));
+            }
+            if !self.fifo_empty {
+                if let Err(e) = self.read_fifo() {
+                    return Some(Err(e.into()));
+                }
+                continue;
+            }
+            match wait_event_sync(self.file, [EcMkbpEventType::SensorFifo], self.timeout) {
+                Ok(PollData::EventHappened(EcMkbpEvent::SensorFifo(_))) => {
+                    match motion_sense_fifo_info(self.file, self.sensor_count) {
+                        Ok(fifo_info) => self.decoder.add_lost(&fifo_info.lost),
+                        Err(e) => return Some(Err(e.into())),
+                    }
+                    self.fifo_empty = false;
+                }
+                Ok(PollData::Timeout) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(Error::WaitEvent(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes a recording and returns the sensor number, timestamp, flags, and data of each sample
+    fn decode(recording: &[u8]) -> Vec<(u8, u64, u8, [i16; 3])> {
+        MotionSenseFifoDecoder::new()
+            .decode_bytes(recording)
+            .into_iter()
+            .map(|sample| {
+                (
+                    sample.sensor_num,
+                    sample.timestamp_us,
+                    sample.flags,
+                    sample.data,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timestamp_wraps() {
+        #[rustfmt::skip]
+        let recording = [
+            // Timestamp 0xffffff00 for sensor 0
+            0x02, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff,
+            // Sensor 0: 1, 2, 3
+            0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00,
+            // Timestamp 0x00000100 for sensor 0, after the wrap
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            // Sensor 0: -1, -2, -3
+            0x00, 0x00, 0xff, 0xff, 0xfe, 0xff, 0xfd, 0xff,
+        ];
+        assert_eq!(
+            decode(&recording),
+            [
+                (0, 0xffffff00, 0, [1, 2, 3]),
+                (0, 0x1_00000100, 0, [-1, -2, -3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamps_out_of_order_across_sensors() {
+        #[rustfmt::skip]
+        let recording = [
+            // Timestamp 1000 for sensor 0
+            0x02, 0x00, 0x00, 0x00, 0xe8, 0x03, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Timestamp 990 for sensor 1, which is earlier than sensor 0's
+            0x02, 0x01, 0x00, 0x00, 0xde, 0x03, 0x00, 0x00,
+            0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Timestamp 1010 for sensor 0
+            0x02, 0x00, 0x00, 0x00, 0xf2, 0x03, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            decode(&recording),
+            [
+                (0, 1000, 0, [1, 0, 0]),
+                (1, 990, 0, [2, 0, 0]),
+                (0, 1010, 0, [3, 0, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamps_out_of_order_around_wrap() {
+        #[rustfmt::skip]
+        let recording = [
+            // Timestamp 0xfffffff0 for sensor 0
+            0x02, 0x00, 0x00, 0x00, 0xf0, 0xff, 0xff, 0xff,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Timestamp 0x10 for sensor 0, after the wrap
+            0x02, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Timestamp 0xfffffff8 for sensor 1, from before the wrap
+            0x02, 0x01, 0x00, 0x00, 0xf8, 0xff, 0xff, 0xff,
+            0x00, 0x01, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Timestamp 0x20 for sensor 1, after the wrap
+            0x02, 0x01, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00,
+            0x00, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            decode(&recording),
+            [
+                (0, 0xfffffff0, 0, [1, 0, 0]),
+                (0, 0x1_00000010, 0, [2, 0, 0]),
+                (1, 0xfffffff8, 0, [3, 0, 0]),
+                (1, 0x1_00000020, 0, [4, 0, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamp_only_entries() {
+        #[rustfmt::skip]
+        let recording = [
+            // Sensor 0 before any timestamp, which is dropped
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Timestamps 100 and 200 for sensor 0 without any samples
+            0x02, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00, 0xc8, 0x00, 0x00, 0x00,
+            // Sensor 0 with the wakeup flag
+            0x04, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            decode(&recording),
+            [(0, 200, MOTIONSENSE_SENSOR_FLAG_WAKEUP, [2, 0, 0])]
+        );
+    }
+
+    #[test]
+    fn odr_change_and_flush() {
+        #[rustfmt::skip]
+        let recording = [
+            0x02, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // ODR change of sensor 0 at 150
+            0x12, 0x00, 0x00, 0x00, 0x96, 0x00, 0x00, 0x00,
+            // Flush of sensor 1 at 160
+            0x03, 0x01, 0x00, 0x00, 0xa0, 0x00, 0x00, 0x00,
+            // Sensor 0 still has the timestamp of the last timestamp entry
+            0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let samples = MotionSenseFifoDecoder::new().decode_bytes(&recording);
+        assert_eq!(samples.len(), 4);
+        assert!(samples[1].odr_changed() && !samples[1].is_flush());
+        assert!(samples[2].is_flush() && !samples[2].odr_changed());
+        assert_eq!(
+            decode(&recording),
+            [
+                (0, 100, 0, [1, 0, 0]),
+                (0, 150, MOTIONSENSE_SENSOR_FLAG_ODR, [0, 0, 0]),
+                (1, 160, MOTIONSENSE_SENSOR_FLAG_FLUSH, [0, 0, 0]),
+                (0, 160, 0, [2, 0, 0]),
+            ]
+        );
+    }
+
+    /// About 1g in the units the EC reports accelerometer readings in
+    const G: i16 = 1024;
+
+    fn assert_angle(angle: Option<f32>, expected: f32) {
+        let angle = angle.unwrap();
+        assert!((angle - expected).abs() < 0.01, "{angle} isn't {expected}");
+    }
+
+    #[test]
+    fn lid_angles() {
+        let base = [0, 0, G];
+        // Closed, so the lid is upside down
+        assert_angle(lid_angle(base, [0, 0, -G], DEFAULT_HINGE_AXIS), 0.0);
+        assert_angle(lid_angle(base, [0, G, 0], DEFAULT_HINGE_AXIS), 90.0);
+        assert_angle(lid_angle(base, [0, G, -G], DEFAULT_HINGE_AXIS), 45.0);
+        assert_angle(lid_angle(base, [0, G, G], DEFAULT_HINGE_AXIS), 135.0);
+        assert_angle(lid_angle(base, [0, 0, G], DEFAULT_HINGE_AXIS), 180.0);
+        assert_angle(lid_angle(base, [0, -G, 0], DEFAULT_HINGE_AXIS), 270.0);
+    }
+
+    #[test]
+    fn lid_angle_with_vertical_hinge() {
+        // Gravity is almost along the hinge, so the readings around it are noise
+        assert_eq!(
+            lid_angle([G, 0, 100], [G, 100, 0], DEFAULT_HINGE_AXIS),
+            None
+        );
+    }
+
+    #[test]
+    fn lid_angle_tracker() {
+        let sample = |sensor_num, flags, data| MotionSenseSample {
+            sensor_num,
+            timestamp_us: 0,
+            flags,
+            data,
+        };
+        let mut tracker = LidAngleTracker::new(0, 1);
+        assert_eq!(tracker.update(&sample(0, 0, [0, 0, G])), None);
+        // Other sensors are ignored
+        assert_eq!(tracker.update(&sample(2, 0, [0, G, 0])), None);
+        assert_angle(tracker.update(&sample(1, 0, [0, G, 0])), 90.0);
+        // Markers don't have data, so they don't change the angle
+        assert_eq!(
+            tracker.update(&sample(1, MOTIONSENSE_SENSOR_FLAG_FLUSH, [0; 3])),
+            None
+        );
+        assert_angle(tracker.angle(), 90.0);
+        assert_angle(tracker.update(&sample(0, 0, [0, G, 0])), 180.0);
+    }
+}
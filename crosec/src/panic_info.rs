@@ -0,0 +1,787 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, FromRepr, IntoStaticStr};
+use thiserror::Error;
+
+use crate::commands::get_panic_info::ec_cmd_get_panic_info;
+use crate::EcError;
+
+/// "Pnc!" in little endian
+pub const PANIC_DATA_MAGIC: u32 = 0x21636e50;
+/// Software panic reasons start at this value
+pub const PANIC_SW_BASE: u32 = 0xdead6660;
+
+const HEADER_SIZE: usize = 4;
+/// `struct_size` and `magic`
+const TRAILER_SIZE: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("EC command failed: {0}")]
+    Ec(#[from] EcError),
+    #[error("the panic data doesn't end with the magic number")]
+    InvalidMagic,
+    #[error("the panic data is too small for architecture {0:?}")]
+    Truncated(PanicArch),
+}
+
+#[repr(u8)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicArch {
+    CortexM = 1,
+    Nds32N8 = 2,
+    X86 = 3,
+    RiscvRv32i = 4,
+}
+
+#[repr(u8)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicDataFlag {
+    /// The exception frame is valid
+    FrameValid = 1 << 0,
+    /// The panic data was already printed on the console
+    OldConsole = 1 << 1,
+    /// The panic data was already read with a host command
+    OldHostcmd = 1 << 2,
+    /// The panic host event was already sent
+    OldHostevent = 1 << 3,
+    /// The panic data was truncated to fit in the space reserved for it
+    Truncated = 1 << 4,
+}
+
+flag_set!(PanicDataFlags, PanicDataFlag, u8);
+
+/// Why the EC firmware called `software_panic`, as opposed to panicking because of an exception
+#[repr(u32)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftwarePanicReason {
+    DivZero = PANIC_SW_BASE,
+    StackOverflow,
+    PdCrash,
+    Assert,
+    Watchdog,
+    Rng,
+    PmicFault,
+    Exit,
+    WatchdogWarn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoftwarePanic {
+    pub reason: SoftwarePanicReason,
+    /// Extra info which depends on the reason, such as the line number of an assert
+    pub info: u32,
+}
+
+/// The exception number in IPSR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CortexMException {
+    /// Not in an exception
+    Thread,
+    Reset,
+    Nmi,
+    HardFault,
+    MemManage,
+    BusFault,
+    UsageFault,
+    SvCall,
+    DebugMonitor,
+    PendSv,
+    SysTick,
+    Irq(u16),
+    Reserved(u16),
+}
+
+impl From<u32> for CortexMException {
+    fn from(ipsr: u32) -> Self {
+        match (ipsr & 0x1ff) as u16 {
+            0 => Self::Thread,
+            1 => Self::Reset,
+            2 => Self::Nmi,
+            3 => Self::HardFault,
+            4 => Self::MemManage,
+            5 => Self::BusFault,
+            6 => Self::UsageFault,
+            11 => Self::SvCall,
+            12 => Self::DebugMonitor,
+            14 => Self::PendSv,
+            15 => Self::SysTick,
+            exception @ 16.. => Self::Irq(exception - 16),
+            exception => Self::Reserved(exception),
+        }
+    }
+}
+
+/// The bits of the Configurable Fault Status Register
+#[repr(u32)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CortexMCfsrFlag {
+    /// Instruction access violation
+    Iaccviol = 1 << 0,
+    /// Data access violation
+    Daccviol = 1 << 1,
+    /// Unstacking error while returning from an exception
+    Munstkerr = 1 << 3,
+    /// Stacking error while entering an exception
+    Mstkerr = 1 << 4,
+    /// Lazy floating point state preservation error
+    Mlsperr = 1 << 5,
+    /// MMFAR has the address of the memory management fault
+    Mmarvalid = 1 << 7,
+    /// Instruction bus error
+    Ibuserr = 1 << 8,
+    /// Precise data bus error
+    Preciserr = 1 << 9,
+    /// Imprecise data bus error
+    Impreciserr = 1 << 10,
+    /// Bus error while unstacking
+    Unstkerr = 1 << 11,
+    /// Bus error while stacking
+    Stkerr = 1 << 12,
+    /// Bus error during lazy floating point state preservation
+    Lsperr = 1 << 13,
+    /// BFAR has the address of the bus fault
+    Bfarvalid = 1 << 15,
+    /// Undefined instruction
+    Undefinstr = 1 << 16,
+    /// Invalid state, such as jumping to an address without the thumb bit
+    Invstate = 1 << 17,
+    /// Invalid EXC_RETURN value loaded into PC
+    Invpc = 1 << 18,
+    /// Tried to use a coprocessor which doesn't exist or is disabled
+    Nocp = 1 << 19,
+    /// Unaligned memory access
+    Unaligned = 1 << 24,
+    /// Divided by zero
+    Divbyzero = 1 << 25,
+}
+
+/// The bits of the HardFault Status Register
+#[repr(u32)]
+#[derive(EnumIter, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CortexMHfsrFlag {
+    /// Bus fault while reading the vector table
+    Vecttbl = 1 << 1,
+    /// A configurable fault was escalated to a hard fault
+    Forced = 1 << 30,
+    /// Debug event while debugging is disabled
+    Debugevt = 1 << 31,
+}
+
+/// The registers which the exception pushed on the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CortexMFrame {
+    pub r0: u32,
+    pub r1: u32,
+    pub r2: u32,
+    pub r3: u32,
+    pub r12: u32,
+    pub lr: u32,
+    pub pc: u32,
+    pub xpsr: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CortexMPanicData {
+    pub psp: u32,
+    pub ipsr: u32,
+    pub msp: u32,
+    pub r4_to_r11: [u32; 8],
+    /// The EXC_RETURN value
+    pub lr: u32,
+    /// Only saved if [`PanicDataFlag::FrameValid`] is set
+    pub frame: Option<CortexMFrame>,
+    pub cfsr: u32,
+    pub bfar: u32,
+    pub mmfar: u32,
+    pub shcsr: u32,
+    pub hfsr: u32,
+    pub dfsr: u32,
+}
+
+impl CortexMPanicData {
+    const WORDS: usize = 26;
+
+    fn parse(words: &[u32], flags: PanicDataFlags) -> Self {
+        let frame = &words[12..20];
+        Self {
+            psp: words[0],
+            ipsr: words[1],
+            msp: words[2],
+            r4_to_r11: words[3..11].try_into().unwrap(),
+            lr: words[11],
+            frame: flags
+                .contains(PanicDataFlag::FrameValid)
+                .then(|| CortexMFrame {
+                    r0: frame[0],
+                    r1: frame[1],
+                    r2: frame[2],
+                    r3: frame[3],
+                    r12: frame[4],
+                    lr: frame[5],
+                    pc: frame[6],
+                    xpsr: frame[7],
+                }),
+            cfsr: words[20],
+            bfar: words[21],
+            mmfar: words[22],
+            shcsr: words[23],
+            hfsr: words[24],
+            dfsr: words[25],
+        }
+    }
+
+    pub fn exception(&self) -> CortexMException {
+        self.ipsr.into()
+    }
+
+    pub fn cfsr_flags(&self) -> impl Iterator<Item = CortexMCfsrFlag> + '_ {
+        CortexMCfsrFlag::iter().filter(|flag| self.cfsr & *flag as u32 != 0)
+    }
+
+    pub fn hfsr_flags(&self) -> impl Iterator<Item = CortexMHfsrFlag> + '_ {
+        CortexMHfsrFlag::iter().filter(|flag| self.hfsr & *flag as u32 != 0)
+    }
+
+    /// The reason is saved in R4 and the info in R5
+    pub fn software_panic(&self) -> Option<SoftwarePanic> {
+        Some(SoftwarePanic {
+            reason: SoftwarePanicReason::from_repr(self.r4_to_r11[0])?,
+            info: self.r4_to_r11[1],
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nds32PanicData {
+    /// The interruption type
+    pub itype: u32,
+    /// r0-r10, r15, fp, gp, lp, and sp
+    pub regs: [u32; 16],
+    /// The interrupted PC
+    pub ipc: u32,
+    /// The interrupted processor status word
+    pub ipsw: u32,
+}
+
+impl Nds32PanicData {
+    const WORDS: usize = 19;
+    pub const REGISTER_NAMES: [&'static str; 16] = [
+        "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r15", "fp", "gp", "lp",
+        "sp",
+    ];
+
+    fn parse(words: &[u32]) -> Self {
+        Self {
+            itype: words[0],
+            regs: words[1..17].try_into().unwrap(),
+            ipc: words[17],
+            ipsw: words[18],
+        }
+    }
+
+    /// The software ID in ITYPE, which is set by system calls
+    pub fn software_id(&self) -> u32 {
+        (self.itype >> 16) & 0x7fff
+    }
+
+    /// The reason is saved in r6 and the info in r7
+    pub fn software_panic(&self) -> Option<SoftwarePanic> {
+        Some(SoftwarePanic {
+            reason: SoftwarePanicReason::from_repr(self.regs[6])?,
+            info: self.regs[7],
+        })
+    }
+}
+
+/// The cause of a RISC-V trap, from the lower bits of `mcause`
+#[repr(u32)]
+#[derive(FromRepr, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiscVException {
+    InstructionAddressMisaligned,
+    InstructionAccessFault,
+    IllegalInstruction,
+    Breakpoint,
+    LoadAddressMisaligned,
+    LoadAccessFault,
+    StoreAddressMisaligned,
+    StoreAccessFault,
+    EnvironmentCallFromUMode,
+    EnvironmentCallFromSMode,
+    EnvironmentCallFromMMode = 11,
+    InstructionPageFault,
+    LoadPageFault,
+    StorePageFault = 15,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiscVPanicData {
+    /// The registers in the order the exception handler saves them, which is [`RiscVPanicData::REGISTER_NAMES`]
+    pub regs: [u32; 31],
+    pub mepc: u32,
+    pub mcause: u32,
+}
+
+impl RiscVPanicData {
+    const WORDS: usize = 33;
+    /// The ABI names of `regs`, which are saved in reverse order, from core/riscv-rv32i/panic.c
+    pub const REGISTER_NAMES: [&'static str; 31] = [
+        "s11", "s10", "s9", "s8", "s7", "s6", "s5", "s4", "s3", "s2", "s1", "s0", "t6", "t5", "t4",
+        "t3", "t2", "t1", "t0", "a7", "a6", "a5", "a4", "a3", "a2", "a1", "a0", "tp", "gp", "ra",
+        "sp",
+    ];
+
+    fn parse(words: &[u32]) -> Self {
+        Self {
+            regs: words[..31].try_into().unwrap(),
+            mepc: words[31],
+            mcause: words[32],
+        }
+    }
+
+    /// `None` if the trap was an interrupt
+    pub fn exception(&self) -> Option<Result<RiscVException, u32>> {
+        if self.mcause & (1 << 31) != 0 || self.software_panic().is_some() {
+            return None;
+        }
+        Some(RiscVException::from_repr(self.mcause).ok_or(self.mcause))
+    }
+
+    /// The reason is saved in s0 and the info in s1. `mcause` has the exception, if there was one.
+    pub fn software_panic(&self) -> Option<SoftwarePanic> {
+        Some(SoftwarePanic {
+            reason: SoftwarePanicReason::from_repr(self.regs[11])?,
+            info: self.regs[10],
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PanicRegisters {
+    CortexM(CortexMPanicData),
+    Nds32N8(Nds32PanicData),
+    RiscV(RiscVPanicData),
+    /// An architecture which isn't decoded, such as x86
+    Other {
+        arch: Result<PanicArch, u8>,
+        words: Vec<u32>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicInfo {
+    pub struct_version: u8,
+    pub flags: PanicDataFlags,
+    pub registers: PanicRegisters,
+}
+
+impl PanicInfo {
+    /// Decodes the `panic_data` struct, which can have trailing zeros. Returns `None` if there is no panic data.
+    ///
+    /// The size of the struct depends on the architectures the EC was built with,
+    /// so the end is found with the magic number and the struct size before it.
+    pub fn parse(data: &[u8]) -> Result<Option<Self>, Error> {
+        if data.iter().all(|byte| *byte == 0) {
+            return Ok(None);
+        }
+        let word = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let struct_size = (HEADER_SIZE..data.len().saturating_sub(TRAILER_SIZE - 1))
+            .step_by(4)
+            .map(|offset| offset + TRAILER_SIZE)
+            .find(|&size| {
+                word(size - 4) == Some(PANIC_DATA_MAGIC) && word(size - 8) == Some(size as u32)
+            })
+            .ok_or(Error::InvalidMagic)?;
+        let words: Vec<u32> = data[HEADER_SIZE..struct_size - TRAILER_SIZE]
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        let flags = PanicDataFlags(data[2]);
+        let arch = PanicArch::from_repr(data[0]).ok_or(data[0]);
+        let check_size = |arch: PanicArch, size: usize| {
+            if words.len() < size {
+                Err(Error::Truncated(arch))
+            } else {
+                Ok(())
+            }
+        };
+        let registers = match arch {
+            Ok(PanicArch::CortexM) => {
+                check_size(PanicArch::CortexM, CortexMPanicData::WORDS)?;
+                PanicRegisters::CortexM(CortexMPanicData::parse(&words, flags))
+            }
+            Ok(PanicArch::Nds32N8) => {
+                check_size(PanicArch::Nds32N8, Nds32PanicData::WORDS)?;
+                PanicRegisters::Nds32N8(Nds32PanicData::parse(&words))
+            }
+            Ok(PanicArch::RiscvRv32i) => {
+                check_size(PanicArch::RiscvRv32i, RiscVPanicData::WORDS)?;
+                PanicRegisters::RiscV(RiscVPanicData::parse(&words))
+            }
+            arch => PanicRegisters::Other { arch, words },
+        };
+        Ok(Some(Self {
+            struct_version: data[1],
+            flags,
+            registers,
+        }))
+    }
+
+    pub fn software_panic(&self) -> Option<SoftwarePanic> {
+        match &self.registers {
+            PanicRegisters::CortexM(registers) => registers.software_panic(),
+            PanicRegisters::Nds32N8(registers) => registers.software_panic(),
+            PanicRegisters::RiscV(registers) => registers.software_panic(),
+            PanicRegisters::Other { .. } => None,
+        }
+    }
+}
+
+/// Gets and decodes the panic data which the EC saved when it last panicked.
+/// Returns `None` if the EC hasn't panicked since it lost power.
+pub fn get_panic_info(file: &mut File) -> Result<Option<PanicInfo>, Error> {
+    PanicInfo::parse(&ec_cmd_get_panic_info(file)?)
+}
+
+fn write_registers(f: &mut Formatter<'_>, names: &[&str], values: &[u32]) -> fmt::Result {
+    for (index, (name, value)) in names.iter().zip(values).enumerate() {
+        write!(f, "{name:>4}: {value:08x}")?;
+        if index % 4 == 3 || index == values.len() - 1 {
+            writeln!(f)?;
+        } else {
+            write!(f, "  ")?;
+        }
+    }
+    Ok(())
+}
+
+impl Display for PanicInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Saved panic data")?;
+        if !self.flags.contains(PanicDataFlag::OldHostcmd) {
+            write!(f, " (NEW)")?;
+        }
+        writeln!(f)?;
+        if self.flags.contains(PanicDataFlag::Truncated) {
+            writeln!(f, "Truncated")?;
+        }
+        if let Some(software_panic) = self.software_panic() {
+            writeln!(
+                f,
+                "Software panic: {} (info {:#x})",
+                <&str>::from(software_panic.reason),
+                software_panic.info
+            )?;
+        }
+        match &self.registers {
+            PanicRegisters::CortexM(registers) => {
+                writeln!(f, "=== EXCEPTION: {:?} ===", registers.exception())?;
+                if let Some(frame) = &registers.frame {
+                    write_registers(
+                        f,
+                        &["r0", "r1", "r2", "r3", "r12", "lr", "pc", "xpsr"],
+                        &[
+                            frame.r0, frame.r1, frame.r2, frame.r3, frame.r12, frame.lr, frame.pc,
+                            frame.xpsr,
+                        ],
+                    )?;
+                }
+                write_registers(
+                    f,
+                    &["r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11"],
+                    &registers.r4_to_r11,
+                )?;
+                write_registers(
+                    f,
+                    &["psp", "msp", "ipsr", "exc"],
+                    &[registers.psp, registers.msp, registers.ipsr, registers.lr],
+                )?;
+                write_registers(
+                    f,
+                    &["cfsr", "bfar", "mfar", "shcs", "hfsr", "dfsr"],
+                    &[
+                        registers.cfsr,
+                        registers.bfar,
+                        registers.mmfar,
+                        registers.shcsr,
+                        registers.hfsr,
+                        registers.dfsr,
+                    ],
+                )?;
+                for flag in registers.cfsr_flags() {
+                    writeln!(f, "CFSR: {}", <&str>::from(flag))?;
+                }
+                for flag in registers.hfsr_flags() {
+                    writeln!(f, "HFSR: {}", <&str>::from(flag))?;
+                }
+            }
+            PanicRegisters::Nds32N8(registers) => {
+                writeln!(f, "=== EXCEPTION: ITYPE={:x} ===", registers.itype)?;
+                write_registers(f, &Nds32PanicData::REGISTER_NAMES, &registers.regs)?;
+                write_registers(f, &["ipc", "ipsw"], &[registers.ipc, registers.ipsw])?;
+                writeln!(f, "SWID of ITYPE: {:x}", registers.software_id())?;
+            }
+            PanicRegisters::RiscV(registers) => {
+                match registers.exception() {
+                    Some(Ok(exception)) => {
+                        writeln!(f, "=== EXCEPTION: {} ===", <&str>::from(exception))?
+                    }
+                    Some(Err(mcause)) => writeln!(f, "=== EXCEPTION: MCAUSE={mcause:x} ===")?,
+                    None => writeln!(f, "=== MCAUSE: {:x} ===", registers.mcause)?,
+                }
+                write_registers(f, &RiscVPanicData::REGISTER_NAMES, &registers.regs)?;
+                write_registers(f, &["mepc"], &[registers.mepc])?;
+            }
+            PanicRegisters::Other { arch, words } => {
+                writeln!(f, "Architecture {arch:?} isn't supported")?;
+                for word in words {
+                    writeln!(f, "{word:08x}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The size of `panic_data`, where the union is as big as the RISC-V registers
+    const STRUCT_SIZE: u32 = 0x90;
+    /// The size of the response to `EC_CMD_GET_PANIC_INFO`, which is bigger than the struct
+    const RESPONSE_SIZE: usize = 0xa0;
+
+    /// Lays out the words like the EC does, with zeros after the struct to fill the response
+    fn blob(words: &[u32]) -> Vec<u8> {
+        let mut data: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        data.resize(RESPONSE_SIZE, 0);
+        data
+    }
+
+    #[rustfmt::skip]
+    const CORTEX_M_BUS_FAULT: [u32; 36] = [
+        // arch 1, struct_version 2, flags FrameValid
+        0x0001_0201,
+        // psp, ipsr, msp
+        0x2000_7f00, 0x0000_0005, 0x2000_7fa0,
+        // r4-r11
+        0x0000_0004, 0x0000_0005, 0x0000_0006, 0x0000_0007,
+        0x0000_0008, 0x0000_0009, 0x0000_000a, 0x0000_000b,
+        // EXC_RETURN
+        0xffff_fffd,
+        // r0, r1, r2, r3, r12, lr, pc, xpsr
+        0x0000_0000, 0x0000_0001, 0x0000_0002, 0x0000_0003,
+        0x0000_000c, 0x0800_1235, 0x0800_4568, 0x2100_0000,
+        // cfsr, bfar, mmfar, shcsr, hfsr, dfsr
+        0x0000_8200, 0x4000_0004, 0x0000_0000, 0x0002_0000, 0x0000_0000, 0x0000_0000,
+        // The rest of the union
+        0, 0, 0, 0, 0, 0, 0,
+        STRUCT_SIZE, PANIC_DATA_MAGIC,
+    ];
+
+    #[test]
+    fn cortex_m_hardware_fault() {
+        let info = PanicInfo::parse(&blob(&CORTEX_M_BUS_FAULT))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.struct_version, 2);
+        assert_eq!(
+            info.flags.iter().collect::<Vec<_>>(),
+            [PanicDataFlag::FrameValid]
+        );
+        assert_eq!(info.software_panic(), None);
+        let PanicRegisters::CortexM(registers) = info.registers else {
+            panic!("not Cortex-M: {:?}", info.registers);
+        };
+        assert_eq!(registers.exception(), CortexMException::BusFault);
+        assert_eq!(
+            registers.cfsr_flags().collect::<Vec<_>>(),
+            [CortexMCfsrFlag::Preciserr, CortexMCfsrFlag::Bfarvalid]
+        );
+        assert_eq!(registers.bfar, 0x4000_0004);
+        assert_eq!(registers.r4_to_r11, [4, 5, 6, 7, 8, 9, 10, 11]);
+        let frame = registers.frame.unwrap();
+        assert_eq!(frame.pc, 0x0800_4568);
+        assert_eq!(frame.lr, 0x0800_1235);
+    }
+
+    #[test]
+    fn cortex_m_software_panic() {
+        #[rustfmt::skip]
+        let words = [
+            // arch 1, struct_version 2, no flags, so the frame isn't valid
+            0x0000_0201,
+            0x2000_7f00, 0x0000_0000, 0x2000_7fa0,
+            // Assert in r4, the line number in r5
+            0xdead_6663, 0x0000_01a4, 0, 0, 0, 0, 0, 0,
+            0xffff_fff9,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0,
+            STRUCT_SIZE, PANIC_DATA_MAGIC,
+        ];
+        let info = PanicInfo::parse(&blob(&words)).unwrap().unwrap();
+        assert_eq!(
+            info.software_panic(),
+            Some(SoftwarePanic {
+                reason: SoftwarePanicReason::Assert,
+                info: 0x1a4,
+            })
+        );
+        let PanicRegisters::CortexM(registers) = &info.registers else {
+            panic!("not Cortex-M: {:?}", info.registers);
+        };
+        assert_eq!(registers.frame, None);
+        assert!(info
+            .to_string()
+            .contains("Software panic: Assert (info 0x1a4)"));
+    }
+
+    #[test]
+    fn nds32_software_panic() {
+        #[rustfmt::skip]
+        let words = [
+            // arch 2, struct_version 2
+            0x0000_0202,
+            // itype
+            0x0000_0008,
+            // r0-r5, then Assert in r6 and the line number in r7
+            0, 0, 0, 0, 0, 0, 0xdead_6663, 0x0000_0042,
+            // r8, r9, r10, r15, fp, gp, lp, sp
+            0, 0, 0, 0, 0x0008_0f00, 0x0008_1000, 0x0000_2345, 0x0008_0e00,
+            // ipc, ipsw
+            0x0000_1234, 0x0007_0009,
+            // The rest of the union
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            STRUCT_SIZE, PANIC_DATA_MAGIC,
+        ];
+        let info = PanicInfo::parse(&blob(&words)).unwrap().unwrap();
+        assert_eq!(
+            info.software_panic(),
+            Some(SoftwarePanic {
+                reason: SoftwarePanicReason::Assert,
+                info: 0x42,
+            })
+        );
+        let PanicRegisters::Nds32N8(registers) = info.registers else {
+            panic!("not NDS32: {:?}", info.registers);
+        };
+        assert_eq!(registers.itype, 8);
+        assert_eq!(registers.ipc, 0x1234);
+        assert_eq!(registers.regs[15], 0x0008_0e00);
+    }
+
+    /// Registers in the order the RISC-V exception handler saves them, from s11 to sp
+    #[rustfmt::skip]
+    fn riscv_words(s0: u32, s1: u32, mepc: u32, mcause: u32) -> [u32; 36] {
+        [
+            // arch 4, struct_version 2
+            0x0000_0204,
+            // s11-s2
+            0x11, 0x10, 0x9, 0x8, 0x7, 0x6, 0x5, 0x4, 0x3, 0x2,
+            s1, s0,
+            // t6-t0
+            0, 0, 0, 0, 0, 0, 0,
+            // a7-a0
+            0, 0, 0, 0, 0, 0, 0, 0xa0,
+            // tp, gp, ra, sp
+            0, 0x0008_0800, 0x0008_1234, 0x0008_0f00,
+            mepc, mcause,
+            STRUCT_SIZE, PANIC_DATA_MAGIC,
+        ]
+    }
+
+    #[test]
+    fn riscv_hardware_fault() {
+        // Load access fault
+        let info = PanicInfo::parse(&blob(&riscv_words(0, 0, 0x0008_2000, 5)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.software_panic(), None);
+        let PanicRegisters::RiscV(registers) = &info.registers else {
+            panic!("not RISC-V: {:?}", info.registers);
+        };
+        assert_eq!(
+            registers.exception(),
+            Some(Ok(RiscVException::LoadAccessFault))
+        );
+        assert_eq!(registers.mepc, 0x0008_2000);
+        let named = |name: &str| {
+            let index = RiscVPanicData::REGISTER_NAMES
+                .iter()
+                .position(|register| *register == name)
+                .unwrap();
+            registers.regs[index]
+        };
+        assert_eq!(named("s11"), 0x11);
+        assert_eq!(named("a0"), 0xa0);
+        assert_eq!(named("ra"), 0x0008_1234);
+        assert_eq!(named("sp"), 0x0008_0f00);
+        let text = info.to_string();
+        assert!(text.contains("LoadAccessFault"));
+        assert!(text.contains("  ra: 00081234"));
+    }
+
+    #[test]
+    fn riscv_software_panic() {
+        let words = riscv_words(0xdead_6663, 0x99, 0x0008_2000, 0);
+        let info = PanicInfo::parse(&blob(&words)).unwrap().unwrap();
+        assert_eq!(
+            info.software_panic(),
+            Some(SoftwarePanic {
+                reason: SoftwarePanicReason::Assert,
+                info: 0x99,
+            })
+        );
+        let PanicRegisters::RiscV(registers) = &info.registers else {
+            panic!("not RISC-V: {:?}", info.registers);
+        };
+        assert_eq!(registers.exception(), None);
+    }
+
+    #[test]
+    fn no_panic_data() {
+        assert_eq!(PanicInfo::parse(&[0; RESPONSE_SIZE]).unwrap(), None);
+        assert_eq!(PanicInfo::parse(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn without_padding() {
+        let data = blob(&CORTEX_M_BUS_FAULT);
+        let info = PanicInfo::parse(&data[..STRUCT_SIZE as usize]).unwrap();
+        assert_eq!(info, PanicInfo::parse(&data).unwrap());
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut words = CORTEX_M_BUS_FAULT;
+        words[35] = 0x2163_6e51;
+        assert!(matches!(
+            PanicInfo::parse(&blob(&words)),
+            Err(Error::InvalidMagic)
+        ));
+        // The magic is right, but the struct size before it isn't
+        let mut words = CORTEX_M_BUS_FAULT;
+        words[34] = STRUCT_SIZE + 4;
+        assert!(matches!(
+            PanicInfo::parse(&blob(&words)),
+            Err(Error::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        // A Cortex-M header with a struct which is too small for the Cortex-M registers
+        let words = [0x0000_0201, 0, 0, 0, 24, PANIC_DATA_MAGIC];
+        assert!(matches!(
+            PanicInfo::parse(&blob(&words)),
+            Err(Error::Truncated(PanicArch::CortexM))
+        ));
+    }
+}
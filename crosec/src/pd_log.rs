@@ -0,0 +1,75 @@
+//! Collects the PD log, which records charging changes, connections, accessory firmware problems,
+//! power supply faults, and video dongle changes.
+//!
+//! Those are the only PD log event types the EC defines, so VDMs and TCPC events don't show up here.
+//! Entries with other types, such as from a newer EC, are [`crate::commands::pd_log::PdLogEvent::Unknown`].
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::os::fd::AsRawFd;
+
+use crate::commands::pd_log::{pd_get_log, PdLogEntry};
+use crate::wait_event::event::{EcMkbpEvent, EcMkbpEventType};
+use crate::wait_event::host_event::HostEventCode;
+use crate::wait_event::{set_event_mask, wait_event_sync, Error, PollData};
+
+const HOST_EVENTS: [EcMkbpEventType; 2] =
+    [EcMkbpEventType::HostEvent, EcMkbpEventType::HostEvent64];
+
+/// An iterator over PD log entries, oldest first.
+/// The log is drained right away, and again every time the EC sends the `PdMcu` host event.
+pub struct PdLogCollector<'a, File: AsRawFd + Read> {
+    file: &'a mut File,
+    entries: VecDeque<PdLogEntry>,
+    drained: bool,
+    timeout: Option<i32>,
+}
+
+impl<'a, File: AsRawFd + Read> PdLogCollector<'a, File> {
+    /// If `timeout` is specified in milliseconds, the iterator ends when no event comes in time
+    pub fn new(file: &'a mut File, timeout: Option<i32>) -> Self {
+        // Listen for host events before draining the log, so an event in between isn't missed
+        set_event_mask(file, HOST_EVENTS);
+        Self {
+            file,
+            entries: Default::default(),
+            drained: false,
+            timeout,
+        }
+    }
+}
+
+impl<'a, File: AsRawFd + Read> Iterator for PdLogCollector<'a, File> {
+    type Item = Result<PdLogEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.entries.pop_front() {
+                return Some(Ok(entry));
+            }
+            if !self.drained {
+                match pd_get_log(self.file) {
+                    Ok(entries) => self.entries.extend(entries),
+                    Err(e) => return Some(Err(e.into())),
+                }
+                self.drained = true;
+                continue;
+            }
+            match wait_event_sync(self.file, HOST_EVENTS, self.timeout) {
+                Ok(PollData::EventHappened(EcMkbpEvent::HostEvent(host_event))) => {
+                    if host_event.contains(HostEventCode::PdMcu) {
+                        self.drained = false;
+                    }
+                }
+                Ok(PollData::EventHappened(EcMkbpEvent::HostEvent64(host_event))) => {
+                    if host_event & HostEventCode::PdMcu as u64 != 0 {
+                        self.drained = false;
+                    }
+                }
+                Ok(PollData::Timeout) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(Error::WaitEvent(e))),
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::commands::reboot_ec::{reboot_ec, RebootEcCommand, RebootEcFlags};
+use crate::wait_event::event::{EcMkbpEvent, EcMkbpEventType};
+use crate::wait_event::host_event::HostEventCode;
+use crate::wait_event::{self, set_event_mask, wait_event_sync, PollData};
+use crate::EcError;
+
+const HOST_EVENTS: [EcMkbpEventType; 2] =
+    [EcMkbpEventType::HostEvent, EcMkbpEventType::HostEvent64];
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Ec(#[from] wait_event::Error),
+    #[error("the EC wasn't ready in time")]
+    Timeout,
+}
+
+impl From<EcError> for Error {
+    fn from(e: EcError) -> Self {
+        Self::Ec(e.into())
+    }
+}
+
+/// Waits for the `InterfaceReady` host event, which the EC sends once it can accept commands after rebooting.
+/// Events which happened before the event mask of `file` was set are missed, so [`reboot_ec_and_wait`] is usually better.
+pub fn wait_interface_ready<File: AsRawFd + Read>(
+    file: &mut File,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err(Error::Timeout);
+        }
+        let remaining_ms = remaining.as_millis().clamp(1, i32::MAX as u128) as i32;
+        match wait_event_sync(file, HOST_EVENTS, Some(remaining_ms)) {
+            Ok(PollData::EventHappened(EcMkbpEvent::HostEvent(host_event))) => {
+                if host_event.contains(HostEventCode::InterfaceReady) {
+                    return Ok(());
+                }
+            }
+            Ok(PollData::EventHappened(EcMkbpEvent::HostEvent64(host_event))) => {
+                if host_event & HostEventCode::InterfaceReady as u64 != 0 {
+                    return Ok(());
+                }
+            }
+            Ok(PollData::Timeout) => return Err(Error::Timeout),
+            Ok(_) => {}
+            Err(e) => return Err(wait_event::Error::WaitEvent(e).into()),
+        }
+    }
+}
+
+/// Reboots the EC or jumps to another image, and waits until the EC is ready to accept commands again.
+///
+/// Only use this with commands which restart the EC right away, like [`RebootEcCommand::JumpRw`] or [`RebootEcCommand::Cold`].
+/// The EC won't send `InterfaceReady` for commands like [`RebootEcCommand::Cancel`],
+/// or if [`crate::commands::reboot_ec::RebootEcFlag::OnApShutdown`] is set, so this would time out.
+pub fn reboot_ec_and_wait<File: AsRawFd + Read>(
+    file: &mut File,
+    command: RebootEcCommand,
+    flags: RebootEcFlags,
+    timeout: Duration,
+) -> Result<(), Error> {
+    // Listen for host events before rebooting, so the event can't come before we start waiting
+    set_event_mask(file, HOST_EVENTS);
+    reboot_ec(file, command, flags)?;
+    wait_interface_ready(file, timeout)
+}
@@ -0,0 +1,88 @@
+use std::ffi::c_int;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::AsRawFd;
+
+use crate::commands::mkbp_info::{mkbp_get_switches, MkbpSwitches};
+use crate::read_mem_any::read_mem_any;
+use crate::wait_event::event::{EcMkbpEvent, EcMkbpEventType};
+use crate::wait_event::{set_event_mask, wait_event_sync, Error, PollData};
+use crate::EC_MEM_MAP_SWITCHES;
+
+pub const EC_SWITCH_LID_OPEN: u8 = 0x01;
+pub const EC_SWITCH_POWER_BUTTON_PRESSED: u8 = 0x02;
+pub const EC_SWITCH_WRITE_PROTECT_DISABLED: u8 = 0x04;
+pub const EC_SWITCH_DEDICATED_RECOVERY: u8 = 0x10;
+
+/// The switch flags in the EC memory map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Switches(pub u8);
+
+impl Switches {
+    pub fn lid_open(&self) -> bool {
+        self.0 & EC_SWITCH_LID_OPEN != 0
+    }
+
+    pub fn power_button_pressed(&self) -> bool {
+        self.0 & EC_SWITCH_POWER_BUTTON_PRESSED != 0
+    }
+
+    pub fn write_protect_disabled(&self) -> bool {
+        self.0 & EC_SWITCH_WRITE_PROTECT_DISABLED != 0
+    }
+
+    /// The dedicated recovery switch is on
+    pub fn dedicated_recovery(&self) -> bool {
+        self.0 & EC_SWITCH_DEDICATED_RECOVERY != 0
+    }
+}
+
+pub fn switches(file: &mut File) -> Result<Switches, c_int> {
+    Ok(Switches(read_mem_any(file, EC_MEM_MAP_SWITCHES)?))
+}
+
+/// An iterator over whether the device is in tablet mode.
+/// The first item is the current state, and after that there is an item every time tablet mode changes.
+pub struct TabletModeWatcher<'a, File: AsRawFd + Read> {
+    file: &'a mut File,
+    tablet_mode: Option<bool>,
+}
+
+impl<'a, File: AsRawFd + Read> Iterator for TabletModeWatcher<'a, File> {
+    type Item = Result<bool, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(previous) = self.tablet_mode else {
+            return Some(match mkbp_get_switches(self.file) {
+                Ok(switches) => {
+                    self.tablet_mode = Some(switches.tablet_mode());
+                    Ok(switches.tablet_mode())
+                }
+                Err(e) => Err(e.into()),
+            });
+        };
+        loop {
+            match wait_event_sync(self.file, [EcMkbpEventType::Switches], None) {
+                Ok(PollData::EventHappened(EcMkbpEvent::Switches(switches))) => {
+                    let tablet_mode = MkbpSwitches(switches).tablet_mode();
+                    if tablet_mode != previous {
+                        self.tablet_mode = Some(tablet_mode);
+                        return Some(Ok(tablet_mode));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => return Some(Err(Error::WaitEvent(e))),
+            }
+        }
+    }
+}
+
+/// Watches for tablet mode changes. See [`TabletModeWatcher`].
+pub fn tablet_mode<File: AsRawFd + Read>(file: &mut File) -> TabletModeWatcher<'_, File> {
+    // Listen for switch events before the first item reads the switches, so a change in between isn't missed
+    set_event_mask(file, [EcMkbpEventType::Switches]);
+    TabletModeWatcher {
+        file,
+        tablet_mode: None,
+    }
+}
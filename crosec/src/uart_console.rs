@@ -0,0 +1,203 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::libc::{poll, pollfd, POLLIN};
+use nix::sys::termios::{cfmakeraw, cfsetspeed, tcgetattr, tcsetattr, BaudRate, SetArg};
+use thiserror::Error;
+
+use crate::console::decode_output;
+
+/// What the EC console prints when it's ready for a command
+pub const EC_CONSOLE_PROMPT: &[u8] = b"> ";
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("failed to set up the serial port: {0}")]
+    Termios(#[from] Errno),
+    /// Has the output which was read before the timeout
+    #[error("the EC didn't print the prompt in time, output: {0:?}")]
+    Timeout(String),
+}
+
+/// Runs commands on the EC console over a UART, such as a servo's `/dev/ttyUSBx`.
+/// This works when the EC doesn't have a host interface, unlike [`crate::console`], which only reads the console.
+pub struct UartConsole {
+    file: File,
+    timeout: Duration,
+}
+
+impl UartConsole {
+    /// Opens a serial port and sets it to raw mode at 115200 baud, which is what ECs use.
+    /// `timeout` is how long to wait for a command to finish.
+    pub fn open(path: impl AsRef<Path>, timeout: Duration) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut termios = tcgetattr(&file)?;
+        cfmakeraw(&mut termios);
+        cfsetspeed(&mut termios, BaudRate::B115200)?;
+        tcsetattr(&file, SetArg::TCSANOW, &termios)?;
+        Ok(Self::new(file, timeout))
+    }
+
+    /// Uses a file which is already set up, such as a PTY
+    pub fn new(file: File, timeout: Duration) -> Self {
+        Self { file, timeout }
+    }
+
+    /// Waits up to `timeout` for the file to be readable
+    fn wait_readable(&self, timeout: Duration) -> io::Result<bool> {
+        let mut fds = pollfd {
+            fd: self.file.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+        match unsafe { poll(&mut fds, 1, timeout) } {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn read_available(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        let mut buffer = [0; 256];
+        let len = self.file.read(&mut buffer)?;
+        output.extend_from_slice(&buffer[..len]);
+        Ok(())
+    }
+
+    /// Throws away output which isn't from a command, such as log messages
+    fn discard_pending(&mut self) -> io::Result<()> {
+        let mut discarded = Vec::new();
+        while self.wait_readable(Duration::ZERO)? {
+            self.read_available(&mut discarded)?;
+        }
+        Ok(())
+    }
+
+    fn read_until_prompt(&mut self) -> Result<Vec<u8>, Error> {
+        let start = Instant::now();
+        let mut output = Vec::new();
+        let mut prompt = b"\n".to_vec();
+        prompt.extend_from_slice(EC_CONSOLE_PROMPT);
+        while !output.ends_with(&prompt) {
+            let remaining = self.timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() || !self.wait_readable(remaining)? {
+                return Err(Error::Timeout(decode_output(&output)));
+            }
+            self.read_available(&mut output)?;
+        }
+        output.truncate(output.len() - EC_CONSOLE_PROMPT.len());
+        Ok(output)
+    }
+
+    /// Runs a console command and returns what it printed, without the echoed command and the prompt
+    pub fn run(&mut self, command: &str) -> Result<String, Error> {
+        self.discard_pending()?;
+        self.file.write_all(command.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        let output = decode_output(&self.read_until_prompt()?);
+        // The console echoes the command before running it
+        let output = match output.split_once('\n') {
+            Some((echo, output)) if echo.trim_end() == command.trim_end() => output,
+            _ => &output,
+        };
+        Ok(output.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use nix::pty::openpty;
+
+    use super::*;
+
+    /// Makes a console on one end of a PTY, and returns the other end, which acts as the EC
+    fn console(timeout: Duration) -> (UartConsole, File) {
+        let pty = openpty(None, None).unwrap();
+        let mut termios = tcgetattr(&pty.slave).unwrap();
+        cfmakeraw(&mut termios);
+        tcsetattr(&pty.slave, SetArg::TCSANOW, &termios).unwrap();
+        (
+            UartConsole::new(File::from(pty.slave), timeout),
+            File::from(pty.master),
+        )
+    }
+
+    /// Reads a command from the console, and sends back each of `replies` in a separate write.
+    /// Returns the command with the EC's end, so it stays open until the test joins the thread.
+    fn fake_ec(
+        mut ec: File,
+        replies: &'static [&'static [u8]],
+    ) -> thread::JoinHandle<(String, File)> {
+        thread::spawn(move || {
+            let mut command = Vec::new();
+            while !command.ends_with(b"\n") {
+                let mut buffer = [0; 64];
+                let len = ec.read(&mut buffer).unwrap();
+                command.extend_from_slice(&buffer[..len]);
+            }
+            for reply in replies {
+                ec.write_all(reply).unwrap();
+                ec.flush().unwrap();
+                thread::sleep(Duration::from_millis(20));
+            }
+            (String::from_utf8(command).unwrap(), ec)
+        })
+    }
+
+    #[test]
+    fn strips_echo_and_prompt() {
+        let (mut console, ec) = console(Duration::from_secs(5));
+        let ec = fake_ec(
+            ec,
+            &[b"version\r\n", b"Chip: stm32\r\nRO: ec_v1.0\r\n", b"> "],
+        );
+        let output = console.run("version").unwrap();
+        assert_eq!(output, "Chip: stm32\nRO: ec_v1.0\n");
+        assert_eq!(ec.join().unwrap().0, "version\n");
+    }
+
+    #[test]
+    fn prompt_split_across_reads() {
+        let (mut console, ec) = console(Duration::from_secs(5));
+        let ec = fake_ec(
+            ec,
+            &[b"gettime\r\nTime: 0x0000000000123456", b"\r", b"\n>", b" "],
+        );
+        assert_eq!(
+            console.run("gettime").unwrap(),
+            "Time: 0x0000000000123456\n"
+        );
+        ec.join().unwrap();
+    }
+
+    #[test]
+    fn discards_pending_output() {
+        let (mut console, mut ec) = console(Duration::from_secs(5));
+        ec.write_all(b"[12.345 Battery 90%]\r\n> ").unwrap();
+        // Wait until the log line reaches the console, so it's pending when the command starts
+        assert!(console.wait_readable(Duration::from_secs(5)).unwrap());
+        let ec = fake_ec(ec, &[b"chan\r\n 0 command\r\n\r\n> "]);
+        assert_eq!(console.run("chan").unwrap(), " 0 command\n\n");
+        ec.join().unwrap();
+    }
+
+    #[test]
+    fn timeout_has_partial_output() {
+        let (mut console, ec) = console(Duration::from_millis(300));
+        let ec = fake_ec(ec, &[b"reboot\r\nRebooting!\r\n"]);
+        match console.run("reboot") {
+            Err(Error::Timeout(output)) => assert_eq!(output, "reboot\nRebooting!\n"),
+            result => panic!("expected a timeout, got {result:?}"),
+        }
+        ec.join().unwrap();
+    }
+}
@@ -5,27 +5,19 @@ use std::mem::size_of;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use bytemuck::{from_bytes, Pod, Zeroable};
+use bytemuck::{from_bytes, pod_read_unaligned};
 use num_derive::FromPrimitive;
 
+pub use crate::commands::motion_sense::EcResponseMotionSenseFifoInfo;
 use crate::wait_event::fingerprint::EcMkbpEventFingerprint;
+use crate::KEYBOARD_COLS_MAX;
 
 use super::host_event::EcMkbpEventHostEvent;
 
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
-#[repr(C, packed)]
-pub struct EcResponseMotionSenseFifoInfo {
-    size: u16,
-    count: u16,
-    timestamp: u32,
-    total_lost: u16,
-    lost: [u16; 0],
-}
-
 #[derive(Debug)]
 #[repr(u8)]
 pub enum EcMkbpEvent {
-    KeyMatrix([u8; 13]),
+    KeyMatrix([u8; KEYBOARD_COLS_MAX]),
     HostEvent(EcMkbpEventHostEvent),
     HostEvent64(u64),
     SensorFifo(EcResponseMotionSenseFifoInfo),
@@ -87,7 +79,7 @@ pub enum EcMkbpEventType {
 impl EcMkbpEventType {
     fn data_size(&self) -> usize {
         match self {
-            Self::KeyMatrix => size_of::<[u8; 13]>(),
+            Self::KeyMatrix => size_of::<[u8; KEYBOARD_COLS_MAX]>(),
             Self::HostEvent => size_of::<u32>(),
             Self::SensorFifo => size_of::<EcResponseMotionSenseFifoInfo>(),
             Self::Buttons => size_of::<u32>(),
@@ -108,6 +100,11 @@ impl EcMkbpEventType {
             EcMkbpEventType::HostEvent => {
                 EcMkbpEvent::HostEvent(from_bytes::<EcMkbpEventHostEvent>(&event).to_owned())
             }
+            EcMkbpEventType::HostEvent64 => EcMkbpEvent::HostEvent64(pod_read_unaligned(&event)),
+            EcMkbpEventType::Switches => EcMkbpEvent::Switches(pod_read_unaligned(&event)),
+            EcMkbpEventType::SensorFifo => EcMkbpEvent::SensorFifo(
+                from_bytes::<EcResponseMotionSenseFifoInfo>(&event).to_owned(),
+            ),
             event_type => panic!("{event_type:#?} from_bytes not implemented yet"),
         }
     }
@@ -86,6 +86,11 @@ impl EcMkbpEventHostEvent {
     pub fn rust(self) -> Result<HostEventCode, u32> {
         HostEventCode::from_u32(self.host_event).ok_or(self.host_event)
     }
+
+    /// The EC can send multiple host events at once, so this checks if `code` is one of them
+    pub fn contains(self, code: HostEventCode) -> bool {
+        self.host_event & code as u32 != 0
+    }
 }
 impl Debug for EcMkbpEventHostEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
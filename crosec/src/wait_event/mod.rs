@@ -6,8 +6,9 @@ use nix::{
 };
 
 use event::{EcMkbpEvent, EcMkbpEventType};
+use thiserror::Error;
 
-use crate::CROS_EC_IOC_MAGIC;
+use crate::{EcError, CROS_EC_IOC_MAGIC};
 
 pub mod event;
 pub mod fingerprint;
@@ -15,6 +16,15 @@ pub mod host_event;
 
 const POLL_IN: i16 = 0x001;
 
+/// An error from running EC commands and waiting for events
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("EC command failed: {0}")]
+    Ec(#[from] EcError),
+    #[error("error waiting for event: {0}")]
+    WaitEvent(i32),
+}
+
 #[derive(Debug)]
 pub enum PollData {
     EventHappened(EcMkbpEvent),
@@ -31,12 +41,12 @@ fn get_mask<I: IntoIterator<Item = EcMkbpEventType>>(event_types: I) -> i32 {
     mask
 }
 
-/// If no timeout is specified, this function will wait for an unlimited amount of time
-pub fn wait_event_sync<File: AsRawFd + std::io::Read, I: IntoIterator<Item = EcMkbpEventType>>(
+/// Only events of these types will be read from the file. The wait functions set this too,
+/// but setting it early makes sure events which happen before waiting aren't missed.
+pub fn set_event_mask<File: AsRawFd, I: IntoIterator<Item = EcMkbpEventType>>(
     file: &mut File,
     event_types: I,
-    timeout: Option<i32>,
-) -> Result<PollData, i32> {
+) {
     unsafe {
         ioctl(
             file.as_raw_fd(),
@@ -44,6 +54,15 @@ pub fn wait_event_sync<File: AsRawFd + std::io::Read, I: IntoIterator<Item = EcM
             get_mask(event_types),
         )
     };
+}
+
+/// If no timeout is specified, this function will wait for an unlimited amount of time
+pub fn wait_event_sync<File: AsRawFd + std::io::Read, I: IntoIterator<Item = EcMkbpEventType>>(
+    file: &mut File,
+    event_types: I,
+    timeout: Option<i32>,
+) -> Result<PollData, i32> {
+    set_event_mask(file, event_types);
     match timeout {
         Some(timeout) => {
             let mut fds = pollfd {
@@ -76,12 +95,6 @@ pub async fn wait_event_async<
     file: &mut File,
     event_types: I,
 ) -> io::Result<EcMkbpEvent> {
-    unsafe {
-        ioctl(
-            file.as_raw_fd(),
-            request_code_none!(CROS_EC_IOC_MAGIC, 2),
-            get_mask(event_types),
-        )
-    };
+    set_event_mask(file, event_types);
     EcMkbpEvent::read_async(file).await
 }
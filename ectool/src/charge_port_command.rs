@@ -0,0 +1,100 @@
+use std::{fs::File, str::FromStr};
+
+use color_eyre::eyre::{eyre, Result};
+use crosec::{
+    commands::{
+        charge_port::{
+            charge_port_count, pd_charge_port_override, usb_charge_set_mode, ChargePortOverride,
+            UsbChargeMode,
+        },
+        get_cmd_versions::{ec_cmd_get_cmd_versions, V1},
+        pd_chip_info::{pd_chip_info, usb_pd_dev_info},
+        usb_pd::usb_pd_ports,
+        CrosEcCmd,
+    },
+    CROS_EC_PATH,
+};
+
+/// `off`, `dontcharge`, or a port number
+#[derive(Clone, Copy)]
+pub struct ChargeOverrideArg(pub ChargePortOverride);
+
+impl FromStr for ChargeOverrideArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self(ChargePortOverride::Off)),
+            "dontcharge" => Ok(Self(ChargePortOverride::DontCharge)),
+            port => port
+                .parse()
+                .map(|port| Self(ChargePortOverride::Port(port)))
+                .map_err(|_| format!("expected off, dontcharge, or a port number, got {port}")),
+        }
+    }
+}
+
+/// Prints the info of every port if `port` is `None`
+pub fn pd_chip_info_command(port: Option<u8>, live: bool) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let ports = match port {
+        Some(port) => port..port + 1,
+        None => 0..usb_pd_ports(&mut file)?,
+    };
+    // Version 1 also has the minimum required firmware version
+    let versions = ec_cmd_get_cmd_versions(&mut file, CrosEcCmd::PdChipInfo)?;
+    let version = if versions & V1 != 0 { 1 } else { 0 };
+    for port in ports {
+        let info = pd_chip_info(&mut file, version, port, live)?;
+        println!("Port C{port}:");
+        println!("  Vendor ID:  {:#06x}", info.vendor_id);
+        println!("  Product ID: {:#06x}", info.product_id);
+        println!("  Device ID:  {:#06x}", info.device_id);
+        match info.fw_version {
+            Some(fw_version) => println!("  FW version: {fw_version:#x}"),
+            None => println!("  FW version: unsupported"),
+        }
+        if let Some(min_req_fw_version) = info.min_req_fw_version {
+            println!("  Min required FW version: {min_req_fw_version:#x}");
+        }
+    }
+    Ok(())
+}
+
+pub fn usb_pd_dev_info_command(port: u8) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let info = usb_pd_dev_info(&mut file, port)?;
+    if info.dev_id == 0 {
+        println!("Port C{port}: no device");
+        return Ok(());
+    }
+    println!(
+        "Port C{port}: device ID {}.{}, hash {}, current image {:?}",
+        info.dev_id_major(),
+        info.dev_id_minor(),
+        hex::encode(info.dev_rw_hash),
+        info.current_image
+    );
+    Ok(())
+}
+
+pub fn charge_port_count_command() -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    println!("Charge port count: {}", charge_port_count(&mut file)?);
+    Ok(())
+}
+
+pub fn charge_override_command(charge_override: ChargeOverrideArg) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    pd_charge_port_override(&mut file, charge_override.0)
+        .map_err(|e| eyre!("Failed to set the charge override: {e}"))?;
+    println!("Charge override set to {:?}", charge_override.0);
+    Ok(())
+}
+
+pub fn usb_charge_mode_command(port: u8, mode: UsbChargeMode, inhibit_charge: bool) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    usb_charge_set_mode(&mut file, port, mode, inhibit_charge)?;
+    println!("USB port {port} set to {mode:?}");
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::{
+    fs::File,
+    io::{stdout, Write},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::get_protocol_info::get_protocol_info,
+    console::{console, ConsoleFollower},
+};
+
+use crate::Device;
+
+pub fn console_command(device: Option<Device>, follow: bool, interval: u64) -> Result<()> {
+    let mut file = File::open(device.unwrap_or_default().get_path())?;
+    let protocol_info = get_protocol_info(&mut file)?;
+    if !follow {
+        let console = console(&mut file, &protocol_info)?;
+        let console = console.trim();
+        println!("{console}");
+        return Ok(());
+    }
+    let start = Instant::now();
+    let mut line_start = true;
+    let mut stdout = stdout().lock();
+    for output in ConsoleFollower::new(&mut file, protocol_info, Duration::from_millis(interval))? {
+        let output = output?;
+        let elapsed = start.elapsed().as_secs_f64();
+        for line in output.split_inclusive('\n') {
+            if line_start {
+                write!(stdout, "[{elapsed:10.3}] ")?;
+            }
+            write!(stdout, "{line}")?;
+            line_start = line.ends_with('\n');
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
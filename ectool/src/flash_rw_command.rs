@@ -0,0 +1,27 @@
+use std::{fs::File, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    flash_rw::{flash_rw, FlashRwDevice, FlashRwStep},
+    CROS_EC_PATH,
+};
+
+pub fn flash_rw_command(image: PathBuf) -> Result<()> {
+    let image = std::fs::read(image)?;
+    let mut file = File::open(CROS_EC_PATH)?;
+    flash_rw(
+        &mut FlashRwDevice::new(&mut file),
+        &image,
+        |step| match step {
+            FlashRwStep::JumpToRo => println!("Jumping to RO"),
+            FlashRwStep::Erase => println!("Erasing RW"),
+            FlashRwStep::Write { written, total } => {
+                println!("Writing RW: {written} / {total} bytes")
+            }
+            FlashRwStep::Verify => println!("Verifying RW"),
+            FlashRwStep::JumpToRw => println!("Jumping to RW"),
+        },
+    )?;
+    println!("RW updated");
+    Ok(())
+}
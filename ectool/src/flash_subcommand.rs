@@ -0,0 +1,158 @@
+use std::{fs::File, path::PathBuf, time::Duration};
+
+use clap::Subcommand;
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{
+        flash::{
+            flash_erase, flash_erase_async, flash_info_latest, flash_info_v2, flash_protect,
+            flash_read, flash_region_info, flash_write, FlashProtectFlag, FlashProtectFlags,
+            FlashRegion, EC_FLASH_INFO_ERASE_TO_0, EC_FLASH_INFO_SELECT_REQUIRED,
+        },
+        get_cmd_versions::{ec_cmd_get_cmd_versions, V2},
+        get_protocol_info::get_protocol_info,
+        CrosEcCmd,
+    },
+    CROS_EC_PATH,
+};
+
+use crate::parse_number::parse_number;
+
+#[derive(Subcommand)]
+pub enum FlashSubcommand {
+    /// Prints the size of the flash and its banks
+    Info,
+    /// Reads flash into a file
+    Read {
+        #[arg(value_parser = parse_number::<u32>)]
+        offset: u32,
+        #[arg(value_parser = parse_number::<u32>)]
+        size: u32,
+        file: PathBuf,
+    },
+    /// Writes a file to flash. The flash must be erased first.
+    Write {
+        #[arg(value_parser = parse_number::<u32>)]
+        offset: u32,
+        file: PathBuf,
+    },
+    /// Erases flash. The offset and size must be multiples of the erase block size.
+    Erase {
+        #[arg(value_parser = parse_number::<u32>)]
+        offset: u32,
+        #[arg(value_parser = parse_number::<u32>)]
+        size: u32,
+        /// Erase in the background and poll until it's done, which avoids timeouts with big erases
+        #[arg(long)]
+        r#async: bool,
+    },
+    /// Prints the flash protection state, and optionally changes it
+    Protect {
+        #[arg(long)]
+        enable: Vec<FlashProtectFlag>,
+        #[arg(long)]
+        disable: Vec<FlashProtectFlag>,
+    },
+    /// Prints the offset and size of a region of flash
+    Region { region: FlashRegion },
+}
+
+fn print_flash_protect_flags(name: &str, flags: FlashProtectFlags) {
+    let names = flags.iter().map(<&str>::from).collect::<Vec<_>>();
+    println!("{name} {:#010x} {}", flags.0, names.join(" "));
+}
+
+pub fn flash_subcommand(command: FlashSubcommand) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    match command {
+        FlashSubcommand::Info => {
+            let versions = ec_cmd_get_cmd_versions(&mut file, CrosEcCmd::FlashInfo)?;
+            if versions & V2 != 0 {
+                let protocol_info = get_protocol_info(&mut file)?;
+                let info = flash_info_v2(&mut file, &protocol_info)?;
+                println!("Flash size:       {}", info.flash_size);
+                println!("Write ideal size: {}", info.write_ideal_size);
+                println!("Flags:            {:#x}", info.flags);
+                println!("Banks:            {}", info.num_banks_total);
+                for bank in info.banks {
+                    println!(
+                        "  {} x {} bytes (write {}, erase {}, protect {})",
+                        bank.count,
+                        bank.size(),
+                        bank.write_size(),
+                        bank.erase_size(),
+                        bank.protect_size()
+                    );
+                }
+            } else {
+                let info = flash_info_latest(&mut file)?;
+                println!("Flash size:         {}", info.flash_size);
+                println!("Write block size:   {}", info.write_block_size);
+                println!("Erase block size:   {}", info.erase_block_size);
+                println!("Protect block size: {}", info.protect_block_size);
+                if let Some(write_ideal_size) = info.write_ideal_size {
+                    println!("Write ideal size:   {write_ideal_size}");
+                }
+                if let Some(flags) = info.flags {
+                    println!("Flags:              {flags:#x}");
+                    if flags & EC_FLASH_INFO_ERASE_TO_0 != 0 {
+                        println!("  Erases to 0");
+                    }
+                    if flags & EC_FLASH_INFO_SELECT_REQUIRED != 0 {
+                        println!("  Select required");
+                    }
+                }
+            }
+        }
+        FlashSubcommand::Read {
+            offset,
+            size,
+            file: path,
+        } => {
+            let protocol_info = get_protocol_info(&mut file)?;
+            let data = flash_read(&mut file, &protocol_info, offset, size)?;
+            std::fs::write(path, data)?;
+        }
+        FlashSubcommand::Write { offset, file: path } => {
+            let protocol_info = get_protocol_info(&mut file)?;
+            let info = flash_info_latest(&mut file)?;
+            let data = std::fs::read(path)?;
+            flash_write(&mut file, &protocol_info, &info, offset, &data)?;
+            println!("Wrote {} bytes at {offset:#x}", data.len());
+        }
+        FlashSubcommand::Erase {
+            offset,
+            size,
+            r#async,
+        } => {
+            if r#async {
+                flash_erase_async(
+                    &mut file,
+                    offset,
+                    size,
+                    Duration::from_millis(500),
+                    |elapsed| println!("Erasing... {}s", elapsed.as_secs()),
+                )?;
+            } else {
+                flash_erase(&mut file, offset, size)?;
+            }
+            println!("Erased {size} bytes at {offset:#x}");
+        }
+        FlashSubcommand::Protect { enable, disable } => {
+            let mask = enable.iter().chain(disable.iter()).copied().collect();
+            let flags = enable.into_iter().collect();
+            let protect = flash_protect(&mut file, mask, flags)?;
+            print_flash_protect_flags("Flash protect flags:", protect.flags);
+            print_flash_protect_flags("Valid flags:        ", protect.valid_flags);
+            print_flash_protect_flags("Writable flags:     ", protect.writable_flags);
+        }
+        FlashSubcommand::Region { region } => {
+            let info = flash_region_info(&mut file, region)?;
+            println!(
+                "{region:?}: offset {:#x}, size {:#x}",
+                info.offset, info.size
+            );
+        }
+    }
+    Ok(())
+}
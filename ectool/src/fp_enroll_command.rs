@@ -0,0 +1,43 @@
+use std::{fs::File, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{fp_info::fp_info, fp_set_context::UserId},
+    fp::{
+        enroll::{FpEnrollProgress, FpEnrollment},
+        store::FpTemplateStore,
+    },
+    CROS_FP_PATH,
+};
+
+pub fn fp_enroll_command(
+    user_id: UserId,
+    store: PathBuf,
+    finger: String,
+    timeout: Option<i32>,
+) -> Result<()> {
+    let mut file = File::open(CROS_FP_PATH)?;
+    let fp_info = fp_info(&mut file)?;
+    let mut enrollment = FpEnrollment::new(&mut file, timeout)?;
+    let result = enrollment.run(|progress| match progress {
+        FpEnrollProgress::PlaceFinger => println!("Touch the sensor"),
+        FpEnrollProgress::Touch {
+            percentage,
+            feedback,
+        } => match feedback {
+            Some(feedback) => println!("{percentage}% enrolled, try again: {feedback:?}"),
+            None => println!("{percentage}% enrolled"),
+        },
+        FpEnrollProgress::LiftFinger => println!("Lift your finger"),
+    });
+    let template = match result {
+        Ok(template) => template,
+        Err(e) => {
+            enrollment.cancel()?;
+            return Err(e.into());
+        }
+    };
+    FpTemplateStore::new(store).save(&user_id, &finger, &template, &fp_info)?;
+    println!("Saved the template of {finger}");
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::{fs::File, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{fp_info::fp_info, fp_set_context::UserId},
+    fp::{matcher::FpMatcher, store::FpTemplateStore},
+    wait_event::fingerprint::EcMkbpEventFingerprintMatchResult,
+    CROS_FP_PATH,
+};
+
+pub fn fp_match_command(
+    user_id: UserId,
+    store: PathBuf,
+    fingers: Vec<String>,
+    timeout: Option<i32>,
+    save_updated: bool,
+) -> Result<()> {
+    let store = FpTemplateStore::new(store);
+    let mut file = File::open(CROS_FP_PATH)?;
+    let fp_info = fp_info(&mut file)?;
+    // Loading through the store checks that the templates were made by this kind of FPMCU
+    let fingers = if fingers.is_empty() {
+        store.fingers(&user_id)?
+    } else {
+        fingers
+    };
+    let templates = fingers
+        .iter()
+        .map(|finger| Ok(store.load(&user_id, finger, &fp_info)?.template))
+        .collect::<Result<Vec<_>>>()?;
+    let mut matcher = FpMatcher::new(&mut file, &templates, timeout)?;
+    println!("Touch the sensor");
+    let fp_match = match matcher.match_finger() {
+        Ok(fp_match) => fp_match,
+        Err(e) => {
+            matcher.cancel()?;
+            return Err(e.into());
+        }
+    };
+    match fp_match.result {
+        EcMkbpEventFingerprintMatchResult::Match(result) => {
+            match matcher.template_index(result.index) {
+                Some(index) => println!("Matched {}", fingers[index]),
+                None => println!("Matched template {} in the FPMCU", result.index),
+            }
+            match result.update {
+                None => {}
+                Some(Ok(())) => println!("The template was updated"),
+                Some(Err(())) => println!("The template couldn't be updated"),
+            }
+        }
+        EcMkbpEventFingerprintMatchResult::NoMatch(Ok(())) => println!("No match"),
+        EcMkbpEventFingerprintMatchResult::NoMatch(Err(e)) => println!("No match: {e:?}"),
+    }
+    if save_updated {
+        for (fpmcu_index, template) in fp_match.updated_templates {
+            if let Some(index) = matcher.template_index(fpmcu_index) {
+                store.save(&user_id, &fingers[index], &template, &fp_info)?;
+                println!("Saved the updated template of {}", fingers[index]);
+            }
+        }
+    }
+    Ok(())
+}
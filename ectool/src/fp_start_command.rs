@@ -0,0 +1,35 @@
+use std::{fs::File, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{fp_info::fp_info, fp_set_context::UserId, fp_set_seed::FP_CONTEXT_TPM_BYTES},
+    fp::{session::FpSession, store::FpTemplateStore},
+    CROS_FP_PATH,
+};
+
+pub fn fp_start_command(
+    seed: [u8; FP_CONTEXT_TPM_BYTES],
+    user_id: UserId,
+    store: PathBuf,
+) -> Result<()> {
+    let mut file = File::open(CROS_FP_PATH)?;
+    // Loading through the store checks that the templates were made by this kind of FPMCU
+    let stored = FpTemplateStore::new(store).load_all(&user_id, &fp_info(&mut file)?)?;
+    let templates = stored
+        .into_iter()
+        .map(|(finger, stored)| {
+            println!("Loading {finger}");
+            stored.template
+        })
+        .collect::<Vec<_>>();
+    let session = FpSession::start(&mut file, seed, user_id, &templates)?;
+    if session.seed_set() {
+        println!("Set the seed");
+    }
+    println!(
+        "Set FP context to user id: 0x{} and loaded {} templates",
+        hex::encode(user_id),
+        templates.len()
+    );
+    Ok(())
+}
@@ -1,12 +1,35 @@
 use std::fs::File;
 
-use crosec::commands::get_uptime_info::ec_cmd_get_uptime_info;
+use crosec::commands::get_uptime_info::{ec_cmd_get_uptime_info, ApResetCause};
 
 use crate::Device;
 
 pub fn get_uptime_info_commnad(device: Option<Device>) -> color_eyre::Result<()> {
     let mut file = File::open(device.unwrap_or_default().get_path())?;
     let uptime_info = ec_cmd_get_uptime_info(&mut file)?;
-    println!("Uptime info: {uptime_info:#?}");
+    println!("EC uptime: {:.3}s", uptime_info.uptime().as_secs_f64());
+    println!(
+        "AP resets since EC boot: {}",
+        uptime_info.ap_resets_since_ec_boot
+    );
+    let reset_flags = uptime_info.reset_flags();
+    print!("EC reset flags: {:#010x}", reset_flags.0);
+    for flag in reset_flags.iter() {
+        print!(" {}", <&str>::from(flag));
+    }
+    println!();
+    println!("Recent AP resets:");
+    for entry in uptime_info.ap_resets() {
+        let cause = match entry.cause() {
+            ApResetCause::Reset(reason) => format!("reset: {}", <&str>::from(reason)),
+            ApResetCause::Shutdown(reason) => format!("shutdown: {}", <&str>::from(reason)),
+            ApResetCause::Unknown(reset_cause) => format!("unknown cause {reset_cause}"),
+        };
+        println!(
+            "  {:.3}s ago (at {} ms): {cause}",
+            entry.age(uptime_info.time_since_ec_boot_ms).as_secs_f64(),
+            entry.reset_time_ms
+        );
+    }
     Ok(())
 }
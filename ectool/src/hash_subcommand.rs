@@ -0,0 +1,104 @@
+use std::{fs::File, path::PathBuf, time::Duration};
+
+use clap::{Subcommand, ValueEnum};
+use color_eyre::eyre::{eyre, Result};
+use crosec::{
+    commands::vboot_hash::{
+        sha256, vboot_hash, vboot_hash_abort, vboot_hash_get, vboot_hash_recalc, VbootHash,
+        EC_VBOOT_HASH_OFFSET_ACTIVE, EC_VBOOT_HASH_OFFSET_RO, EC_VBOOT_HASH_OFFSET_UPDATE,
+    },
+    fmap::Fmap,
+    CROS_EC_PATH,
+};
+
+use crate::parse_number::parse_number;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Parses `ro`, `active`, `update`, or a number in decimal, or in hex if it starts with 0x
+fn parse_offset(s: &str) -> Result<u32, String> {
+    match s {
+        "ro" => Ok(EC_VBOOT_HASH_OFFSET_RO),
+        "active" => Ok(EC_VBOOT_HASH_OFFSET_ACTIVE),
+        "update" => Ok(EC_VBOOT_HASH_OFFSET_UPDATE),
+        s => parse_number(s),
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImageRegion {
+    Ro,
+    Rw,
+}
+
+#[derive(Subcommand)]
+pub enum HashSubcommand {
+    /// Prints the last hash
+    Get,
+    /// Stops calculating the hash
+    Abort,
+    /// Hashes flash in the background and waits until it's done
+    Start {
+        /// `ro`, `active`, `update`, or an offset in flash. The EC picks the size for `ro`, `active`, and `update`.
+        #[arg(value_parser = parse_offset)]
+        offset: u32,
+        #[arg(default_value_t = 0)]
+        size: u32,
+    },
+    /// Hashes flash, blocking the EC until it's done
+    Recalc {
+        /// `ro`, `active`, `update`, or an offset in flash. The EC picks the size for `ro`, `active`, and `update`.
+        #[arg(value_parser = parse_offset)]
+        offset: u32,
+        #[arg(default_value_t = 0)]
+        size: u32,
+    },
+    /// Checks that the hash of a region of flash matches the SHA-256 of the same region in an image file
+    Verify {
+        image: PathBuf,
+        #[arg(default_value = "rw")]
+        region: ImageRegion,
+    },
+}
+
+fn print_hash(hash: &VbootHash) {
+    println!("Status: {:?}", hash.status);
+    println!("Offset: {:#010x}", hash.offset);
+    println!("Size:   {:#010x}", hash.size);
+    if !hash.digest.is_empty() {
+        println!("Digest: {}", hex::encode(&hash.digest));
+    }
+}
+
+pub fn hash_subcommand(command: HashSubcommand) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    match command {
+        HashSubcommand::Get => print_hash(&vboot_hash_get(&mut file)?),
+        HashSubcommand::Abort => print_hash(&vboot_hash_abort(&mut file)?),
+        HashSubcommand::Start { offset, size } => {
+            print_hash(&vboot_hash(&mut file, offset, size, POLL_INTERVAL)?)
+        }
+        HashSubcommand::Recalc { offset, size } => {
+            print_hash(&vboot_hash_recalc(&mut file, offset, size)?)
+        }
+        HashSubcommand::Verify { image, region } => {
+            let image = std::fs::read(image)?;
+            let fmap = Fmap::find(&image)?;
+            let name = match region {
+                ImageRegion::Ro => "EC_RO",
+                ImageRegion::Rw => "EC_RW",
+            };
+            let area = fmap.area(name)?;
+            let data = fmap.area_data(&image, name)?;
+            let hash = vboot_hash(&mut file, area.offset, area.size, POLL_INTERVAL)?;
+            print_hash(&hash);
+            println!("Expected digest: {}", hex::encode(sha256(data)));
+            if hash.size == area.size && hash.matches(data) {
+                println!("{name} matches");
+            } else {
+                return Err(eyre!("{name} doesn't match the image"));
+            }
+        }
+    }
+    Ok(())
+}
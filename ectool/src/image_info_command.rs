@@ -0,0 +1,73 @@
+use std::{fs::File, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use crosec::{image::EcImageInfo, CROS_EC_PATH};
+
+pub fn image_info_command(path: PathBuf, compare: bool) -> Result<()> {
+    let image = std::fs::read(path)?;
+    let info = EcImageInfo::parse(&image)?;
+    println!(
+        "FMAP \"{}\" v{}.{} at {:#x}, base {:#x}, size {:#x}",
+        info.fmap.name,
+        info.fmap.ver_major,
+        info.fmap.ver_minor,
+        info.fmap.fmap_offset,
+        info.fmap.base,
+        info.fmap.size
+    );
+    for area in &info.fmap.areas {
+        println!(
+            "  {:<16} offset {:#010x} size {:#010x} flags {:#06x}",
+            area.name, area.offset, area.size, area.flags
+        );
+    }
+    println!(
+        "RO version: {}",
+        info.ro_version.as_deref().unwrap_or("none")
+    );
+    println!(
+        "RW version: {}",
+        info.rw_version.as_deref().unwrap_or("none")
+    );
+    if let Some(key) = &info.key {
+        println!("RO key: {:?}", key.description);
+        println!("  Algorithm: {:?} / {:?}", key.sig_alg, key.hash_alg);
+        println!("  Version:   {}", key.key_version);
+        println!("  ID:        {}", hex::encode(key.id));
+    }
+    if let Some(signature) = &info.signature {
+        println!("RW signature: {:?}", signature.description);
+        println!(
+            "  Algorithm:   {:?} / {:?}",
+            signature.sig_alg, signature.hash_alg
+        );
+        println!("  Signed size: {:#x}", signature.data_size);
+        println!("  Key ID:      {}", hex::encode(signature.id));
+    }
+    if let Some(signed_with_ro_key) = info.signed_with_ro_key() {
+        println!("RW signed with RO key: {signed_with_ro_key}");
+    }
+    if compare {
+        let mut file = File::open(CROS_EC_PATH)?;
+        let comparison = info.compare_with_ec(&mut file)?;
+        println!(
+            "EC RO version: {} ({})",
+            comparison.ec_ro_version,
+            if comparison.ro_matches {
+                "matches"
+            } else {
+                "differs"
+            }
+        );
+        println!(
+            "EC RW version: {} ({})",
+            comparison.ec_rw_version,
+            if comparison.rw_matches {
+                "matches"
+            } else {
+                "differs"
+            }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::{fs::File, path::PathBuf, thread::sleep, time::Duration};
+
+use color_eyre::eyre::{eyre, Result};
+use crosec::{
+    commands::{
+        get_protocol_info::get_protocol_info,
+        keyscan_seq_ctrl::{
+            key_matrix_scan, keyscan_seq_add, keyscan_seq_clear, keyscan_seq_collect,
+            keyscan_seq_start, keyscan_seq_status,
+        },
+    },
+    CROS_EC_PATH, KEYBOARD_COLS_MAX,
+};
+
+/// A step in a key sequence script, which is the state of the whole keyboard matrix at a point in time
+struct Step {
+    time_us: u32,
+    scan: [u8; KEYBOARD_COLS_MAX],
+}
+
+/// Each line of the script is `<time_us> [<row>,<col>]...`, where the time is measured from the start of the sequence,
+/// and the listed keys are pressed at that time. Keys that aren't listed are released. Everything after `#` is ignored.
+fn parse_script(script: &str) -> Result<Vec<Step>> {
+    script
+        .lines()
+        .enumerate()
+        .filter_map(|(line_index, line)| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            (!line.is_empty()).then_some((line_index + 1, line))
+        })
+        .map(|(line_number, line)| {
+            let mut words = line.split_whitespace();
+            let time_us = words
+                .next()
+                .unwrap_or_default()
+                .parse()
+                .map_err(|e| eyre!("Line {line_number}: invalid time: {e}"))?;
+            let keys = words
+                .map(|key| {
+                    let (row, col) = key
+                        .split_once(',')
+                        .ok_or(eyre!("Line {line_number}: key {key:?} must be <row>,<col>"))?;
+                    let row: u8 = row
+                        .parse()
+                        .map_err(|e| eyre!("Line {line_number}: invalid row: {e}"))?;
+                    let col: u8 = col
+                        .parse()
+                        .map_err(|e| eyre!("Line {line_number}: invalid col: {e}"))?;
+                    Ok((row, col))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let scan = key_matrix_scan(keys).map_err(|(row, col)| {
+                eyre!("Line {line_number}: key \"{row},{col}\" is out of range")
+            })?;
+            Ok(Step { time_us, scan })
+        })
+        .collect()
+}
+
+pub fn keyscan_command(script: PathBuf) -> Result<()> {
+    let steps = parse_script(&std::fs::read_to_string(script)?)?;
+    let mut file = File::open(CROS_EC_PATH)?;
+    keyscan_seq_clear(&mut file)?;
+    for Step { time_us, scan } in &steps {
+        keyscan_seq_add(&mut file, *time_us, *scan)?;
+    }
+    println!("Running sequence of {} scans", steps.len());
+    keyscan_seq_start(&mut file)?;
+    while keyscan_seq_status(&mut file)?.active != 0 {
+        sleep(Duration::from_millis(100));
+    }
+    let protocol_info = get_protocol_info(&mut file)?;
+    let items = keyscan_seq_collect(&mut file, &protocol_info)?;
+    let done = items.iter().filter(|item| item.done()).count();
+    println!("{done} of {} scans were done", items.len());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::{fs::File, str::FromStr};
+
+use color_eyre::eyre::{eyre, Result};
+use crosec::{
+    commands::led_control::{
+        led_query, led_set_auto, led_set_brightness, LedBrightness, LedColor, LedId,
+    },
+    CROS_EC_PATH,
+};
+
+/// `action` is either `query`, `auto`, `off`, a color to turn on at full brightness, or a list of `<color>=<brightness>`
+pub fn led_command(led: LedId, action: Vec<String>) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let led_name = <&'static str>::from(led);
+    match action.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["query"] => {
+            let range = led_query(&mut file, led)?;
+            println!("Brightness range for LED {led_name}:");
+            for (color, max_brightness) in range.iter() {
+                let color_name = <&'static str>::from(color);
+                println!("  {color_name:8}: 0x{max_brightness:x}");
+            }
+        }
+        ["auto"] => {
+            led_set_auto(&mut file, led)?;
+            println!("LED {led_name} set to auto");
+        }
+        ["off"] => {
+            led_set_brightness(&mut file, led, Default::default())?;
+            println!("LED {led_name} turned off");
+        }
+        [] => return Err(eyre!("Specify query, auto, off, or a color")),
+        ref colors => {
+            let mut brightness = LedBrightness::default();
+            for color in colors {
+                let (color, value) = match color.split_once('=') {
+                    Some((color, value)) => (
+                        color,
+                        value
+                            .parse::<u8>()
+                            .map_err(|e| eyre!("Invalid brightness for {color}: {e}"))?,
+                    ),
+                    None => (*color, u8::MAX),
+                };
+                brightness.set(
+                    LedColor::from_str(color).map_err(|_| eyre!("Unknown color: {color}"))?,
+                    value,
+                );
+            }
+            led_set_brightness(&mut file, led, brightness)?;
+            println!("LED {led_name} set to {brightness:?}");
+        }
+    }
+    Ok(())
+}
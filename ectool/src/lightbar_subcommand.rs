@@ -0,0 +1,188 @@
+use std::{fs::File, mem::size_of, path::PathBuf};
+
+use bytemuck::{pod_read_unaligned, Pod};
+use clap::Subcommand;
+use color_eyre::eyre::{eyre, Result};
+use crosec::{
+    commands::lightbar::{
+        lightbar_demo, lightbar_dump, lightbar_get_brightness, lightbar_get_demo,
+        lightbar_get_params_v0, lightbar_get_params_v1, lightbar_get_params_v2, lightbar_get_rgb,
+        lightbar_get_seq, lightbar_init, lightbar_manual_suspend_ctrl, lightbar_off, lightbar_on,
+        lightbar_reg, lightbar_resume, lightbar_seq, lightbar_set_brightness,
+        lightbar_set_params_v0, lightbar_set_params_v1, lightbar_set_params_v2,
+        lightbar_set_program, lightbar_set_rgb, lightbar_suspend, lightbar_version,
+        LightbarParamsV0, LightbarParamsV1, LightbarParamsV2, LightbarSequence, Rgb,
+        EC_LB_PROG_LEN, NUM_LEDS,
+    },
+    CROS_EC_PATH,
+};
+
+#[derive(Subcommand)]
+pub enum LightbarSubcommand {
+    On,
+    Off,
+    Init,
+    /// Only works with manual suspend control enabled
+    Suspend,
+    /// Only works with manual suspend control enabled
+    Resume,
+    /// Prints the registers of the LED controllers
+    Dump,
+    /// Prints or sets the brightness
+    Brightness {
+        brightness: Option<u8>,
+    },
+    /// Prints or sets the current sequence
+    Seq {
+        sequence: Option<LightbarSequence>,
+    },
+    /// Writes to a register of an LED controller
+    Reg {
+        ctrl: u8,
+        reg: u8,
+        value: u8,
+    },
+    /// Prints or sets the color of an LED. Use LED 4 to set all of them.
+    Rgb {
+        led: u8,
+        #[arg(requires_all = ["green", "blue"])]
+        red: Option<u8>,
+        green: Option<u8>,
+        blue: Option<u8>,
+    },
+    /// Prints or sets whether demo mode is on
+    Demo {
+        enable: Option<bool>,
+    },
+    /// Prints the params, or sets them from a binary file in the format of the params version the EC supports
+    Params {
+        #[arg(long)]
+        set: Option<PathBuf>,
+    },
+    /// Prints the lightbar version
+    Version,
+    /// Controls whether the lightbar waits for `suspend` and `resume` instead of following the chipset state
+    ManualSuspend {
+        enable: bool,
+    },
+    /// Uploads a binary lightbar program. Run it with `seq program`.
+    Program {
+        file: PathBuf,
+    },
+}
+
+fn read_params<T: Pod>(path: &PathBuf) -> Result<T> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() != size_of::<T>() {
+        return Err(eyre!(
+            "Expected {} bytes of params, but the file is {} bytes",
+            size_of::<T>(),
+            bytes.len()
+        ));
+    }
+    Ok(pod_read_unaligned(&bytes))
+}
+
+pub fn lightbar_subcommand(command: LightbarSubcommand) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    match command {
+        LightbarSubcommand::On => lightbar_on(&mut file)?,
+        LightbarSubcommand::Off => lightbar_off(&mut file)?,
+        LightbarSubcommand::Init => lightbar_init(&mut file)?,
+        LightbarSubcommand::Suspend => lightbar_suspend(&mut file)?,
+        LightbarSubcommand::Resume => lightbar_resume(&mut file)?,
+        LightbarSubcommand::Dump => {
+            for value in lightbar_dump(&mut file)? {
+                println!(
+                    " {:02x}     {:02x}     {:02x}",
+                    value.reg, value.ic0, value.ic1
+                );
+            }
+        }
+        LightbarSubcommand::Brightness { brightness } => match brightness {
+            Some(brightness) => {
+                lightbar_set_brightness(&mut file, brightness)?;
+                println!("Set brightness to {brightness}");
+            }
+            None => {
+                let brightness = lightbar_get_brightness(&mut file)?;
+                println!("Brightness: {brightness}");
+            }
+        },
+        LightbarSubcommand::Seq { sequence } => match sequence {
+            Some(sequence) => {
+                lightbar_seq(&mut file, sequence)?;
+                println!("Set sequence to {}", <&'static str>::from(sequence));
+            }
+            None => match lightbar_get_seq(&mut file)? {
+                Ok(sequence) => println!("Sequence: {}", <&'static str>::from(sequence)),
+                Err(sequence) => println!("Sequence: unknown ({sequence})"),
+            },
+        },
+        LightbarSubcommand::Reg { ctrl, reg, value } => {
+            lightbar_reg(&mut file, ctrl, reg, value)?;
+        }
+        LightbarSubcommand::Rgb {
+            led,
+            red,
+            green,
+            blue,
+        } => match (red, green, blue) {
+            (Some(r), Some(g), Some(b)) => {
+                lightbar_set_rgb(&mut file, led, Rgb { r, g, b })?;
+            }
+            _ => {
+                if led >= NUM_LEDS {
+                    return Err(eyre!("Specify an LED from 0 to {}", NUM_LEDS - 1));
+                }
+                let Rgb { r, g, b } = lightbar_get_rgb(&mut file, led)?;
+                println!("{r:02x} {g:02x} {b:02x}");
+            }
+        },
+        LightbarSubcommand::Demo { enable } => match enable {
+            Some(enable) => lightbar_demo(&mut file, enable)?,
+            None => {
+                let demo = lightbar_get_demo(&mut file)?;
+                println!("Demo mode is {}", if demo { "on" } else { "off" });
+            }
+        },
+        LightbarSubcommand::Params { set } => {
+            let version = lightbar_version(&mut file)?.num;
+            match (version, set) {
+                (0, None) => println!("{:#?}", lightbar_get_params_v0(&mut file)?),
+                (1, None) => println!("{:#?}", lightbar_get_params_v1(&mut file)?),
+                (2, None) => println!("{:#?}", lightbar_get_params_v2(&mut file)?),
+                (0, Some(path)) => {
+                    lightbar_set_params_v0(&mut file, &read_params::<LightbarParamsV0>(&path)?)?
+                }
+                (1, Some(path)) => {
+                    lightbar_set_params_v1(&mut file, &read_params::<LightbarParamsV1>(&path)?)?
+                }
+                (2, Some(path)) => {
+                    lightbar_set_params_v2(&mut file, &read_params::<LightbarParamsV2>(&path)?)?
+                }
+                (version, _) => return Err(eyre!("Unsupported params version: {version}")),
+            }
+        }
+        LightbarSubcommand::Version => {
+            let version = lightbar_version(&mut file)?;
+            println!("Version: {}", version.num);
+            println!("Flags:   {:#x}", version.flags);
+        }
+        LightbarSubcommand::ManualSuspend { enable } => {
+            lightbar_manual_suspend_ctrl(&mut file, enable)?;
+        }
+        LightbarSubcommand::Program { file: path } => {
+            let program = std::fs::read(path)?;
+            if program.len() > EC_LB_PROG_LEN {
+                return Err(eyre!(
+                    "The program is {} bytes, but the max size is {EC_LB_PROG_LEN} bytes",
+                    program.len()
+                ));
+            }
+            lightbar_set_program(&mut file, &program)?;
+            println!("Uploaded {} byte program", program.len());
+        }
+    }
+    Ok(())
+}
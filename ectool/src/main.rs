@@ -1,13 +1,19 @@
 #![warn(unused_crate_dependencies)]
 
 use std::fs::File;
+use std::path::PathBuf;
 
 use charge_control_subcommand::{charge_control_subcommand, ChargeControlSubcommand};
 use charge_current_limit_subcommand::charge_current_limit_subcommand;
+use charge_port_command::{
+    charge_override_command, charge_port_count_command, pd_chip_info_command,
+    usb_charge_mode_command, usb_pd_dev_info_command, ChargeOverrideArg,
+};
 use check_seed::check_seed;
 use check_user_id::check_user_id;
 use clap::{Parser, Subcommand, ValueEnum};
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
+use console_command::console_command;
 use crosec::commands::fp_info::fp_info;
 use crosec::commands::fp_mode::{fp_mode, FpMode};
 use crosec::commands::fp_set_context::UserId;
@@ -15,29 +21,62 @@ use crosec::commands::fp_set_seed::{fp_set_seed, FP_CONTEXT_TPM_BYTES};
 use crosec::commands::fp_stats::fp_stats;
 use crosec::commands::get_protocol_info::get_protocol_info;
 use crosec::wait_event::{event::EcMkbpEventType, wait_event_sync};
+use flash_rw_command::flash_rw_command;
+use flash_subcommand::{flash_subcommand, FlashSubcommand};
 use fp_download_subcommand::{fp_download_subcommand, FpDownloadSubcommand};
+use fp_enroll_command::fp_enroll_command;
+use fp_match_command::fp_match_command;
 use fp_set_context_command::fp_context_command;
+use fp_start_command::fp_start_command;
 use fp_upload_template_command::fp_upload_template_command;
 use get_uptime_info_command::get_uptime_info_commnad;
+use hash_subcommand::{hash_subcommand, HashSubcommand};
+use image_info_command::image_info_command;
+use keyscan_command::keyscan_command;
+use led_command::led_command;
+use lightbar_subcommand::{lightbar_subcommand, LightbarSubcommand};
+use mkbp_config_subcommand::{mkbp_config_subcommand, MkbpConfigSubcommand};
+use motion_sense_subcommand::{motion_sense_subcommand, MotionSenseSubcommand};
 use num_traits::cast::FromPrimitive;
+use panic_info_command::panic_info_command;
+use pd_log_command::pd_log_command;
+use reboot_command::{hibernation_delay_command, reboot_ap_on_g3_command, reboot_ec_command};
 use strum::IntoEnumIterator;
+use typec_control_subcommand::{typec_control_subcommand, TypecControlSubcommand};
+use uart_console_command::uart_console_command;
+use usb_pd_command::{
+    typec_discovery_command, typec_status_command, usb_pd_command, usb_pd_power_command,
+};
 
 use crate::fp_get_encryption_status_command::fp_get_encryption_status_command;
 use crosec::battery::battery;
 use crosec::commands::board_version::ec_cmd_board_version;
+use crosec::commands::charge_port::UsbChargeMode;
 use crosec::commands::get_cmd_versions::ec_cmd_get_cmd_versions;
 use crosec::commands::get_features::{ec_cmd_get_features, EC_FEATURE_PWM_FAN};
 use crosec::commands::get_keyboard_config::{
-    ec_cmd_get_keyboard_config, ActionKey, KEYBD_CAP_ASSISTANT_KEY, KEYBD_CAP_FUNCTION_KEYS,
-    KEYBD_CAP_NUMERIC_KEYPAD, KEYBD_CAP_SCRNLOCK_KEY,
+    ec_cmd_get_keyboard_config, KeybdCapability, TopRowMapping,
 };
+use crosec::commands::keyboard_backlight::{
+    pwm_get_keyboard_backlight, pwm_set_keyboard_backlight,
+};
+use crosec::commands::led_control::LedId;
+use crosec::commands::mkbp_info::mkbp_get_switches;
+use crosec::commands::mkbp_simulate_key::mkbp_simulate_key;
+use crosec::commands::pwm_duty::{pwm_get_duty, pwm_set_duty, PwmType, EC_PWM_MAX_DUTY};
+use crosec::commands::reboot_ec::{RebootEcCommand, RebootEcFlag};
+use crosec::commands::rwsig::{rwsig_action, rwsig_check_status, RwsigAction};
 use crosec::commands::set_fan_target_rpm::ec_cmd_set_fan_target_rpm;
+use crosec::commands::set_tablet_mode::{set_tablet_mode, TabletMode};
+use crosec::commands::usb_pd::{
+    TypecPartnerType, UsbPdControlMux, UsbPdControlRole, UsbPdControlSwap,
+};
 use crosec::commands::{
     get_chip_info::ec_cmd_get_chip_info, hello::ec_cmd_hello, version::ec_cmd_version, CrosEcCmd,
 };
-use crosec::console::console;
 use crosec::get_number_of_fans::{get_number_of_fans, Error};
 use crosec::read_mem_any::read_mem_any;
+use crosec::switches::{switches, tablet_mode};
 use crosec::{
     CROS_EC_PATH, CROS_FP_PATH, EC_FAN_SPEED_ENTRIES, EC_FAN_SPEED_NOT_PRESENT,
     EC_FAN_SPEED_STALLED, EC_MEM_MAP_FAN,
@@ -45,13 +84,34 @@ use crosec::{
 
 mod charge_control_subcommand;
 mod charge_current_limit_subcommand;
+mod charge_port_command;
 mod check_seed;
 mod check_user_id;
+mod console_command;
+mod flash_rw_command;
+mod flash_subcommand;
 mod fp_download_subcommand;
+mod fp_enroll_command;
 mod fp_get_encryption_status_command;
+mod fp_match_command;
 mod fp_set_context_command;
+mod fp_start_command;
 mod fp_upload_template_command;
 mod get_uptime_info_command;
+mod hash_subcommand;
+mod image_info_command;
+mod keyscan_command;
+mod led_command;
+mod lightbar_subcommand;
+mod mkbp_config_subcommand;
+mod motion_sense_subcommand;
+mod panic_info_command;
+mod parse_number;
+mod pd_log_command;
+mod reboot_command;
+mod typec_control_subcommand;
+mod uart_console_command;
+mod usb_pd_command;
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -67,6 +127,12 @@ enum Device {
     Fp,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum KeyState {
+    Press,
+    Release,
+}
+
 impl Device {
     pub fn get_path(&self) -> &'static str {
         match self {
@@ -103,6 +169,13 @@ enum Commands {
     GetFeatures,
     // Gets vivaldi keyboarc configuration
     GetKeybdConfig,
+    /// Prints a udev hwdb entry for the vivaldi top row keys
+    KeybdHwdb {
+        /// The hwdb match line
+        #[arg(long, default_value = "evdev:atkbd:dmi:bvn*:bvr*:bd*:svnGoogle:*")]
+        evdev_match: String,
+        mapping: Option<TopRowMapping>,
+    },
     /// Get number of fans
     GetNumberOfFans,
     /// Get the speed of fans, in RPM
@@ -111,6 +184,12 @@ enum Commands {
     Console {
         #[arg()]
         device: Option<Device>,
+        /// Keep printing new output, with the time since following started
+        #[arg(short, long)]
+        follow: bool,
+        /// How often to check for new output in milliseconds
+        #[arg(long, default_value_t = 100, requires = "follow")]
+        interval: u64,
     },
     /// Prints battery info
     Battery,
@@ -155,6 +234,246 @@ enum Commands {
         #[arg(value_parser = check_user_id)]
         user_id: UserId,
     },
+    /// Simulates pressing or releasing a key in the keyboard matrix
+    SimulateKey {
+        row: u8,
+        col: u8,
+        state: KeyState,
+    },
+    /// Prints or sets the keyboard scan config
+    MkbpConfig {
+        #[command(subcommand)]
+        command: Option<MkbpConfigSubcommand>,
+    },
+    /// Plays a timed key sequence from a script file.
+    /// Each line is `<time_us> [<row>,<col>]...` with the keys that are pressed at that time.
+    Keyscan {
+        script: PathBuf,
+    },
+    /// Prints the keyboard backlight brightness
+    PwmGetKbLight,
+    /// Sets the keyboard backlight brightness
+    PwmSetKbLight {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Prints the PWM duty cycle
+    PwmGetDuty {
+        pwm_type: PwmType,
+        /// Only used for the generic PWM type
+        index: Option<u8>,
+    },
+    /// Sets the PWM duty cycle
+    PwmSetDuty {
+        pwm_type: PwmType,
+        /// Out of 65535
+        duty: u16,
+        /// Only used for the generic PWM type
+        index: Option<u8>,
+    },
+    /// Controls an LED. The action can be `query`, `auto`, `off`, a color, or a list of `<color>=<brightness>`
+    Led {
+        led: LedId,
+        action: Vec<String>,
+    },
+    /// Controls the lightbar
+    Lightbar {
+        #[command(subcommand)]
+        command: LightbarSubcommand,
+    },
+    /// Reads and configures the motion sensors
+    MotionSense {
+        #[command(subcommand)]
+        command: MotionSenseSubcommand,
+    },
+    /// Forces tablet or clamshell mode
+    SetTabletMode {
+        mode: TabletMode,
+    },
+    /// Prints the state of the lid, power button, write protect, and tablet mode switches
+    Switches,
+    /// Prints whether the device is in tablet mode every time it changes
+    WatchTabletMode,
+    /// Prints the status of a USB-PD port, optionally changing its role or mux or doing a swap
+    #[command(visible_alias = "usbpd")]
+    UsbPd {
+        port: u8,
+        #[arg(long, default_value = "no-change")]
+        role: UsbPdControlRole,
+        #[arg(long, default_value = "no-change")]
+        mux: UsbPdControlMux,
+        #[arg(long, default_value = "none")]
+        swap: UsbPdControlSwap,
+    },
+    /// Prints the power info of every USB-PD port
+    #[command(visible_alias = "usbpdpower")]
+    UsbPdPower,
+    /// Prints the Type-C status of a port, including the source and sink capabilities
+    #[command(visible_alias = "typecstatus")]
+    TypecStatus {
+        port: u8,
+    },
+    /// Prints the identity and SVIDs discovered from the port partner or cable
+    #[command(visible_alias = "typecdiscovery")]
+    TypecDiscovery {
+        port: u8,
+        #[arg(default_value = "sop")]
+        partner_type: TypecPartnerType,
+    },
+    /// Controls mode entry, the mux, and VDMs on a Type-C port
+    #[command(visible_alias = "typeccontrol")]
+    TypecControl {
+        port: u8,
+        #[command(subcommand)]
+        command: TypecControlSubcommand,
+    },
+    /// Prints and clears the PD event log
+    #[command(visible_alias = "pdlog")]
+    PdLog {
+        /// Keep printing new entries as the EC reports them
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Prints the vendor, product, and firmware version of the TCPC of a port, or of every port
+    #[command(visible_alias = "pdchipinfo")]
+    PdChipInfo {
+        port: Option<u8>,
+        /// Read the info from the chip instead of the EC's cache
+        #[arg(long)]
+        live: bool,
+    },
+    /// Prints the ID and firmware of an updatable device, such as a dock, connected to a port
+    #[command(visible_alias = "infopddev")]
+    UsbPdDevInfo {
+        port: u8,
+    },
+    /// Prints the number of ports which the device can charge from
+    #[command(visible_alias = "chargeportcount")]
+    ChargePortCount,
+    /// Forces charging from a port. Use `off` to let the EC choose, or `dontcharge` to not charge.
+    #[command(visible_alias = "chargeoverride")]
+    ChargeOverride {
+        port: ChargeOverrideArg,
+    },
+    /// Sets the charging mode of a USB-A port
+    #[command(visible_alias = "usbchargemode")]
+    UsbChargeMode {
+        port: u8,
+        mode: UsbChargeMode,
+        /// Don't charge until the mode is set again
+        #[arg(long)]
+        inhibit_charge: bool,
+    },
+    /// Reads, writes, erases, and protects the EC's flash
+    Flash {
+        #[command(subcommand)]
+        command: FlashSubcommand,
+    },
+    /// Updates the RW image of the EC from a full EC image, jumping to RO while the RW image is written
+    FlashRw {
+        image: PathBuf,
+    },
+    /// Prints the FMAP, versions, and RW signature of an EC image file
+    ImageInfo {
+        file: PathBuf,
+        /// Compare the versions with the running EC
+        #[arg(long)]
+        compare: bool,
+    },
+    /// Hashes flash with the EC and compares it with an image
+    Hash {
+        #[command(subcommand)]
+        command: HashSubcommand,
+    },
+    /// Checks the signature of the RW image
+    #[command(visible_alias = "rwsigstatus")]
+    RwsigStatus,
+    /// Aborts or continues jumping to RW while RO is waiting to jump
+    #[command(visible_alias = "rwsigaction")]
+    RwsigAction {
+        action: RwsigAction,
+    },
+    /// Reboots the EC or jumps to another image
+    #[command(visible_alias = "reboot_ec")]
+    RebootEc {
+        command: RebootEcCommand,
+        #[arg(long = "flag")]
+        flags: Vec<RebootEcFlag>,
+        /// Wait until the EC is ready to accept commands again
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Makes the EC boot the AP again after it shuts down
+    #[command(visible_alias = "reboot_ap_on_g3")]
+    RebootApOnG3 {
+        /// How many seconds to wait after the AP shuts down
+        delay: Option<u64>,
+    },
+    /// Gets or sets how long the EC waits after the AP shuts down before hibernating
+    #[command(visible_alias = "hibdelay")]
+    HibernationDelay {
+        /// The new delay in seconds
+        #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+        seconds: Option<u64>,
+    },
+    /// Prints why the EC last panicked
+    #[command(visible_alias = "panicinfo")]
+    PanicInfo {
+        /// Decode panic data which was saved to a file instead of getting it from the EC
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Also print the panic data in hex
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Runs an EC console command over a UART, such as a servo's /dev/ttyUSBx
+    UartConsole {
+        port: PathBuf,
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        /// How long to wait for the command to finish in milliseconds
+        #[arg(long, default_value_t = 2000)]
+        timeout: u64,
+    },
+    /// Enrolls a finger and saves the template to a template store
+    FpEnroll {
+        /// A 32 byte hex string
+        #[arg(value_parser = check_user_id)]
+        user_id: UserId,
+        /// The directory of a template store, which has the templates of each user
+        store: PathBuf,
+        /// The name to save the template as, such as "left-index"
+        finger: String,
+        /// How long to wait for each touch in milliseconds
+        #[arg(short, long)]
+        timeout: Option<i32>,
+    },
+    /// Matches a finger against templates which were saved to a template store
+    FpMatch {
+        /// A 32 byte hex string
+        #[arg(value_parser = check_user_id)]
+        user_id: UserId,
+        /// The directory of a template store, which has the templates of each user
+        store: PathBuf,
+        /// Fingers to upload after the templates which are already loaded. All of the user's fingers if none are given.
+        fingers: Vec<String>,
+        /// How long to wait for a touch in milliseconds
+        #[arg(short, long)]
+        timeout: Option<i32>,
+        /// Save templates which the FPMCU updated while matching back to the store
+        #[arg(long)]
+        save_updated: bool,
+    },
+    /// Sets the seed if it isn't set, sets the context, and loads the user's saved templates, which is what the FPMCU needs before enrolling or matching
+    FpStart {
+        #[arg(value_parser = check_seed)]
+        seed: [u8; FP_CONTEXT_TPM_BYTES],
+        /// A 32 byte hex string
+        #[arg(value_parser = check_user_id)]
+        user_id: UserId,
+        /// The directory of a template store, which has the templates of each user
+        store: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
@@ -228,49 +547,36 @@ fn main() -> Result<()> {
             let config = ec_cmd_get_keyboard_config(&mut file)?;
             println!("Number of top row keys: {}", config.num_top_row_keys);
             println!("Keys:");
-            for i in 0..config.num_top_row_keys as usize {
-                match FromPrimitive::from_u8(config.action_keys[i]) {
-                    Some(ActionKey::TkAbsent) => println!("Absent"),
-                    Some(ActionKey::TkBack) => println!("Back"),
-                    Some(ActionKey::TkForward) => println!("Forward"),
-                    Some(ActionKey::TkRefresh) => println!("Refresh"),
-                    Some(ActionKey::TkFullscreen) => println!("Fullscreen"),
-                    Some(ActionKey::TkOverview) => println!("Overview"),
-                    Some(ActionKey::TkBrightnessDown) => println!("Brightness Down"),
-                    Some(ActionKey::TkBrightnessUp) => println!("Brightness Up"),
-                    Some(ActionKey::TkVolMute) => println!("Volume Mute"),
-                    Some(ActionKey::TkVolDown) => println!("Volume Down"),
-                    Some(ActionKey::TkVolUp) => println!("Volume Up"),
-                    Some(ActionKey::TkSnapshot) => println!("Snapshot"),
-                    Some(ActionKey::TkPrivacyScrnToggle) => println!("Privacy Screen Toggle"),
-                    Some(ActionKey::TkKbdBklightDown) => println!("Keyboard Backlight Down"),
-                    Some(ActionKey::TkKbdBklightUp) => println!("Keyboard Backlight Up"),
-                    Some(ActionKey::TkPlayPause) => println!("Play/Pause"),
-                    Some(ActionKey::TkNextTrack) => println!("Next Track"),
-                    Some(ActionKey::TkPrevTrack) => println!("Previous Track"),
-                    Some(ActionKey::TkKbdBklightToggle) => println!("Keyboard Backlight Toggle"),
-                    Some(ActionKey::TkMicmute) => println!("Mic Mute"),
-                    Some(ActionKey::TkMenu) => println!("Menu"),
-                    Some(ActionKey::TkDictate) => println!("Dictate"),
-                    Some(ActionKey::TkAccessibility) => println!("Accessibility"),
-                    Some(ActionKey::TkDonotdisturb) => println!("No Not Disturb"),
-                    None => println!("Unknown"),
+            for key in config.top_row_keys() {
+                match key.map(|key| (key, key.linux_key())) {
+                    Ok((key, Some(linux_key))) => {
+                        println!("{key} (KEY_{})", linux_key.name.to_uppercase())
+                    }
+                    Ok((key, None)) => println!("{key}"),
+                    Err(_) => println!("Unknown"),
                 }
             }
             println!("Capabilities: {:#x}", config.capabilities);
-            if config.capabilities & KEYBD_CAP_FUNCTION_KEYS >= 1 {
-                println!("KEYBD_CAP_FUNCTION_KEYS");
-            }
-            if config.capabilities & KEYBD_CAP_NUMERIC_KEYPAD >= 1 {
-                println!("KEYBD_CAP_NUMERIC_KEYPAD");
-            }
-            if config.capabilities & KEYBD_CAP_SCRNLOCK_KEY >= 1 {
-                println!("KEYBD_CAP_SCRNLOCK_KEY");
-            }
-            if config.capabilities & KEYBD_CAP_ASSISTANT_KEY >= 1 {
-                println!("KEYBD_CAP_ASSISTANT_KEY");
+            for capability in config.capabilities() {
+                println!(
+                    "{}",
+                    match capability {
+                        KeybdCapability::FunctionKeys => "KEYBD_CAP_FUNCTION_KEYS",
+                        KeybdCapability::NumericKeypad => "KEYBD_CAP_NUMERIC_KEYPAD",
+                        KeybdCapability::ScrnlockKey => "KEYBD_CAP_SCRNLOCK_KEY",
+                        KeybdCapability::AssistantKey => "KEYBD_CAP_ASSISTANT_KEY",
+                    }
+                );
             }
         }
+        Commands::KeybdHwdb {
+            evdev_match,
+            mapping,
+        } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            let config = ec_cmd_get_keyboard_config(&mut file)?;
+            print!("{}", config.hwdb(&evdev_match, mapping.unwrap_or_default()));
+        }
         Commands::GetNumberOfFans => {
             let mut file = File::open(CROS_EC_PATH)?;
             let number_of_fans = get_number_of_fans(&mut file).unwrap();
@@ -297,13 +603,11 @@ fn main() -> Result<()> {
                 println!("No fans");
             };
         }
-        Commands::Console { device } => {
-            let mut file = File::open(device.unwrap_or_default().get_path())?;
-            let max_sizes = get_protocol_info(&mut file)?;
-            let console = console(&mut file, &max_sizes)?;
-            let console = console.trim();
-            println!("{console}");
-        }
+        Commands::Console {
+            device,
+            follow,
+            interval,
+        } => console_command(device, follow, interval)?,
         Commands::Battery => {
             let mut file = File::open(CROS_EC_PATH)?;
             let battery_info = battery(&mut file)?;
@@ -359,6 +663,160 @@ fn main() -> Result<()> {
         Commands::GetUptimeInfo { device } => get_uptime_info_commnad(device)?,
         Commands::ChargeCurrentLimit { limit } => charge_current_limit_subcommand(limit)?,
         Commands::FpSetContext { user_id } => fp_context_command(user_id)?,
+        Commands::SimulateKey { row, col, state } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            let pressed = matches!(state, KeyState::Press);
+            mkbp_simulate_key(&mut file, row, col, pressed)?;
+            if pressed {
+                println!("Pressed key at row {row}, col {col}");
+            } else {
+                println!("Released key at row {row}, col {col}");
+            }
+        }
+        Commands::MkbpConfig { command } => mkbp_config_subcommand(command)?,
+        Commands::Keyscan { script } => keyscan_command(script)?,
+        Commands::PwmGetKbLight => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            let backlight = pwm_get_keyboard_backlight(&mut file)?;
+            if backlight.enabled {
+                println!("Current keyboard backlight: {}%", backlight.percent);
+            } else {
+                println!("Keyboard backlight disabled");
+            }
+        }
+        Commands::PwmSetKbLight { percent } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            pwm_set_keyboard_backlight(&mut file, percent)?;
+            println!("Keyboard backlight set to {percent}%");
+        }
+        Commands::PwmGetDuty { pwm_type, index } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            let duty = pwm_get_duty(&mut file, pwm_type, index.unwrap_or_default())?;
+            println!(
+                "Current PWM duty: {duty} ({}%)",
+                duty as u32 * 100 / EC_PWM_MAX_DUTY as u32
+            );
+        }
+        Commands::PwmSetDuty {
+            pwm_type,
+            duty,
+            index,
+        } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            pwm_set_duty(&mut file, pwm_type, index.unwrap_or_default(), duty)?;
+            println!("Set PWM duty to {duty}");
+        }
+        Commands::Led { led, action } => led_command(led, action)?,
+        Commands::Lightbar { command } => lightbar_subcommand(command)?,
+        Commands::MotionSense { command } => motion_sense_subcommand(command)?,
+        Commands::SetTabletMode { mode } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            set_tablet_mode(&mut file, mode)?;
+            println!("Set tablet mode to {mode:?}");
+        }
+        Commands::Switches => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            let switches = switches(&mut file).map_err(|e| eyre!("Error reading memory: {e}"))?;
+            println!("Switches: {:#04x}", switches.0);
+            println!("  Lid open:               {}", switches.lid_open());
+            println!(
+                "  Power button pressed:   {}",
+                switches.power_button_pressed()
+            );
+            println!(
+                "  Write protect disabled: {}",
+                switches.write_protect_disabled()
+            );
+            println!(
+                "  Dedicated recovery:     {}",
+                switches.dedicated_recovery()
+            );
+            let mkbp_switches = mkbp_get_switches(&mut file)?;
+            println!("  Tablet mode:            {}", mkbp_switches.tablet_mode());
+            println!(
+                "  Base attached:          {}",
+                mkbp_switches.base_attached()
+            );
+        }
+        Commands::WatchTabletMode => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            for tablet_mode in tablet_mode(&mut file) {
+                if tablet_mode? {
+                    println!("Tablet mode");
+                } else {
+                    println!("Clamshell mode");
+                }
+            }
+        }
+        Commands::UsbPd {
+            port,
+            role,
+            mux,
+            swap,
+        } => usb_pd_command(port, role, mux, swap)?,
+        Commands::UsbPdPower => usb_pd_power_command()?,
+        Commands::TypecStatus { port } => typec_status_command(port)?,
+        Commands::TypecDiscovery { port, partner_type } => {
+            typec_discovery_command(port, partner_type)?
+        }
+        Commands::TypecControl { port, command } => typec_control_subcommand(port, command)?,
+        Commands::PdLog { follow } => pd_log_command(follow)?,
+        Commands::PdChipInfo { port, live } => pd_chip_info_command(port, live)?,
+        Commands::UsbPdDevInfo { port } => usb_pd_dev_info_command(port)?,
+        Commands::ChargePortCount => charge_port_count_command()?,
+        Commands::ChargeOverride { port } => charge_override_command(port)?,
+        Commands::UsbChargeMode {
+            port,
+            mode,
+            inhibit_charge,
+        } => usb_charge_mode_command(port, mode, inhibit_charge)?,
+        Commands::Flash { command } => flash_subcommand(command)?,
+        Commands::FlashRw { image } => flash_rw_command(image)?,
+        Commands::ImageInfo { file, compare } => image_info_command(file, compare)?,
+        Commands::Hash { command } => hash_subcommand(command)?,
+        Commands::RwsigStatus => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            if rwsig_check_status(&mut file)? {
+                println!("RW signature check: OK");
+            } else {
+                println!("RW signature check: FAILED");
+            }
+        }
+        Commands::RwsigAction { action } => {
+            let mut file = File::open(CROS_EC_PATH)?;
+            rwsig_action(&mut file, action)?;
+        }
+        Commands::RebootEc {
+            command,
+            flags,
+            wait,
+        } => reboot_ec_command(command, flags, wait)?,
+        Commands::RebootApOnG3 { delay } => reboot_ap_on_g3_command(delay)?,
+        Commands::HibernationDelay { seconds } => hibernation_delay_command(seconds)?,
+        Commands::PanicInfo { file, raw } => panic_info_command(file, raw)?,
+        Commands::UartConsole {
+            port,
+            command,
+            timeout,
+        } => uart_console_command(port, command, timeout)?,
+        Commands::FpEnroll {
+            user_id,
+            store,
+            finger,
+            timeout,
+        } => fp_enroll_command(user_id, store, finger, timeout)?,
+        Commands::FpMatch {
+            user_id,
+            store,
+            fingers,
+            timeout,
+            save_updated,
+        } => fp_match_command(user_id, store, fingers, timeout, save_updated)?,
+        Commands::FpStart {
+            seed,
+            user_id,
+            store,
+        } => fp_start_command(seed, user_id, store)?,
     }
 
     Ok(())
@@ -0,0 +1,92 @@
+use std::fs::File;
+
+use clap::Subcommand;
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::mkbp_config::{
+        mkbp_get_config, mkbp_set_config, EcMkbpConfig, SetMkbpConfig, EC_MKBP_FLAGS_ENABLE,
+    },
+    CROS_EC_PATH,
+};
+
+#[derive(Subcommand)]
+pub enum MkbpConfigSubcommand {
+    /// Only the options that are specified will be changed
+    Set {
+        #[arg(long)]
+        enabled: Option<bool>,
+        /// Period between the start of scans
+        #[arg(long)]
+        scan_period_us: Option<u16>,
+        /// Revert to interrupt mode after no activity for this long
+        #[arg(long)]
+        poll_timeout_us: Option<u32>,
+        #[arg(long)]
+        min_post_scan_delay_us: Option<u16>,
+        #[arg(long)]
+        output_settle_us: Option<u16>,
+        #[arg(long)]
+        debounce_down_us: Option<u16>,
+        #[arg(long)]
+        debounce_up_us: Option<u16>,
+        /// Maximum depth of the key fifo (0 = no keyscan output)
+        #[arg(long)]
+        fifo_max_depth: Option<u8>,
+    },
+}
+
+pub fn mkbp_config_subcommand(command: Option<MkbpConfigSubcommand>) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    match command {
+        None => {
+            let EcMkbpConfig {
+                flags,
+                scan_period_us,
+                poll_timeout_us,
+                min_post_scan_delay_us,
+                output_settle_us,
+                debounce_down_us,
+                debounce_up_us,
+                fifo_max_depth,
+                ..
+            } = mkbp_get_config(&mut file)?;
+            println!(
+                "Enabled:                {}",
+                flags & EC_MKBP_FLAGS_ENABLE != 0
+            );
+            println!("Scan period:            {scan_period_us} us");
+            println!("Poll timeout:           {poll_timeout_us} us");
+            println!("Min post scan delay:    {min_post_scan_delay_us} us");
+            println!("Output settle:          {output_settle_us} us");
+            println!("Debounce down:          {debounce_down_us} us");
+            println!("Debounce up:            {debounce_up_us} us");
+            println!("Fifo max depth:         {fifo_max_depth}");
+        }
+        Some(MkbpConfigSubcommand::Set {
+            enabled,
+            scan_period_us,
+            poll_timeout_us,
+            min_post_scan_delay_us,
+            output_settle_us,
+            debounce_down_us,
+            debounce_up_us,
+            fifo_max_depth,
+        }) => {
+            mkbp_set_config(
+                &mut file,
+                SetMkbpConfig {
+                    enabled,
+                    scan_period_us,
+                    poll_timeout_us,
+                    min_post_scan_delay_us,
+                    output_settle_us,
+                    debounce_down_us,
+                    debounce_up_us,
+                    fifo_max_depth,
+                },
+            )?;
+            println!("Set MKBP config");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,354 @@
+use std::{fs::File, path::PathBuf};
+
+use clap::Subcommand;
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{
+        get_cmd_versions::{ec_cmd_get_cmd_versions, V3},
+        get_protocol_info::get_protocol_info,
+        motion_sense::{
+            motion_sense_data, motion_sense_dump, motion_sense_ec_rate, motion_sense_fifo_flush,
+            motion_sense_fifo_info, motion_sense_fifo_int_enable, motion_sense_fifo_read,
+            motion_sense_info, motion_sense_kb_wake_angle, motion_sense_lid_angle,
+            motion_sense_perform_calib, motion_sense_sensor_count, motion_sense_sensor_odr,
+            motion_sense_sensor_offset, motion_sense_sensor_range, motion_sense_sensor_scale,
+            motion_sense_spoof, motion_sense_tablet_mode_lid_angle, EcResponseMotionSensorData,
+            MotionSenseOffset, MotionSenseScale, MotionSenseSpoofMode, TabletModeLidAngle,
+            EC_MOTION_SENSE_INVALID_CALIB_TEMP, LID_ANGLE_UNRELIABLE,
+            MOTIONSENSE_MODULE_FLAG_ACTIVE, MOTIONSENSE_SENSOR_FLAG_PRESENT,
+        },
+        CrosEcCmd,
+    },
+    motion_sense_fifo::{
+        LidAngleTracker, MotionSenseFifo, MotionSenseFifoDecoder, MotionSenseSample,
+    },
+    CROS_EC_PATH,
+};
+
+#[derive(Subcommand)]
+pub enum MotionSenseEcSubcommand {
+    /// Prints the latest data of every sensor
+    Dump,
+    /// Prints the type, location, and chip of a sensor
+    Info {
+        sensor: u8,
+    },
+    /// Prints the latest data of a sensor
+    Data {
+        sensor: u8,
+    },
+    /// Prints or sets the rate in ms at which the EC reads a sensor
+    EcRate {
+        sensor: u8,
+        rate_ms: Option<i32>,
+    },
+    /// Prints or sets the output data rate of a sensor in mHz
+    Odr {
+        sensor: u8,
+        odr: Option<i32>,
+        #[arg(long)]
+        roundup: bool,
+    },
+    /// Prints or sets the range of a sensor
+    Range {
+        sensor: u8,
+        range: Option<i32>,
+        #[arg(long)]
+        roundup: bool,
+    },
+    /// Prints or sets the lid angle above which the keyboard doesn't wake the device
+    KbWakeAngle {
+        angle: Option<i16>,
+    },
+    /// Prints the angle between the lid and the base
+    LidAngle,
+    FifoInfo,
+    FifoFlush {
+        sensor: u8,
+    },
+    /// Reads the entries in the FIFO
+    FifoRead,
+    /// Prints or sets whether the EC sends an event when there is new data in the FIFO
+    FifoIntEnable {
+        enable: Option<bool>,
+    },
+    /// Calibrates a sensor. Keep the device still while it is calibrating.
+    Calibrate {
+        sensor: u8,
+    },
+    /// Prints or sets the calibration offset of a sensor
+    Offset {
+        sensor: u8,
+        #[arg(requires_all = ["y", "z"])]
+        x: Option<i16>,
+        y: Option<i16>,
+        z: Option<i16>,
+        /// The temperature at calibration in 1/100 of a degree C
+        #[arg(long)]
+        temp: Option<i16>,
+    },
+    /// Prints or sets the calibration scale of a sensor, where 32768 is a scale of 1
+    Scale {
+        sensor: u8,
+        #[arg(requires_all = ["y", "z"])]
+        x: Option<u16>,
+        y: Option<u16>,
+        z: Option<u16>,
+        /// The temperature at calibration in 1/100 of a degree C
+        #[arg(long)]
+        temp: Option<i16>,
+    },
+    /// Makes a sensor report fake values. The values are only used in custom mode.
+    Spoof {
+        sensor: u8,
+        mode: MotionSenseSpoofMode,
+        #[arg(default_value_t = 0)]
+        x: i16,
+        #[arg(default_value_t = 0)]
+        y: i16,
+        #[arg(default_value_t = 0)]
+        z: i16,
+    },
+    /// Prints or sets the lid angle and hysteresis in degrees for switching to tablet mode
+    TabletModeAngle {
+        #[arg(requires = "hys_degree")]
+        lid_angle: Option<u16>,
+        hys_degree: Option<u16>,
+    },
+    /// Prints samples from the FIFO as they come in, along with the lid angle
+    Watch {
+        /// Stop after no samples come in for this many milliseconds
+        #[arg(short, long)]
+        timeout: Option<i32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MotionSenseSubcommand {
+    #[command(flatten)]
+    Ec(MotionSenseEcSubcommand),
+    /// Decodes a recording of FIFO entries
+    Decode { file: PathBuf },
+}
+
+fn print_sample(sample: &MotionSenseSample) {
+    let MotionSenseSample {
+        sensor_num,
+        timestamp_us,
+        flags,
+        data,
+    } = sample;
+    if sample.is_flush() {
+        println!("[{timestamp_us} us] Sensor {sensor_num}: flush");
+    } else if sample.odr_changed() {
+        println!("[{timestamp_us} us] Sensor {sensor_num}: ODR changed");
+    } else {
+        println!(
+            "[{timestamp_us} us] Sensor {sensor_num}: {}\t{}\t{}\t(flags: {flags:#x})",
+            data[0], data[1], data[2]
+        );
+    }
+}
+
+fn print_sensor_data(sensor_data: &EcResponseMotionSensorData) {
+    let EcResponseMotionSensorData {
+        flags,
+        sensor_num,
+        data,
+    } = *sensor_data;
+    match sensor_data.timestamp() {
+        Some(timestamp) => println!("Timestamp: {timestamp} flags: {flags:#x}"),
+        None => println!(
+            "Sensor {sensor_num}: {}\t{}\t{}\t(flags: {flags:#x})",
+            data[0], data[1], data[2]
+        ),
+    }
+}
+
+pub fn motion_sense_subcommand(command: MotionSenseSubcommand) -> Result<()> {
+    match command {
+        MotionSenseSubcommand::Ec(command) => motion_sense_ec_subcommand(command),
+        // Decoding a recording doesn't need the EC
+        MotionSenseSubcommand::Decode { file: path } => {
+            let recording = std::fs::read(path)?;
+            for sample in MotionSenseFifoDecoder::new().decode_bytes(&recording) {
+                print_sample(&sample);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn motion_sense_ec_subcommand(command: MotionSenseEcSubcommand) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    match command {
+        MotionSenseEcSubcommand::Dump => {
+            let sensor_count = motion_sense_sensor_count(&mut file)?;
+            let dump = motion_sense_dump(&mut file, sensor_count)?;
+            println!(
+                "Motion sensing active: {}",
+                dump.module_flags & MOTIONSENSE_MODULE_FLAG_ACTIVE != 0
+            );
+            for sensor in &dump.sensors {
+                if sensor.flags & MOTIONSENSE_SENSOR_FLAG_PRESENT != 0 {
+                    let data = sensor.data;
+                    println!(
+                        "Sensor {}: {}\t{}\t{}",
+                        sensor.sensor_num, data[0], data[1], data[2]
+                    );
+                } else {
+                    println!("Sensor {}: not present", sensor.sensor_num);
+                }
+            }
+        }
+        MotionSenseEcSubcommand::Info { sensor } => {
+            // Version 3 also has the frequency range
+            let versions = ec_cmd_get_cmd_versions(&mut file, CrosEcCmd::MotionSenseCmd)?;
+            let version = if versions & V3 != 0 { 3 } else { 1 };
+            let info = motion_sense_info(&mut file, version, sensor)?;
+            println!("Type:     {:?}", info.sensor_type);
+            println!("Location: {:?}", info.location);
+            println!("Chip:     {:?}", info.chip);
+            if let Some(frequencies) = info.frequencies {
+                println!("Min frequency:        {} mHz", frequencies.min_frequency);
+                println!("Max frequency:        {} mHz", frequencies.max_frequency);
+                println!("FIFO max event count: {}", frequencies.fifo_max_event_count);
+            }
+        }
+        MotionSenseEcSubcommand::Data { sensor } => {
+            print_sensor_data(&motion_sense_data(&mut file, sensor)?);
+        }
+        MotionSenseEcSubcommand::EcRate { sensor, rate_ms } => {
+            let rate_ms = motion_sense_ec_rate(&mut file, sensor, rate_ms)?;
+            println!("EC rate: {rate_ms} ms");
+        }
+        MotionSenseEcSubcommand::Odr {
+            sensor,
+            odr,
+            roundup,
+        } => {
+            let odr = motion_sense_sensor_odr(&mut file, sensor, odr, roundup)?;
+            println!("ODR: {odr} mHz");
+        }
+        MotionSenseEcSubcommand::Range {
+            sensor,
+            range,
+            roundup,
+        } => {
+            let range = motion_sense_sensor_range(&mut file, sensor, range, roundup)?;
+            println!("Range: {range}");
+        }
+        MotionSenseEcSubcommand::KbWakeAngle { angle } => {
+            let angle = motion_sense_kb_wake_angle(&mut file, angle)?;
+            println!("Keyboard wake angle: {angle}");
+        }
+        MotionSenseEcSubcommand::LidAngle => match motion_sense_lid_angle(&mut file)? {
+            LID_ANGLE_UNRELIABLE => println!("Lid angle: unreliable"),
+            angle => println!("Lid angle: {angle}"),
+        },
+        MotionSenseEcSubcommand::FifoInfo => {
+            let sensor_count = motion_sense_sensor_count(&mut file)?;
+            let fifo_info = motion_sense_fifo_info(&mut file, sensor_count)?;
+            println!("{fifo_info:#?}");
+        }
+        MotionSenseEcSubcommand::FifoFlush { sensor } => {
+            let sensor_count = motion_sense_sensor_count(&mut file)?;
+            let fifo_info = motion_sense_fifo_flush(&mut file, sensor, sensor_count)?;
+            println!("{fifo_info:#?}");
+        }
+        MotionSenseEcSubcommand::FifoRead => {
+            let protocol_info = get_protocol_info(&mut file)?;
+            for data in motion_sense_fifo_read(&mut file, &protocol_info)? {
+                print_sensor_data(&data);
+            }
+        }
+        MotionSenseEcSubcommand::FifoIntEnable { enable } => {
+            let enabled = motion_sense_fifo_int_enable(&mut file, enable)?;
+            println!("FIFO interrupt enabled: {enabled}");
+        }
+        MotionSenseEcSubcommand::Calibrate { sensor } => {
+            let offset = motion_sense_perform_calib(&mut file, sensor, true)?;
+            println!("{offset:#?}");
+        }
+        MotionSenseEcSubcommand::Offset {
+            sensor,
+            x,
+            y,
+            z,
+            temp,
+        } => {
+            let offset = match (x, y, z) {
+                (Some(x), Some(y), Some(z)) => Some(MotionSenseOffset {
+                    temp: temp.unwrap_or(EC_MOTION_SENSE_INVALID_CALIB_TEMP),
+                    offset: [x, y, z],
+                }),
+                _ => None,
+            };
+            let offset = motion_sense_sensor_offset(&mut file, sensor, offset)?;
+            println!("{offset:#?}");
+        }
+        MotionSenseEcSubcommand::Scale {
+            sensor,
+            x,
+            y,
+            z,
+            temp,
+        } => {
+            let scale = match (x, y, z) {
+                (Some(x), Some(y), Some(z)) => Some(MotionSenseScale {
+                    temp: temp.unwrap_or(EC_MOTION_SENSE_INVALID_CALIB_TEMP),
+                    scale: [x, y, z],
+                }),
+                _ => None,
+            };
+            let scale = motion_sense_sensor_scale(&mut file, sensor, scale)?;
+            println!("{scale:#?}");
+        }
+        MotionSenseEcSubcommand::Spoof {
+            sensor,
+            mode,
+            x,
+            y,
+            z,
+        } => {
+            let enabled = motion_sense_spoof(&mut file, sensor, mode, [x, y, z])?;
+            if let MotionSenseSpoofMode::Query = mode {
+                println!("Spoofing enabled: {enabled}");
+            }
+        }
+        MotionSenseEcSubcommand::TabletModeAngle {
+            lid_angle,
+            hys_degree,
+        } => {
+            let threshold = match (lid_angle, hys_degree) {
+                (Some(lid_angle), Some(hys_degree)) => Some(TabletModeLidAngle {
+                    lid_angle,
+                    hys_degree,
+                }),
+                _ => None,
+            };
+            let TabletModeLidAngle {
+                lid_angle,
+                hys_degree,
+            } = motion_sense_tablet_mode_lid_angle(&mut file, threshold)?;
+            println!("Tablet mode lid angle: {lid_angle}");
+            println!("Hysteresis:            {hys_degree}");
+        }
+        MotionSenseEcSubcommand::Watch { timeout } => {
+            let mut lid_angle_tracker = LidAngleTracker::from_sensors(&mut file)?;
+            let mut fifo = MotionSenseFifo::new(&mut file, timeout)?;
+            for sample in fifo.by_ref() {
+                let sample = sample?;
+                print_sample(&sample);
+                if let Some(angle) = lid_angle_tracker
+                    .as_mut()
+                    .and_then(|tracker| tracker.update(&sample))
+                {
+                    println!("Lid angle: {angle:.0}");
+                }
+            }
+            println!("Lost samples: {:?}", fifo.lost());
+        }
+    }
+    Ok(())
+}
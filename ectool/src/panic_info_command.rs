@@ -0,0 +1,21 @@
+use std::{fs::File, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::get_panic_info::ec_cmd_get_panic_info, panic_info::PanicInfo, CROS_EC_PATH,
+};
+
+pub fn panic_info_command(file: Option<PathBuf>, raw: bool) -> Result<()> {
+    let data = match file {
+        Some(file) => std::fs::read(file)?,
+        None => ec_cmd_get_panic_info(&mut File::open(CROS_EC_PATH)?)?,
+    };
+    if raw {
+        println!("{}", hex::encode(&data));
+    }
+    match PanicInfo::parse(&data)? {
+        Some(panic_info) => print!("{panic_info}"),
+        None => println!("No panic info"),
+    }
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use std::num::ParseIntError;
+
+use num_traits::Num;
+
+/// Parses a number in decimal, or in hex if it starts with 0x
+pub fn parse_number<T: Num<FromStrRadixErr = ParseIntError>>(s: &str) -> Result<T, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => T::from_str_radix(hex, 16),
+        None => T::from_str_radix(s, 10),
+    }
+    .map_err(|e| e.to_string())
+}
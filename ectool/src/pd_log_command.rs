@@ -0,0 +1,82 @@
+use std::fs::File;
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{
+        get_uptime_info::ec_cmd_get_uptime_info,
+        pd_log::{pd_get_log, PdLogEntry, PdLogEvent},
+    },
+    pd_log::PdLogCollector,
+    CROS_EC_PATH,
+};
+use uom::si::{electric_current::milliampere, electric_potential::millivolt};
+
+fn print_entry(entry: &PdLogEntry, uptime_ms: u32) {
+    match entry.time_since_ec_boot_ms(uptime_ms) {
+        Some(ms) => print!("{}.{:03} P{} ", ms / 1000, ms % 1000, entry.port),
+        None => print!("{:.3}s ago P{} ", entry.age.as_secs_f32(), entry.port),
+    }
+    match &entry.event {
+        PdLogEvent::Charge(charge) => {
+            if charge.override_port {
+                print!("override ");
+            }
+            if charge.delayed_override {
+                print!("pending_override ");
+            }
+            print!("{:?} {:?}", charge.role, charge.charger_type);
+            if charge.dualrole {
+                print!(" DRP");
+            }
+            println!(
+                " {}mV max {}mV / {}mA",
+                charge.voltage_now.get::<millivolt>(),
+                charge.voltage_max.get::<millivolt>(),
+                charge.current_max.get::<milliampere>()
+            );
+        }
+        PdLogEvent::Connect => println!("New connection"),
+        PdLogEvent::BoardCustom => println!("Board custom event"),
+        PdLogEvent::AccessoryRwFail => println!("RW signature check failed"),
+        PdLogEvent::AccessoryRwErase => println!("RW erased"),
+        PdLogEvent::PowerSupplyFault(fault) => println!("Power supply fault: {fault:?}"),
+        PdLogEvent::DpMode(enabled) => {
+            println!("DP mode {}", if *enabled { "enabled" } else { "disabled" })
+        }
+        PdLogEvent::VideoCodec {
+            family,
+            chip_id,
+            irom,
+            fw,
+        } => println!(
+            "HDMI info: family: {family:04x} chip ID: {chip_id:04x} irom: {}.{}.{} fw: {}.{}.{}",
+            irom.0, irom.1, irom.2, fw.0, fw.1, fw.2
+        ),
+        PdLogEvent::Unknown {
+            event_type,
+            data,
+            payload,
+        } => println!("Event {event_type:02x} ({data:04x}) {payload:02x?}"),
+    }
+}
+
+pub fn pd_log_command(follow: bool) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    if follow {
+        // The collector borrows the file, so the uptime is read with another one
+        let mut uptime_file = File::open(CROS_EC_PATH)?;
+        for entry in PdLogCollector::new(&mut file, None) {
+            let entry = entry?;
+            let uptime_ms = ec_cmd_get_uptime_info(&mut uptime_file)?.time_since_ec_boot_ms;
+            print_entry(&entry, uptime_ms);
+        }
+    } else {
+        let entries = pd_get_log(&mut file)?;
+        let uptime_ms = ec_cmd_get_uptime_info(&mut file)?.time_since_ec_boot_ms;
+        for entry in &entries {
+            print_entry(entry, uptime_ms);
+        }
+        println!("--- END OF LOG ---");
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::{fs::File, time::Duration};
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{
+        hibernation_delay::hibernation_delay,
+        reboot_ap_on_g3::reboot_ap_on_g3,
+        reboot_ec::{reboot_ec, RebootEcCommand, RebootEcFlag},
+    },
+    reboot::reboot_ec_and_wait,
+    CROS_EC_PATH,
+};
+
+/// How long to wait for the EC to be ready after rebooting
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn reboot_ec_command(
+    command: RebootEcCommand,
+    flags: Vec<RebootEcFlag>,
+    wait: bool,
+) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let flags = flags.into_iter().collect();
+    if wait {
+        reboot_ec_and_wait(&mut file, command, flags, READY_TIMEOUT)?;
+        println!("EC is ready");
+    } else {
+        reboot_ec(&mut file, command, flags)?;
+    }
+    Ok(())
+}
+
+pub fn reboot_ap_on_g3_command(delay: Option<u64>) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    reboot_ap_on_g3(&mut file, delay.map(Duration::from_secs))?;
+    Ok(())
+}
+
+pub fn hibernation_delay_command(seconds: Option<u64>) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let delay = hibernation_delay(&mut file, seconds.map(Duration::from_secs))?;
+    println!("Time in G3:      {}s", delay.time_g3.as_secs());
+    println!("Time remaining:  {}s", delay.time_remaining.as_secs());
+    println!("Hibernate delay: {}s", delay.hibernate_delay.as_secs());
+    Ok(())
+}
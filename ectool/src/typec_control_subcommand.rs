@@ -0,0 +1,97 @@
+use std::fs::File;
+
+use clap::Subcommand;
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{
+        typec_control::{
+            typec_bist_share_mode, typec_clear_events, typec_enter_mode, typec_exit_modes,
+            typec_send_vdm_req, typec_tbt_ufp_reply, typec_usb_mux_set, typec_vdm_response,
+            TypecMode, TypecTbtUfpReply, TYPEC_USB_MUX_SET_ALL_CHIPS,
+        },
+        usb_pd::TypecPartnerType,
+    },
+    CROS_EC_PATH,
+};
+
+use crate::parse_number::parse_number;
+
+#[derive(Subcommand)]
+pub enum TypecControlSubcommand {
+    /// Exits all alternate modes
+    ExitModes,
+    /// Clears events from the Type-C status
+    ClearEvents {
+        #[arg(value_parser = parse_number::<u32>)]
+        mask: u32,
+    },
+    EnterMode {
+        mode: TypecMode,
+    },
+    /// Replies to a Thunderbolt mode entry request
+    TbtUfpReply {
+        reply: TypecTbtUfpReply,
+    },
+    /// Sets the USB mux state
+    MuxSet {
+        /// A `USB_PD_MUX_*` bitmask
+        #[arg(value_parser = parse_number::<u8>)]
+        mux_flags: u8,
+        /// The index of the mux in the chain. All muxes are set if this isn't specified.
+        #[arg(long)]
+        index: Option<u8>,
+    },
+    BistShareMode {
+        enable: bool,
+    },
+    /// Sends a VDM and prints the response
+    SendVdm {
+        partner_type: TypecPartnerType,
+        /// The VDM header followed by the data objects
+        #[arg(value_parser = parse_number::<u32>, required = true, num_args = 1..=7)]
+        vdos: Vec<u32>,
+    },
+    /// Prints the response to the last VDM and the oldest Attention message
+    VdmResponse,
+}
+
+pub fn typec_control_subcommand(port: u8, command: TypecControlSubcommand) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    match command {
+        TypecControlSubcommand::ExitModes => typec_exit_modes(&mut file, port)?,
+        TypecControlSubcommand::ClearEvents { mask } => typec_clear_events(&mut file, port, mask)?,
+        TypecControlSubcommand::EnterMode { mode } => typec_enter_mode(&mut file, port, mode)?,
+        TypecControlSubcommand::TbtUfpReply { reply } => {
+            typec_tbt_ufp_reply(&mut file, port, reply)?
+        }
+        TypecControlSubcommand::MuxSet { mux_flags, index } => typec_usb_mux_set(
+            &mut file,
+            port,
+            index.unwrap_or(TYPEC_USB_MUX_SET_ALL_CHIPS),
+            mux_flags,
+        )?,
+        TypecControlSubcommand::BistShareMode { enable } => {
+            typec_bist_share_mode(&mut file, port, enable)?
+        }
+        TypecControlSubcommand::SendVdm { partner_type, vdos } => {
+            typec_send_vdm_req(&mut file, port, partner_type, &vdos)?;
+            println!("Sent VDM");
+        }
+        TypecControlSubcommand::VdmResponse => {
+            let response = typec_vdm_response(&mut file, port)?;
+            println!("Result: {:?}", response.result);
+            println!("Response:");
+            for vdo in response.response {
+                println!("  {vdo:#010x}");
+            }
+            if !response.attention.is_empty() {
+                println!("Attention:");
+                for vdo in response.attention {
+                    println!("  {vdo:#010x}");
+                }
+                println!("{} more Attention messages queued", response.attention_left);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use std::{path::PathBuf, time::Duration};
+
+use color_eyre::eyre::Result;
+use crosec::uart_console::UartConsole;
+
+pub fn uart_console_command(port: PathBuf, command: Vec<String>, timeout: u64) -> Result<()> {
+    let mut console = UartConsole::open(port, Duration::from_millis(timeout))?;
+    let output = console.run(&command.join(" "))?;
+    print!("{output}");
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::fs::File;
+
+use color_eyre::eyre::Result;
+use crosec::{
+    commands::{
+        get_cmd_versions::{ec_cmd_get_cmd_versions, V1, V2},
+        get_protocol_info::get_protocol_info,
+        usb_pd::{
+            dp_pin_assignment, svid_name, typec_discovery, typec_status, usb_pd_control,
+            usb_pd_ports, usb_pd_power_info, TypecPartnerType, UsbPdControlMux, UsbPdControlRole,
+            UsbPdControlSwap, UsbPdState, PD_CTRL_RESP_ENABLED_COMMS,
+            PD_CTRL_RESP_ENABLED_CONNECTED, PD_CTRL_RESP_ENABLED_PD_CAPABLE,
+            PD_CTRL_RESP_ROLE_DATA, PD_CTRL_RESP_ROLE_DR_DATA, PD_CTRL_RESP_ROLE_DR_POWER,
+            PD_CTRL_RESP_ROLE_POWER, PD_CTRL_RESP_ROLE_UNCONSTRAINED, PD_CTRL_RESP_ROLE_USB_COMM,
+            PD_CTRL_RESP_ROLE_VCONN,
+        },
+        CrosEcCmd,
+    },
+    CROS_EC_PATH,
+};
+use uom::si::{electric_current::milliampere, electric_potential::millivolt, power::milliwatt};
+
+pub fn usb_pd_command(
+    port: u8,
+    role: UsbPdControlRole,
+    mux: UsbPdControlMux,
+    swap: UsbPdControlSwap,
+) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    // Use the latest version, so the request is only sent once
+    let versions = ec_cmd_get_cmd_versions(&mut file, CrosEcCmd::UsbPdControl)?;
+    let version = if versions & V2 != 0 {
+        2
+    } else if versions & V1 != 0 {
+        1
+    } else {
+        0
+    };
+    let status = usb_pd_control(&mut file, version, port, role, mux, swap)?;
+    let flag = |flags: u8, flag: u8| flags & flag != 0;
+    println!(
+        "Port C{port} is {}, {}",
+        if flag(status.enabled, PD_CTRL_RESP_ENABLED_COMMS) {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        if flag(status.enabled, PD_CTRL_RESP_ENABLED_CONNECTED) {
+            "connected"
+        } else {
+            "disconnected"
+        }
+    );
+    println!(
+        "PD capable partner: {}",
+        flag(status.enabled, PD_CTRL_RESP_ENABLED_PD_CAPABLE)
+    );
+    println!(
+        "Role: {}-{}{}",
+        if flag(status.role, PD_CTRL_RESP_ROLE_POWER) {
+            "SRC"
+        } else {
+            "SNK"
+        },
+        if flag(status.role, PD_CTRL_RESP_ROLE_DATA) {
+            "DFP"
+        } else {
+            "UFP"
+        },
+        if flag(status.role, PD_CTRL_RESP_ROLE_VCONN) {
+            " VCONN"
+        } else {
+            ""
+        }
+    );
+    println!("Polarity: CC{}", status.polarity + 1);
+    match status.state {
+        UsbPdState::Number(state) => println!("State: {state}"),
+        UsbPdState::Name(state) => println!("State: {state}"),
+    }
+    for (role_flag, name) in [
+        (PD_CTRL_RESP_ROLE_DR_POWER, "Partner is dual role power"),
+        (PD_CTRL_RESP_ROLE_DR_DATA, "Partner is dual role data"),
+        (PD_CTRL_RESP_ROLE_USB_COMM, "Partner is USB comms capable"),
+        (
+            PD_CTRL_RESP_ROLE_UNCONSTRAINED,
+            "Partner has unconstrained power",
+        ),
+    ] {
+        if flag(status.role, role_flag) {
+            println!("{name}");
+        }
+    }
+    if let Some(cable_info) = status.cable_info {
+        println!("CC state: {:?}", cable_info.cc_state);
+        if let Some(pin) = dp_pin_assignment(cable_info.dp_mode) {
+            println!("DP pin mode: {pin}");
+        }
+        println!("Cable flags: {:#04x}", cable_info.control_flags);
+        println!("TBT cable speed: {}", cable_info.cable_speed);
+        println!("TBT cable gen: {}", cable_info.cable_gen);
+    }
+    Ok(())
+}
+
+pub fn usb_pd_power_command() -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    for port in 0..usb_pd_ports(&mut file)? {
+        let info = usb_pd_power_info(&mut file, port)?;
+        print!("Port {port}: {:?}", info.role);
+        if info.dualrole {
+            print!(" (dual role)");
+        }
+        println!();
+        println!("  Charger type: {:?}", info.charger_type);
+        println!(
+            "  {}mV / {}mA, max {}mV / {}mA / {}mW",
+            info.voltage_now.get::<millivolt>(),
+            info.current_lim.get::<milliampere>(),
+            info.voltage_max.get::<millivolt>(),
+            info.current_max.get::<milliampere>(),
+            info.max_power.get::<milliwatt>()
+        );
+    }
+    Ok(())
+}
+
+pub fn typec_status_command(port: u8) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let status = typec_status(&mut file, port)?;
+    println!("Port C{port}: {}", status.tc_state());
+    println!("  PD enabled:    {}", status.pd_enabled != 0);
+    println!("  Connected:     {}", status.dev_connected != 0);
+    println!("  SOP capable:   {}", status.sop_connected != 0);
+    println!("  Power role:    {:?}", status.power_role());
+    println!("  Data role:     {:?}", status.data_role());
+    println!("  VCONN role:    {:?}", status.vconn_role());
+    println!("  Polarity:      {:?}", status.polarity());
+    println!("  CC state:      {:?}", status.cc_state());
+    if let Some(pin) = dp_pin_assignment(status.dp_pin) {
+        println!("  DP pin mode:   {pin}");
+    }
+    println!("  MUX state:     {:#04x}", status.mux_state);
+    println!("  Events:        {:#x}", { status.events });
+    println!(
+        "  SOP revision:  {:x}.{:02x}",
+        status.sop_revision >> 8,
+        status.sop_revision & 0xff
+    );
+    println!(
+        "  SOP' revision: {:x}.{:02x}",
+        status.sop_prime_revision >> 8,
+        status.sop_prime_revision & 0xff
+    );
+    println!("  Source caps:");
+    for pdo in status.source_caps() {
+        println!("    {pdo}");
+    }
+    println!("  Sink caps:");
+    for pdo in status.sink_caps() {
+        println!("    {pdo}");
+    }
+    Ok(())
+}
+
+pub fn typec_discovery_command(port: u8, partner_type: TypecPartnerType) -> Result<()> {
+    let mut file = File::open(CROS_EC_PATH)?;
+    let protocol_info = get_protocol_info(&mut file)?;
+    let discovery = typec_discovery(&mut file, port, partner_type, &protocol_info)?;
+    println!("Identity VDOs:");
+    for vdo in discovery.identity_vdos {
+        println!("  {vdo:#010x}");
+    }
+    for svid in discovery.svids {
+        match svid_name(svid.svid) {
+            Some(name) => println!("SVID {:#06x} ({name}):", svid.svid),
+            None => println!("SVID {:#06x}:", svid.svid),
+        }
+        for vdo in svid.mode_vdos {
+            println!("  {vdo:#010x}");
+        }
+    }
+    Ok(())
+}